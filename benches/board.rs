@@ -0,0 +1,92 @@
+//! Compares the map-based `Board` with `CompactBoard` on the operations
+//! a search bot runs for every position it looks at.
+//!
+//! Run with `cargo bench --bench board`.
+
+#[macro_use]
+extern crate criterion;
+#[macro_use]
+extern crate parcheesi;
+
+use criterion::{black_box, Criterion};
+use parcheesi::board::{Board, Color, Loc, Pawn};
+use parcheesi::compactboard::CompactBoard;
+use parcheesi::game::{Move, MoveType};
+use parcheesi::rules::RuleSet;
+
+/// A mid-game position with a blockade and a pawn to bop.
+fn mid_game() -> Board {
+    Board::from(map!{
+        Color::Red => [Loc::Spot { index: 10 }, Loc::Spot { index: 30 },
+                       Loc::Spot { index: 30 }, Loc::Nest],
+        Color::Blue => [Loc::Spot { index: 14 }, Loc::Spot { index: 202 },
+                        Loc::Nest, Loc::Home],
+        Color::Yellow => [Loc::Spot { index: 60 }, Loc::Nest,
+                          Loc::Spot { index: 38 }, Loc::Nest],
+        Color::Green => [Loc::Spot { index: 2 }, Loc::Spot { index: 40 },
+                         Loc::Home, Loc::Home]
+    })
+}
+
+/// Red's pawn at 10 moving 4 spaces to bop Blue's pawn at 14.
+fn bop_move() -> Move {
+    Move {
+        pawn: Pawn::new(0, Color::Red),
+        m_type: MoveType::MoveMain {
+            start: 10,
+            distance: 4,
+        },
+    }
+}
+
+fn handle_move(c: &mut Criterion) {
+    let board: Board = mid_game();
+    let compact: CompactBoard = CompactBoard::from_board(&board);
+    let rules: RuleSet = RuleSet::standard();
+
+    let mut group = c.benchmark_group("handle_move");
+    group.bench_function("Board", |b| {
+        b.iter(|| black_box(&board).handle_move_with(bop_move(), &rules))
+    });
+    group.bench_function("CompactBoard", |b| {
+        b.iter(|| {
+                   let mut next: CompactBoard = *black_box(&compact);
+                   next.apply_move(bop_move(), &rules)
+               })
+    });
+    group.finish();
+}
+
+fn get_blockades(c: &mut Criterion) {
+    let board: Board = mid_game();
+    let compact: CompactBoard = CompactBoard::from_board(&board);
+    let blockade: Loc = Loc::Spot { index: 30 };
+
+    let mut group = c.benchmark_group("is_blockade");
+    group.bench_function("Board", |b| {
+        b.iter(|| black_box(&board).get_blockades().contains(&blockade))
+    });
+    group.bench_function("CompactBoard", |b| {
+        b.iter(|| black_box(&compact).is_blockade(blockade))
+    });
+    group.finish();
+}
+
+fn can_bop(c: &mut Criterion) {
+    let board: Board = mid_game();
+    let compact: CompactBoard = CompactBoard::from_board(&board);
+    let rules: RuleSet = RuleSet::standard();
+    let dest: Loc = Loc::Spot { index: 14 };
+
+    let mut group = c.benchmark_group("can_bop");
+    group.bench_function("Board", |b| {
+        b.iter(|| black_box(&board).can_bop_with(Color::Red, dest, &rules))
+    });
+    group.bench_function("CompactBoard", |b| {
+        b.iter(|| black_box(&compact).can_bop_with(Color::Red, dest, &rules))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, handle_move, get_blockades, can_bop);
+criterion_main!(benches);
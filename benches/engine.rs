@@ -0,0 +1,218 @@
+//! Measures the rule engine on a set of representative mid-game
+//! positions: applying and checking moves, finding blockades, ordering
+//! pawns along their path, listing moves and complete turns, and the
+//! XML the network protocol sends every turn.
+//!
+//! Run with `cargo bench --bench engine`.
+
+#[macro_use]
+extern crate criterion;
+#[macro_use]
+extern crate parcheesi;
+
+use criterion::{black_box, BenchmarkId, Criterion};
+use parcheesi::board::{Board, Color, Loc};
+use parcheesi::deserialize;
+use parcheesi::dice::Dice;
+use parcheesi::game::Move;
+use parcheesi::gametree::{self, GameTree};
+use parcheesi::rules::RuleSet;
+use parcheesi::serialize;
+use parcheesi::zobrist::TranspositionTable;
+
+/// A position to benchmark: the board, the player to move and their dice.
+struct Position {
+    name: &'static str,
+    board: Board,
+    color: Color,
+    dice: Dice,
+}
+
+impl Position {
+    /// Every move the player to move can make with their dice.
+    fn moves(&self) -> Vec<Move> {
+        GameTree::new(self.board.clone(), self.dice.clone(), self.color).collect()
+    }
+}
+
+fn positions() -> Vec<Position> {
+    vec![Position {
+             // Pawns spread around the ring, a blockade and a pawn to bop.
+             name: "open",
+             board: Board::from(map!{
+                 Color::Red => [Loc::Spot { index: 10 }, Loc::Spot { index: 30 },
+                                Loc::Spot { index: 30 }, Loc::Nest],
+                 Color::Blue => [Loc::Spot { index: 14 }, Loc::Spot { index: 202 },
+                                 Loc::Nest, Loc::Home],
+                 Color::Yellow => [Loc::Spot { index: 60 }, Loc::Nest,
+                                   Loc::Spot { index: 38 }, Loc::Nest],
+                 Color::Green => [Loc::Spot { index: 2 }, Loc::Spot { index: 40 },
+                                  Loc::Home, Loc::Home]
+             }),
+             color: Color::Red,
+             dice: Dice { rolls: vec![4, 6] },
+         },
+         Position {
+             // Blockades from three colors hemming pawns in.
+             name: "crowded",
+             board: Board::from(map!{
+                 Color::Red => [Loc::Spot { index: 20 }, Loc::Spot { index: 20 },
+                                Loc::Spot { index: 24 }, Loc::Spot { index: 5 }],
+                 Color::Blue => [Loc::Spot { index: 26 }, Loc::Spot { index: 26 },
+                                 Loc::Spot { index: 23 }, Loc::Nest],
+                 Color::Yellow => [Loc::Spot { index: 38 }, Loc::Spot { index: 38 },
+                                   Loc::Spot { index: 40 }, Loc::Nest],
+                 Color::Green => [Loc::Spot { index: 55 }, Loc::Spot { index: 8 },
+                                  Loc::Nest, Loc::Nest]
+             }),
+             color: Color::Red,
+             dice: Dice { rolls: vec![3, 1] },
+         },
+         Position {
+             // All of Blue's pawns out with doubles, so four dice to play.
+             name: "doubles",
+             board: Board::from(map!{
+                 Color::Red => [Loc::Spot { index: 45 }, Loc::Spot { index: 52 },
+                                Loc::Spot { index: 104 }, Loc::Nest],
+                 Color::Blue => [Loc::Spot { index: 30 }, Loc::Spot { index: 41 },
+                                 Loc::Spot { index: 66 }, Loc::Spot { index: 5 }],
+                 Color::Yellow => [Loc::Spot { index: 60 }, Loc::Spot { index: 302 },
+                                   Loc::Home, Loc::Nest],
+                 Color::Green => [Loc::Spot { index: 12 }, Loc::Spot { index: 403 },
+                                  Loc::Nest, Loc::Home]
+             }),
+             color: Color::Blue,
+             dice: Dice { rolls: vec![3, 3, 4, 4] },
+         },
+         Position {
+             // Late in the game, with pawns in and near the home rows.
+             name: "endgame",
+             board: Board::from(map!{
+                 Color::Red => [Loc::Spot { index: 101 }, Loc::Spot { index: 64 },
+                                Loc::Home, Loc::Home],
+                 Color::Blue => [Loc::Spot { index: 204 }, Loc::Spot { index: 12 },
+                                 Loc::Spot { index: 12 }, Loc::Home],
+                 Color::Yellow => [Loc::Spot { index: 306 }, Loc::Home,
+                                   Loc::Home, Loc::Home],
+                 Color::Green => [Loc::Spot { index: 47 }, Loc::Spot { index: 400 },
+                                  Loc::Spot { index: 405 }, Loc::Home]
+             }),
+             color: Color::Red,
+             dice: Dice { rolls: vec![2, 5] },
+         }]
+}
+
+fn handle_move(c: &mut Criterion) {
+    let rules: RuleSet = RuleSet::standard();
+    let mut group = c.benchmark_group("handle_move");
+    for position in positions() {
+        let moves: Vec<Move> = position.moves();
+        group.bench_with_input(BenchmarkId::from_parameter(position.name),
+                               &position,
+                               |b, p| {
+            b.iter(|| for &m in moves.iter() {
+                       black_box(p.board.handle_move_with(m, &rules)).ok();
+                   })
+        });
+    }
+    group.finish();
+}
+
+fn is_valid_move(c: &mut Criterion) {
+    let rules: RuleSet = RuleSet::standard();
+    let mut group = c.benchmark_group("is_valid_move");
+    for position in positions() {
+        let moves: Vec<Move> = position.moves();
+        group.bench_with_input(BenchmarkId::from_parameter(position.name),
+                               &position,
+                               |b, p| {
+            b.iter(|| {
+                       moves.iter()
+                           .filter(|m| Board::is_valid_move_with(&p.board, &p.dice, m, &rules))
+                           .count()
+                   })
+        });
+    }
+    group.finish();
+}
+
+fn get_blockades(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_blockades");
+    for position in positions() {
+        group.bench_with_input(BenchmarkId::from_parameter(position.name),
+                               &position,
+                               |b, p| b.iter(|| black_box(&p.board).get_blockades()));
+    }
+    group.finish();
+}
+
+fn sort_player_locs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sort_player_locs");
+    for position in positions() {
+        group.bench_with_input(BenchmarkId::from_parameter(position.name),
+                               &position,
+                               |b, p| {
+            b.iter(|| for (clr, &locs) in p.board.positions.iter() {
+                       black_box(Board::sort_player_locs(clr, locs));
+                   })
+        });
+    }
+    group.finish();
+}
+
+fn game_tree(c: &mut Criterion) {
+    let rules: RuleSet = RuleSet::standard();
+
+    let mut group = c.benchmark_group("game_tree");
+    for position in positions() {
+        group.bench_with_input(BenchmarkId::new("moves", position.name),
+                               &position,
+                               |b, p| {
+            b.iter(|| {
+                       GameTree::new(p.board.clone(), p.dice.clone(), p.color)
+                           .with_rules(rules)
+                           .count()
+                   })
+        });
+        group.bench_with_input(BenchmarkId::new("turn_outcomes", position.name),
+                               &position,
+                               |b, p| {
+            let mut table: TranspositionTable<()> = TranspositionTable::new(1 << 12);
+            b.iter(|| {
+                       table.clear();
+                       gametree::turn_outcomes(&p.board, &p.dice, p.color, &rules, &mut table)
+                   })
+        });
+    }
+    group.finish();
+}
+
+fn xml_round_trip(c: &mut Criterion) {
+    let mut group = c.benchmark_group("xml");
+    for position in positions() {
+        let do_move: String = serialize::xml_do_move(&position.board, &position.dice);
+        let moves: String = serialize::xml_moves(&position.moves());
+
+        group.bench_with_input(BenchmarkId::new("do_move", position.name),
+                               &position,
+                               |b, p| {
+            b.iter(|| deserialize::deserialize_do_move(serialize::xml_do_move(&p.board, &p.dice)))
+        });
+        group.bench_with_input(BenchmarkId::new("parse_do_move", position.name),
+                               &do_move,
+                               |b, xml| b.iter(|| deserialize::deserialize_do_move(xml.clone())));
+        group.bench_with_input(BenchmarkId::new("moves", position.name),
+                               &moves,
+                               |b, xml| b.iter(|| deserialize::deserialize_moves(xml.clone())));
+    }
+    group.finish();
+}
+
+criterion_group!(benches,
+                 handle_move,
+                 is_valid_move,
+                 get_blockades,
+                 sort_player_locs,
+                 game_tree,
+                 xml_round_trip);
+criterion_main!(benches);
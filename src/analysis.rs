@@ -0,0 +1,328 @@
+use super::board::{Board, Color, Loc, Path};
+use super::dice::Dice;
+use super::game::Move;
+use super::gametree;
+use super::probability;
+use super::rules::RuleSet;
+use super::zobrist::TranspositionTable;
+
+/// Scores a board from one player's point of view. Higher is better.
+pub trait Evaluator {
+    fn evaluate(&self, board: &Board, color: &Color) -> f64;
+}
+
+impl<F> Evaluator for F
+    where F: Fn(&Board, &Color) -> f64
+{
+    fn evaluate(&self, board: &Board, color: &Color) -> f64 {
+        self(board, color)
+    }
+}
+
+/// Scores a board by how far the player's pawns have travelled along
+/// their path, minus how far the average opponent's pawns have.
+pub struct Progress;
+
+impl Evaluator for Progress {
+    fn evaluate(&self, board: &Board, color: &Color) -> f64 {
+        let mut own: f64 = 0.0;
+        let mut opponents: f64 = 0.0;
+        let mut num_opponents: usize = 0;
+
+        for (clr, locs) in board.positions.iter() {
+            let travelled: usize = locs.iter().map(|&loc| progress(clr, loc)).sum();
+            if clr == color {
+                own = travelled as f64;
+            } else {
+                opponents += travelled as f64;
+                num_opponents += 1;
+            }
+        }
+
+        if num_opponents > 0 {
+            own - opponents / num_opponents as f64
+        } else {
+            own
+        }
+    }
+}
+
+/// Scores a board like `Progress`, less the progress each of the
+/// player's pawns stands to lose if it's bopped before the player's
+/// next turn.
+pub struct Safety {
+    pub rules: RuleSet,
+}
+
+impl Evaluator for Safety {
+    fn evaluate(&self, board: &Board, color: &Color) -> f64 {
+        let chances: [f64; 4] = probability::chance_bopped_with(board, color, &self.rules);
+        let at_risk: f64 = board
+            .get_pawns_by_color(color)
+            .iter()
+            .enumerate()
+            .map(|(id, &loc)| chances[id] * progress(color, loc) as f64)
+            .sum();
+        Progress.evaluate(board, color) - at_risk
+    }
+}
+
+/// How many steps along its path a pawn at the given location has
+/// taken, from 0 in the nest up to the length of the path at home.
+pub fn progress(color: &Color, loc: Loc) -> usize {
+    Path::new(*color)
+        .position(|l| l == loc)
+        .unwrap_or(0)
+}
+
+/// A complete turn a player could take, with the board it leads to.
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    pub moves: Vec<Move>,
+    pub board: Board,
+    pub score: f64,
+}
+
+/// Ranks the complete turns a player can take.
+///
+/// At depth 1 each turn is scored by evaluating the board it leads to.
+/// Deeper searches also play out the following players' turns, averaging
+/// over their dice rolls, with each of them taking the turn that
+/// evaluates best for themselves.
+pub struct Analyzer {
+    rules: RuleSet,
+    evaluator: Box<Evaluator>,
+    depth: usize,
+}
+
+impl Analyzer {
+    /// An analyzer using the standard rules and the `Progress`
+    /// evaluator, looking one turn ahead.
+    pub fn new() -> Analyzer {
+        Analyzer {
+            rules: RuleSet::standard(),
+            evaluator: Box::new(Progress),
+            depth: 1,
+        }
+    }
+
+    /// Analyze under the given rules instead of the standard ones.
+    pub fn with_rules(mut self, rules: RuleSet) -> Analyzer {
+        self.rules = rules;
+        self
+    }
+
+    /// Score boards with the given evaluator.
+    pub fn with_evaluator(mut self, evaluator: Box<Evaluator>) -> Analyzer {
+        self.evaluator = evaluator;
+        self
+    }
+
+    /// Look ahead the given number of turns, counting the player's own.
+    pub fn with_depth(mut self, depth: usize) -> Analyzer {
+        self.depth = if depth == 0 { 1 } else { depth };
+        self
+    }
+
+    /// The rules being analyzed under.
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    /// Score a board for a player, using this analyzer's evaluator.
+    pub fn evaluate(&self, board: &Board, color: &Color) -> f64 {
+        self.evaluator.evaluate(board, color)
+    }
+
+    /// Score the board a player's turn ended on, the same way `analyze`
+    /// scores each turn.
+    pub fn score_turn(&self, after: &Board, color: Color) -> f64 {
+        let mut table: TranspositionTable<()> = TranspositionTable::new(TABLE_SIZE);
+        self.value(after,
+                   &next_player(after, &color),
+                   self.depth - 1,
+                   &color,
+                   &mut table)
+    }
+
+    /// Every distinct complete turn the player can take with the given
+    /// dice, best first.
+    pub fn analyze(&self, board: &Board, dice: &Dice, color: Color) -> Vec<Analysis> {
+        let mut table: TranspositionTable<()> = TranspositionTable::new(TABLE_SIZE);
+        let outcomes = gametree::turn_outcomes(board, dice, color, &self.rules, &mut table);
+
+        let mut analyses: Vec<Analysis> = outcomes
+            .into_iter()
+            .map(|(moves, next_board)| {
+                let score: f64 = self.score_turn(&next_board, color);
+                Analysis {
+                    moves: moves,
+                    board: next_board,
+                    score: score,
+                }
+            })
+            .collect();
+
+        analyses.sort_by(|a, b| {
+                             b.score
+                                 .partial_cmp(&a.score)
+                                 .expect("Evaluator returned NaN")
+                         });
+        analyses
+    }
+
+    /// Expected score for `me` of a board with `to_move` about to roll,
+    /// looking `depth` more turns ahead.
+    fn value(&self,
+             board: &Board,
+             to_move: &Color,
+             depth: usize,
+             me: &Color,
+             table: &mut TranspositionTable<()>)
+             -> f64 {
+        if depth == 0 || board.has_winner().is_some() {
+            return self.evaluator.evaluate(board, me);
+        }
+
+        let mut total: f64 = 0.0;
+        for d1 in 1..7 {
+            for d2 in d1..7 {
+                let weight: f64 = if d1 == d2 { 1.0 } else { 2.0 } / 36.0;
+                let rolls: Vec<usize> = if d1 == d2 && self.rules.doubles_bonus &&
+                                           board.all_pawns_entered(to_move) {
+                    vec![d1, d1, 7 - d1, 7 - d1]
+                } else {
+                    vec![d1, d2]
+                };
+
+                table.clear();
+                let outcomes = gametree::turn_outcomes(board,
+                                                       &Dice { rolls: rolls },
+                                                       *to_move,
+                                                       &self.rules,
+                                                       table);
+                let chosen: Board = outcomes
+                    .into_iter()
+                    .map(|(_, b)| (self.evaluator.evaluate(&b, to_move), b))
+                    .fold(None, |best: Option<(f64, Board)>, (score, b)| match best {
+                        Some((best_score, _)) if best_score >= score => best,
+                        _ => Some((score, b)),
+                    })
+                    .map(|(_, b)| b)
+                    .unwrap_or_else(|| board.clone());
+
+                total += weight *
+                         self.value(&chosen,
+                                    &next_player(&chosen, to_move),
+                                    depth - 1,
+                                    me,
+                                    table);
+            }
+        }
+        total
+    }
+}
+
+/// Number of slots in the table used to skip repeated positions
+/// while listing a player's turns.
+static TABLE_SIZE: usize = 1 << 12;
+
+/// Ranks the complete turns a player can take with the given dice, best
+/// first, using the standard rules and the `Progress` evaluator.
+pub fn analyze(board: &Board, dice: &Dice, color: Color) -> Vec<Analysis> {
+    Analyzer::new().analyze(board, dice, color)
+}
+
+/// The player who rolls after the given one, skipping players
+/// whose pawns are all home.
+fn next_player(board: &Board, color: &Color) -> Color {
+    let colors: Vec<Color> = board.colors();
+    let start: usize = colors
+        .iter()
+        .position(|c| c == color)
+        .unwrap_or(0);
+
+    (1..colors.len() + 1)
+        .map(|i| colors[(start + i) % colors.len()])
+        .find(|c| !board.has_finished(c))
+        .unwrap_or(*color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Bopping an opponent sends them back to the nest, so it ranks
+    /// above an ordinary move.
+    fn analyze_prefers_bop() {
+        let board: Board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 20 },
+                           Loc::Spot { index: 40 },
+                           Loc::Home,
+                           Loc::Home],
+            Color::Blue => [Loc::Spot { index: 43 },
+                            Loc::Nest,
+                            Loc::Nest,
+                            Loc::Nest]
+        });
+        let dice: Dice = Dice { rolls: vec![3] };
+
+        // Bopping earns a bonus 20 for either pawn, or pawn 0 moves 3.
+        let analyses: Vec<Analysis> = analyze(&board, &dice, Color::Red);
+        assert_eq!(analyses.len(), 3);
+        assert_eq!(analyses[0].board.get_pawn_loc(&Color::Blue, 0), Loc::Nest);
+        assert_eq!(analyses[2].board.get_pawn_loc(&Color::Blue, 0),
+                   Loc::Spot { index: 43 });
+        assert!(analyses[1].score > analyses[2].score);
+    }
+
+    #[test]
+    /// Looking ahead still lists every turn, and a custom evaluator
+    /// can reverse the ranking.
+    fn analyze_with_depth_and_evaluator() {
+        let board: Board = Board::with_colors(&[Color::Red, Color::Blue]);
+        let dice: Dice = Dice { rolls: vec![5, 5] };
+        let entrance: Loc = Loc::Spot { index: Board::get_entrance(&Color::Red) };
+        let entered = |b: &Board| {
+            b.get_pawns_by_color(&Color::Red)
+                .iter()
+                .filter(|&&l| l == entrance)
+                .count()
+        };
+
+        // Either enter two pawns, or enter one and move it 5.
+        let ahead: Vec<Analysis> = analyze(&board, &dice, Color::Red);
+        assert_eq!(ahead.len(), 2);
+        assert_eq!(entered(&ahead[0].board), 0);
+
+        let deep: Vec<Analysis> = Analyzer::new()
+            .with_depth(2)
+            .analyze(&board, &dice, Color::Red);
+        assert_eq!(deep.len(), 2);
+
+        let backwards: Vec<Analysis> = Analyzer::new()
+            .with_evaluator(Box::new(|b: &Board, c: &Color| -Progress.evaluate(b, c)))
+            .analyze(&board, &dice, Color::Red);
+        assert_eq!(entered(&backwards[0].board), 2);
+    }
+
+    #[test]
+    /// Stopping where an opponent can reach costs more than a safety.
+    fn safety_avoids_exposed_spots() {
+        let board: Board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 26 }, Loc::Home, Loc::Home, Loc::Home],
+            Color::Blue => [Loc::Spot { index: 22 }, Loc::Nest, Loc::Nest, Loc::Nest]
+        });
+        let dice: Dice = Dice { rolls: vec![2] };
+        let safety = Safety { rules: RuleSet::standard() };
+
+        // Moving 2 onto the safety at 28 is better than staying exposed.
+        assert!(safety.evaluate(&board, &Color::Red) < Progress.evaluate(&board, &Color::Red));
+        let analyses: Vec<Analysis> = Analyzer::new()
+            .with_evaluator(Box::new(safety))
+            .analyze(&board, &dice, Color::Red);
+        assert_eq!(analyses[0].board.get_pawn_loc(&Color::Red, 0),
+                   Loc::Spot { index: 28 });
+    }
+}
@@ -3,12 +3,15 @@ use super::board::{Board, Pawn, Color, Loc, PawnLocs, MoveResult};
 use super::game::{Move, MoveType};
 use super::dice::Dice;
 use super::gametree::GameTree;
+use super::rules::RuleSet;
+use super::analysis::Analyzer;
 use super::networkplayer::NetworkPlayer;
 use super::deserialize::XmlMessage;
 use super::deserialize;
 use super::serialize;
 use std::net::TcpStream;
 use std::io::{Write, BufReader, BufWriter, BufRead};
+use rand::{Rng, SeedableRng, StdRng};
 
 pub struct XMLTestPlayer {
     pub color: Color,
@@ -17,7 +20,7 @@ pub struct XMLTestPlayer {
 }
 
 impl Player for XMLTestPlayer {
-    fn do_move(&self, board: Board, dice: Dice) -> Vec<Move> {
+    fn do_move(&mut self, board: Board, dice: Dice) -> Result<Vec<Move>, &'static str> {
         // Create a vector to store the moves we'll eventually return.
         let mut moves: Vec<Move> = Vec::new();
 
@@ -37,14 +40,14 @@ impl Player for XMLTestPlayer {
         // for now just take the first legal move.
         loop {
             if let Some(chosen_move) = options.next() {
-                println!("Our chosen move is {:#?}", chosen_move);
+                debug!("Chosen move {:?}", chosen_move);
                 let move_result: Result<MoveResult,
                                         &'static str> =
                     temp_board.handle_move(chosen_move);
 
                 match move_result {
                     Ok(MoveResult(next_board, bonus)) => {
-                        println!("BONUS BONUS {:#?}", bonus);
+                        trace!("Bonus {:?}", bonus);
                         temp_board = next_board;
                         match chosen_move.m_type {
                             MoveType::EnterPiece => {
@@ -78,15 +81,14 @@ impl Player for XMLTestPlayer {
                                      self.color,
                                      original_board.clone());
         }
-        println!("WERKLJHASDLKJAHSDLJA {:#?}\n{:#?}",
-                 moves,
-                 temp_dice.clone());
-        moves
+        debug!("Moves {:?} leave dice {:?}", moves, temp_dice.rolls);
+        Ok(moves)
     }
 
-    fn start_game(&self) -> String {
+    fn start_game(&mut self, color: Color) -> Result<String, &'static str> {
+        self.color = color;
         self.send(serialize::xml_start_game_response(&self));
-        self.name.to_string()
+        Ok(self.name.to_string())
     }
 }
 
@@ -96,7 +98,7 @@ impl NetworkPlayer for XMLTestPlayer {
     }
 
     fn send(&self, mut msg: String) -> () {
-        println!("We send {}\n", msg);
+        debug!("Sending {}", msg);
         let mut writer = BufWriter::new(&self.stream);
         msg.push_str("\n");
         writer
@@ -113,30 +115,37 @@ impl NetworkPlayer for XMLTestPlayer {
         reader
             .read_line(&mut response)
             .expect("Player could not read");
-        println!("Player received {}", response);
+        debug!("Received {}", response.trim_end());
         let decision: XmlMessage =
             deserialize::deserialize_decision(response.clone());
         match decision {
             XmlMessage::StartGame => {
-                self.color = deserialize::deserialize_start_game(response);
-                self.start_game();
-                ()
+                let color: Color = deserialize::deserialize_start_game(response);
+                self.start_game(color).expect("Could not start the game");
             }
             XmlMessage::DoMove => {
                 // The deserialize method will return a tuple with the board and the dice, so we must decompose that
-                // before we proceed
-                println!("rec do move");
-                let (board, dice) = deserialize::deserialize_do_move(response);
-                let moves_vec = self.do_move(board, dice); //TODO move the write to do_move?
+                // before we proceed. A board we can't read, or that couldn't
+                // happen, is answered with an error rather than moves.
+                let (board, dice) =
+                    match deserialize::deserialize_valid_do_move(response) {
+                        Ok(state) => state,
+                        Err(reason) => {
+                            warn!("Received a bad board: {}", reason);
+                            self.send(serialize::xml_error(&reason));
+                            return;
+                        }
+                    };
+                let moves_vec = self.do_move(board, dice).expect("Could not choose moves"); //TODO move the write to do_move?
                 // println!("Our move vec {:#?}", moves_vec);
                 self.send(serialize::xml_moves(&moves_vec));
                 ()
             }
             XmlMessage::DoublesPenalty => {
                 self.send("<void> </void>".to_string());
-                self.doubles_penalty()
+                self.doubles_penalty().expect("Could not take the penalty")
             }
-            XmlMessage::Error => panic!("Could not parse message"),        
+            XmlMessage::Error => self.send(serialize::xml_error("Could not parse message")),
         };
     }
 }
@@ -151,6 +160,7 @@ pub struct MoveEndPawnPlayer {
     pub color: Color,
     pub name: String,
     should_reverse_path: bool,
+    rules: RuleSet,
 }
 
 impl MoveEndPawnPlayer {
@@ -162,20 +172,19 @@ impl MoveEndPawnPlayer {
             name: name,
             color: color,
             should_reverse_path: should_reverse_path,
+            rules: RuleSet::standard(),
         }
     }
-}
 
-impl Player for MoveEndPawnPlayer {
-    fn start_game(&self) -> String {
-        self.name.to_string()
+    /// Choose moves by the given rules instead of the standard ones.
+    pub fn with_rules(mut self, rules: RuleSet) -> MoveEndPawnPlayer {
+        self.rules = rules;
+        self
     }
 
-    /// Always try to move the furthest pawn.
-    /// If none of the pawns can be moved with any of the mini-moves,
-    /// return an empty vector of moves.
-    fn do_move(&self, board: Board, dice: Dice) -> Vec<Move> {
-        let mut moves: Vec<Move> = Vec::new();
+    /// The first legal mini-move for the furthest (or least advanced)
+    /// pawn that has one, given that the roll started from `start`.
+    fn next_move(&self, start: &Board, board: &Board, dice: &Dice) -> Option<Move> {
         let pawn_locs: PawnLocs = board.get_pawns_by_color(&self.color);
         let mut sorted_pawn_locs: Vec<(usize, Loc)> =
             Board::sort_player_locs(&self.color, pawn_locs);
@@ -186,7 +195,7 @@ impl Player for MoveEndPawnPlayer {
             sorted_pawn_locs.reverse();
         }
 
-        'outer: for &(pawn_id, loc) in sorted_pawn_locs.iter() {
+        for &(pawn_id, loc) in sorted_pawn_locs.iter() {
             for &mini_move in dice.rolls.iter() {
                 let m = Move {
                     pawn: Pawn {
@@ -212,29 +221,183 @@ impl Player for MoveEndPawnPlayer {
                     },
                 };
 
-                // is valid move check should be done here
-                if Board::is_valid_move(&board, &dice, &m) {
+                if let Err(reason) = Board::explain_move_with(board, dice, &m, &self.rules) {
+                    trace!("Skipping {:?}: {}", m, reason);
+                    continue;
+                }
+
+                // Blockades can't move together, even one mini-move at a time.
+                match board.handle_move_with(m, &self.rules) {
+                    Ok(MoveResult(next_board, _)) => {
+                        if start.is_valid_turn(&next_board, dice, self.color) {
+                            return Some(m);
+                        }
+                        trace!("Skipping {:?}: moves a blockade together", m);
+                    }
+                    Err(reason) => trace!("Skipping {:?}: {}", m, reason),
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Player for MoveEndPawnPlayer {
+    fn start_game(&mut self, color: Color) -> Result<String, &'static str> {
+        self.color = color;
+        Ok(self.name.to_string())
+    }
+
+    /// Always try to move the furthest pawn, until none of the pawns
+    /// can be moved with any of the mini-moves that are left.
+    /// If none can be moved at all, return an empty vector of moves.
+    fn do_move(&mut self, board: Board, dice: Dice) -> Result<Vec<Move>, &'static str> {
+        let mut moves: Vec<Move> = Vec::new();
+        let mut temp_board: Board = board.clone();
+        let mut temp_dice: Dice = dice;
+
+        while let Some(m) = self.next_move(&board, &temp_board, &temp_dice) {
+            match temp_board.handle_move_with(m, &self.rules) {
+                Ok(MoveResult(next_board, bonus)) => {
+                    temp_board = next_board;
+                    temp_dice = temp_dice.consume_move_with(&m, &self.rules);
+
+                    if let Some(amt) = bonus {
+                        temp_dice = temp_dice.give_bonus(amt);
+                    }
+
                     moves.push(m);
-                    break 'outer;
-                } else {
-                    println!("invalid");
                 }
+                Err(_) => unreachable!(),
             }
         }
-        moves
+        Ok(moves)
     }
 }
 
-fn move_first_pawn_player(name: String, color: Color) -> MoveEndPawnPlayer {
+pub fn move_first_pawn_player(name: String, color: Color) -> MoveEndPawnPlayer {
     // To move the furthest ahead pawn, we need to iterate over pawns
     // in reverse order, so we set should_reverse_list to true.
     MoveEndPawnPlayer::new(name, color, true)
 }
 
-fn move_last_pawn_player(name: String, color: Color) -> MoveEndPawnPlayer {
+pub fn move_last_pawn_player(name: String, color: Color) -> MoveEndPawnPlayer {
     MoveEndPawnPlayer::new(name, color, false)
 }
 
+/// RandomPlayer plays random legal mini-moves until none are left.
+/// The player's random number generator is seeded, so a given seed
+/// always makes the same choices when shown the same boards and dice.
+pub struct RandomPlayer {
+    pub color: Color,
+    pub name: String,
+    rng: StdRng,
+    rules: RuleSet,
+}
+
+impl RandomPlayer {
+    pub fn new(name: String, color: Color, seed: usize) -> RandomPlayer {
+        RandomPlayer {
+            name: name,
+            color: color,
+            rng: SeedableRng::from_seed(&[seed][..]),
+            rules: RuleSet::standard(),
+        }
+    }
+
+    /// Choose moves by the given rules instead of the standard ones.
+    pub fn with_rules(mut self, rules: RuleSet) -> RandomPlayer {
+        self.rules = rules;
+        self
+    }
+}
+
+impl Player for RandomPlayer {
+    fn start_game(&mut self, color: Color) -> Result<String, &'static str> {
+        self.color = color;
+        Ok(self.name.to_string())
+    }
+
+    fn do_move(&mut self, board: Board, dice: Dice) -> Result<Vec<Move>, &'static str> {
+        let mut moves: Vec<Move> = Vec::new();
+        let original_board: Board = board.clone();
+        let mut temp_board: Board = board;
+        let mut temp_dice: Dice = dice;
+
+        loop {
+            let options: Vec<Move> = GameTree::from(temp_board.clone(),
+                                                    temp_dice.clone(),
+                                                    self.color,
+                                                    original_board.clone())
+                    .with_rules(self.rules)
+                    .collect();
+
+            let chosen_move: Move = match self.rng.choose(&options) {
+                Some(&m) => m,
+                None => break,
+            };
+
+            match temp_board.handle_move_with(chosen_move, &self.rules) {
+                Ok(MoveResult(next_board, bonus)) => {
+                    temp_board = next_board;
+                    temp_dice = temp_dice.consume_move_with(&chosen_move,
+                                                            &self.rules);
+
+                    if let Some(amt) = bonus {
+                        temp_dice = temp_dice.give_bonus(amt);
+                    }
+
+                    moves.push(chosen_move);
+                }
+                Err(_) => unreachable!(),
+            };
+        }
+
+        Ok(moves)
+    }
+}
+
+/// GreedyPlayer takes whichever complete turn leaves its pawns furthest
+/// along compared to its opponents', without looking ahead.
+/// See `analysis::Progress`.
+pub struct GreedyPlayer {
+    pub color: Color,
+    pub name: String,
+    analyzer: Analyzer,
+}
+
+impl GreedyPlayer {
+    pub fn new(name: String, color: Color) -> GreedyPlayer {
+        GreedyPlayer {
+            name: name,
+            color: color,
+            analyzer: Analyzer::new(),
+        }
+    }
+
+    /// Choose moves by the given rules instead of the standard ones.
+    pub fn with_rules(mut self, rules: RuleSet) -> GreedyPlayer {
+        self.analyzer = self.analyzer.with_rules(rules);
+        self
+    }
+}
+
+impl Player for GreedyPlayer {
+    fn start_game(&mut self, color: Color) -> Result<String, &'static str> {
+        self.color = color;
+        Ok(self.name.to_string())
+    }
+
+    fn do_move(&mut self, board: Board, dice: Dice) -> Result<Vec<Move>, &'static str> {
+        Ok(self.analyzer
+               .analyze(&board, &dice, self.color)
+               .into_iter()
+               .next()
+               .map(|best| best.moves)
+               .unwrap_or_default())
+    }
+}
+
 mod test {
     use super::*;
 
@@ -242,7 +405,7 @@ mod test {
 
     #[test]
     fn do_move_basic() {
-        let test_player = move_first_pawn_player("Test".to_string(),
+        let mut test_player = move_first_pawn_player("Test".to_string(),
                                                  Color::Green);
         let test_board = Board::from(map!{
             Color::Green => [Loc::Spot {
@@ -265,8 +428,8 @@ mod test {
             },
         };
         assert!(test_player
-                    .do_move(test_board, test_dice)
-                    .pop() == Some(expected_move));
+                    .do_move(test_board, test_dice).unwrap()
+                    .first() == Some(&expected_move));
     }
 
     // Chooses furthest pawn
@@ -276,7 +439,7 @@ mod test {
     // Expect: MoveMain { start: 36, distance: 1 }
     #[test]
     fn do_move_choose_farthest_pawn() {
-        let test_player = move_first_pawn_player("Test".to_string(),
+        let mut test_player = move_first_pawn_player("Test".to_string(),
                                                  Color::Green);
         let test_board = Board::from(map!{
                 Color::Green => [Loc::Spot {
@@ -312,8 +475,8 @@ mod test {
             },
         };
         assert!(test_player
-                    .do_move(test_board, test_dice)
-                    .pop() == Some(expected_move));
+                    .do_move(test_board, test_dice).unwrap()
+                    .first() == Some(&expected_move));
     }
 
     // Chooses second pawn if first pawn is blockaded
@@ -324,7 +487,7 @@ mod test {
     // Expect: MoveMain { start: 34, distance: 3 }
     #[test]
     fn do_move_choose_second_pawn_if_first_blockaded() {
-        let test_player: MoveEndPawnPlayer =
+        let mut test_player: MoveEndPawnPlayer =
             move_first_pawn_player("Test".to_string(), Color::Green);
         let test_board = Board::from(map!{
             Color::Green => [Loc::Nest,
@@ -365,8 +528,8 @@ mod test {
             },
         };
         assert!(test_player
-                    .do_move(test_board, test_dice)
-                    .pop() == Some(expected_move));
+                    .do_move(test_board, test_dice).unwrap()
+                    .first() == Some(&expected_move));
     }
 
     // Chooses third pawn if first pawn would overshoot home and second is blockaded
@@ -377,7 +540,7 @@ mod test {
     // Expect: MoveMain { start: 19, distance: 3 }
     #[test]
     fn do_move_choose_second_pawn_if_first_overshoot_second_blockaded() {
-        let test_player: MoveEndPawnPlayer =
+        let mut test_player: MoveEndPawnPlayer =
             move_first_pawn_player("Test".to_string(), Color::Green);
         let test_board = Board::from(map!{
             Color::Green => [Loc::Nest,
@@ -418,8 +581,8 @@ mod test {
             },
         };
         assert!(test_player
-                    .do_move(test_board, test_dice)
-                    .pop() == Some(expected_move));
+                    .do_move(test_board, test_dice).unwrap()
+                    .first() == Some(&expected_move));
     }
 
     // Chooses first pawn, leading to a bop
@@ -430,7 +593,7 @@ mod test {
     // Expect: MoveMain { start: 47, distance: 2 }
     #[test]
     fn do_move_choose_first_pawn_and_bop() {
-        let test_player: MoveEndPawnPlayer =
+        let mut test_player: MoveEndPawnPlayer =
             move_first_pawn_player("Test".to_string(), Color::Green);
         let test_board = Board::from(map!{
             Color::Green => [Loc::Nest,
@@ -468,8 +631,30 @@ mod test {
                 color: Color::Green,
             },
         };
-        assert_eq!(test_player.do_move(test_board, test_dice),
-                   vec![expected_move]);
+        // The same pawn carries on with the 3, and the bop's bonus goes
+        // to the furthest pawn that can use it.
+        let next_move = Move {
+            m_type: MoveType::MoveMain {
+                start: 49,
+                distance: 3,
+            },
+            pawn: Pawn {
+                id: 2,
+                color: Color::Green,
+            },
+        };
+        let bonus_move = Move {
+            m_type: MoveType::MoveMain {
+                start: 34,
+                distance: 20,
+            },
+            pawn: Pawn {
+                id: 1,
+                color: Color::Green,
+            },
+        };
+        assert_eq!(test_player.do_move(test_board, test_dice).unwrap(),
+                   vec![expected_move, next_move, bonus_move]);
     }
 
     // Enter, if no other pawns can be moved
@@ -482,7 +667,7 @@ mod test {
     // I changed the test so that 21 was also unable to be used, else 19 could move there
     #[test]
     fn do_move_enter_if_no_others_can_move() {
-        let test_player: MoveEndPawnPlayer =
+        let mut test_player: MoveEndPawnPlayer =
             move_first_pawn_player("Test".to_string(), Color::Green);
         let test_board = Board::from(map!{
             Color::Green => [Loc::Nest,
@@ -531,8 +716,8 @@ mod test {
             },
         };
         assert!(test_player
-                    .do_move(test_board, test_dice)
-                    .pop() == Some(expected_move));
+                    .do_move(test_board, test_dice).unwrap()
+                    .first() == Some(&expected_move));
     }
 
     // Return empty array if no moves are valid
@@ -543,7 +728,7 @@ mod test {
     // Expect: []
     #[test]
     fn do_move_no_possible_moves() {
-        let test_player: MoveEndPawnPlayer =
+        let mut test_player: MoveEndPawnPlayer =
             move_first_pawn_player("Test".to_string(), Color::Green);
         let test_board = Board::from(map!{
             Color::Green => [Loc::Nest,
@@ -575,13 +760,13 @@ mod test {
         });
         let test_dice = Dice { rolls: vec![3, 3] };
         assert!(test_player
-                    .do_move(test_board, test_dice)
+                    .do_move(test_board, test_dice).unwrap()
                     .pop() == None);
     }
 
     #[test]
     fn do_move_into_blockade() {
-        let test_player: XMLTestPlayer = XMLTestPlayer {
+        let mut test_player: XMLTestPlayer = XMLTestPlayer {
             color: Color::Red,
             name: String::from("Moses"),
             stream: TcpStream::connect("172.217.6.110:80").expect("Could not connect"),
@@ -599,14 +784,14 @@ mod test {
         });
 
 
-        let move_vector = test_player.do_move(test_board, test_dice);
+        let move_vector = test_player.do_move(test_board, test_dice).unwrap();
         println!("The vector of moves is {:#?}", move_vector);
         assert!(move_vector == vec![]);
     }
 
     #[test]
     fn do_move_random1() {
-        let test_player: XMLTestPlayer = XMLTestPlayer {
+        let mut test_player: XMLTestPlayer = XMLTestPlayer {
             color: Color::Red,
             name: String::from("Moses"),
             stream: TcpStream::connect("172.217.6.110:80").expect("Could not connect"),
@@ -620,8 +805,95 @@ mod test {
         });
 
 
-        let move_vector = test_player.do_move(test_board, test_dice);
+        let move_vector = test_player.do_move(test_board, test_dice).unwrap();
         println!("The vector of moves is {:#?}", move_vector);
         assert!(move_vector.len() > 1);
     }
+
+    #[test]
+    /// Greedy players bring a pawn home when they can, for the bonus.
+    fn greedy_player_takes_bonus() {
+        let mut test_player = GreedyPlayer::new("Greedy".to_string(), Color::Red);
+        let test_board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 106 }, Loc::Spot { index: 20 },
+                           Loc::Nest, Loc::Nest]
+        });
+        let test_dice = Dice { rolls: vec![1, 4] };
+
+        let moves: Vec<Move> = test_player.do_move(test_board, test_dice).unwrap();
+        assert!(moves.contains(&Move {
+                                   m_type: MoveType::MoveHome {
+                                       start: 106,
+                                       distance: 1,
+                                   },
+                                   pawn: Pawn::new(0, Color::Red),
+                               }));
+    }
+
+    #[test]
+    /// Pawn-order players play by the game's rules, whichever they are,
+    /// so they get through whole games without breaking them.
+    fn move_end_pawn_players_follow_rules() {
+        use std::cell::RefCell;
+        use game::{Game, GameOutcome};
+
+        for rules in [RuleSet::parchis(), RuleSet::simple()].iter() {
+            for seed in 1..4 {
+                let mut game: Game = Game::new();
+                game.set_rules(*rules);
+                for (i, clr) in [Color::Red, Color::Blue, Color::Yellow, Color::Green]
+                        .iter()
+                        .enumerate() {
+                    let player: MoveEndPawnPlayer = if i % 2 == 0 {
+                        move_first_pawn_player("MoveFirst".to_string(), *clr)
+                    } else {
+                        move_last_pawn_player("MoveLast".to_string(), *clr)
+                    };
+                    game.seat_player(Box::new(player.with_rules(*rules)), *clr)
+                        .unwrap();
+                }
+
+                let rng: RefCell<StdRng> =
+                    RefCell::new(SeedableRng::from_seed(&[seed][..]));
+                let outcome: GameOutcome =
+                    game.play(|bonus| Dice::roll_with(&mut *rng.borrow_mut(), bonus),
+                              1000);
+                for (clr, stats) in outcome.stats.iter() {
+                    assert_eq!((*clr, stats.violation.clone()), (*clr, None));
+                }
+                assert!(outcome.winner.is_some());
+            }
+        }
+    }
+
+    #[test]
+    /// A board that can't be read, or couldn't happen, is answered with
+    /// an error instead of moves.
+    fn bad_boards_get_errors() {
+        use std::net::TcpListener;
+
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut test_player: XMLTestPlayer = XMLTestPlayer {
+            color: Color::Red,
+            name: String::from("Moses"),
+            stream: TcpStream::connect(listener.local_addr().unwrap()).unwrap(),
+        };
+        let (server, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(&server);
+        let dice = Dice { rolls: vec![3, 5] };
+        let crowded = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 20 }, Loc::Spot { index: 20 },
+                           Loc::Spot { index: 20 }, Loc::Nest]
+        });
+
+        for message in vec![serialize::xml_do_move(&crowded, &dice),
+                            "<do-move> <board> <start> </start> </board> </do-move>"
+                                .to_string()] {
+            (&server).write_all((message + "\n").as_bytes()).unwrap();
+            test_player.receive();
+            let mut answer: String = String::new();
+            reader.read_line(&mut answer).unwrap();
+            assert!(answer.starts_with("<error>"), "Answered {}", answer);
+        }
+    }
 }
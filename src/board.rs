@@ -6,6 +6,9 @@ use super::game::{Move, MoveType};
 use super::constants::*;
 use super::dice::{Dice, EntryMove};
 use super::deserialize;
+use super::rules::RuleSet;
+use super::zobrist;
+use super::notation;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 /// Represents the location of a pawn.
@@ -153,7 +156,92 @@ impl Pawn {
 
 pub struct MoveResult(pub Board, pub Option<Bonus>);
 
-type Bonus = usize;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Why a mini-move is illegal.
+pub enum IllegalMove {
+    /// The pawn isn't where the move starts.
+    PawnNotAtStart { expected: Loc, actual: Loc },
+    /// No unused die matches the distance moved.
+    DistanceNotRolled(usize),
+    /// A blockade stands in the pawn's way.
+    BlockadeOnPath(Loc),
+    /// The move goes past Home.
+    OvershootsHome,
+    /// An opponent is on the safety square the pawn would land on.
+    SafetyOccupied(Loc),
+    /// A blockade is sitting on the player's entrance.
+    EntranceBlockaded(Loc),
+    /// Two pawns are already on the player's entrance, and neither can
+    /// be bopped.
+    EntranceFull(Loc),
+    /// The dice don't have a 5, or two dice adding up to 5.
+    NeedsFiveToEnter,
+}
+
+impl fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IllegalMove::PawnNotAtStart { expected, actual } => {
+                write!(f, "Pawn is at {:?}, not {:?}", actual, expected)
+            }
+            IllegalMove::DistanceNotRolled(distance) => {
+                write!(f, "No die to move {}", distance)
+            }
+            IllegalMove::BlockadeOnPath(loc) => {
+                write!(f, "Blockade in the way at {:?}", loc)
+            }
+            IllegalMove::OvershootsHome => write!(f, "Move goes past Home"),
+            IllegalMove::SafetyOccupied(loc) => {
+                write!(f, "An opponent is on the safety square at {:?}", loc)
+            }
+            IllegalMove::EntranceBlockaded(loc) => {
+                write!(f, "Entrance at {:?} is blockaded", loc)
+            }
+            IllegalMove::EntranceFull(loc) => {
+                write!(f, "Entrance at {:?} already has two pawns", loc)
+            }
+            IllegalMove::NeedsFiveToEnter => write!(f, "Entering needs a 5"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A way in which a board is physically impossible.
+pub enum Violation {
+    /// The pawn's spot is neither on the main ring nor in a home row.
+    OffBoard(Pawn, usize),
+    /// The pawn is in another color's home row.
+    WrongHomeRow(Pawn, usize),
+    /// More than two pawns share a spot.
+    TooManyPawns(Loc),
+    /// Pawns of different colors share a spot that isn't a safety,
+    /// and they aren't partners playing as a team.
+    SharedSpot(Loc),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Violation::OffBoard(pawn, index) => {
+                write!(f, "{} pawn {} is on spot {}, which is off the board",
+                       pawn.color, pawn.id, index)
+            }
+            Violation::WrongHomeRow(pawn, index) => {
+                write!(f, "{} pawn {} is on spot {}, in another color's home row",
+                       pawn.color, pawn.id, index)
+            }
+            Violation::TooManyPawns(loc) => {
+                write!(f, "More than two pawns are on {:?}", loc)
+            }
+            Violation::SharedSpot(loc) => {
+                write!(f, "Pawns of different colors share {:?}, which isn't a safety",
+                       loc)
+            }
+        }
+    }
+}
+
+pub type Bonus = usize;
 
 /// To make testing less verbose, we can express a board in terms of
 /// the difference in pawn positions from an initial board.
@@ -169,13 +257,17 @@ impl Board {
     /// Initialize a new game board in the starting configuration,
     /// i.e. all pawns are in their respective nests.
     pub fn new() -> Board {
+        Board::with_colors(&COLORS)
+    }
+
+    /// Initialize a new game board for only the given colors, with
+    /// their pawns in their nests. Absent colors have no pawns on the board.
+    pub fn with_colors(colors: &[Color]) -> Board {
         let mut positions: BTreeMap<Color, PawnLocs> = BTreeMap::new();
         let init_pawn_locs: PawnLocs = [Loc::Nest; 4];
 
-        for clr in COLORS.iter() {
-            for _ in 0..4 {
-                positions.insert(clr.clone(), init_pawn_locs.clone());
-            }
+        for clr in colors.iter() {
+            positions.insert(clr.clone(), init_pawn_locs.clone());
         }
 
         Board { positions: positions }
@@ -196,6 +288,15 @@ impl Board {
 
     /// Takes a move and returns a new board.
     pub fn handle_move(&self, m: Move) -> Result<MoveResult, &'static str> {
+        self.handle_move_with(m, &RuleSet::standard())
+    }
+
+    /// Takes a move and returns a new board, awarding bonuses
+    /// according to the given rules.
+    pub fn handle_move_with(&self,
+                            m: Move,
+                            rules: &RuleSet)
+                            -> Result<MoveResult, &'static str> {
         let Move {
             pawn: Pawn { color, id },
             m_type,
@@ -226,10 +327,10 @@ impl Board {
                 };
 
                 if next_loc == Loc::Home {
-                    bonus = Some(HOME_BONUS);
+                    bonus = rules.home_bonus();
                 }
 
-                if let Some(bopped) = self.can_bop(color, next_loc) {
+                if let Some(bopped) = self.can_bop_with(color, next_loc, rules) {
                     // If a bop occurs, we need to handle two side effects:
                     // 1. Move the bopped pawn back to the nest.
                     let bopped_pawn_locs: PawnLocs =
@@ -240,14 +341,14 @@ impl Board {
                     next_positions.insert(bopped.color, next_bopped_pawns);
 
                     // 2. Award the bop bonus.
-                    bonus = Some(BOP_BONUS);
+                    bonus = rules.bop_bonus();
                 }
 
                 next_loc
             }
         };
 
-        if let Some(bopped) = self.can_bop(color, next_loc) {
+        if let Some(bopped) = self.can_bop_with(color, next_loc, rules) {
             // If a bop occurs, we need to handle two side effects:
             // 1. Move the bopped pawn back to the nest.
             let bopped_pawn_locs: PawnLocs =
@@ -257,7 +358,7 @@ impl Board {
             next_positions.insert(bopped.color, next_bopped_pawns);
 
             // 2. Award the bop bonus.
-            bonus = Some(BOP_BONUS);
+            bonus = rules.bop_bonus();
         }
 
         // Modify the copy of the position map with the next_position
@@ -270,6 +371,142 @@ impl Board {
         Ok(result)
     }
 
+    /// Takes a move and this board's Zobrist hash, and returns the new
+    /// board along with its hash. Only the pawns that moved are rehashed.
+    pub fn handle_move_hashed(&self,
+                              m: Move,
+                              rules: &RuleSet,
+                              hash: u64)
+                              -> Result<(MoveResult, u64), &'static str> {
+        let MoveResult(next_board, bonus) = self.handle_move_with(m, rules)?;
+        let mut next_hash: u64 = hash;
+
+        // A bop sends an opponent's pawn back to the nest.
+        for (clr, locs) in self.positions.iter() {
+            if *clr == m.pawn.color {
+                continue;
+            }
+
+            let next_locs: PawnLocs = next_board.get_pawns_by_color(clr);
+            for (id, &loc) in locs.iter().enumerate() {
+                if next_locs[id] != loc {
+                    next_hash = self.zobrist_move_pawn(next_hash,
+                                                       Pawn::new(id, *clr),
+                                                       next_locs[id]);
+                }
+            }
+        }
+
+        let next_loc: Loc = next_board.get_pawn_loc(&m.pawn.color, m.pawn.id);
+        next_hash = self.zobrist_move_pawn(next_hash, m.pawn, next_loc);
+
+        Ok((MoveResult(next_board, bonus), next_hash))
+    }
+
+    /// Check that the board is physically possible, returning every
+    /// way in which it isn't.
+    pub fn validate(&self) -> Result<(), Vec<Violation>> {
+        self.validate_with(&RuleSet::standard())
+    }
+
+    /// Check that the board is physically possible under the given
+    /// rules. Partners playing as a team may share any spot.
+    pub fn validate_with(&self, rules: &RuleSet) -> Result<(), Vec<Violation>> {
+        let mut violations: Vec<Violation> = Vec::new();
+        let mut occupants: BTreeMap<usize, Vec<Color>> = BTreeMap::new();
+
+        for (clr, locs) in self.positions.iter() {
+            for (id, &loc) in locs.iter().enumerate() {
+                let index: usize = match loc {
+                    Loc::Spot { index } => index,
+                    _ => continue,
+                };
+
+                let row: usize = index - index % 100;
+                if index >= BOARD_SIZE &&
+                   (index % 100 >= HOME_ROW_LENGTH ||
+                    !COLORS.iter().any(|c| Board::get_home_row(c) == row)) {
+                    violations.push(Violation::OffBoard(Pawn::new(id, *clr), index));
+                    continue;
+                }
+                if index >= BOARD_SIZE && row != Board::get_home_row(clr) {
+                    violations.push(Violation::WrongHomeRow(Pawn::new(id, *clr), index));
+                    continue;
+                }
+
+                occupants.entry(index).or_insert_with(Vec::new).push(*clr);
+            }
+        }
+
+        for (&index, colors) in occupants.iter() {
+            let loc: Loc = Loc::Spot { index: index };
+            if colors.len() > 2 {
+                violations.push(Violation::TooManyPawns(loc));
+            }
+            let partner: Color = Board::get_partner(&colors[0]);
+            let shared: bool = colors.iter()
+                .any(|c| *c != colors[0] && !(rules.partners && *c == partner));
+            if shared && !Board::is_safety(loc) {
+                violations.push(Violation::SharedSpot(loc));
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Write the pawn locations in position notation,
+    /// e.g. `r:10,20,h,h/b:22,n,n,n`. See `notation::Position`.
+    pub fn to_notation(&self) -> String {
+        notation::format_board(self)
+    }
+
+    /// Read pawn locations written in position notation.
+    pub fn from_notation(s: &str) -> Result<Board, &'static str> {
+        notation::parse_board(s)
+    }
+
+    /// Zobrist hash of the position with the given player to move.
+    ///
+    /// The hash only depends on how many of each player's pawns are on
+    /// each location, not on which pawn is which, so positions reached
+    /// by moving pawns in a different order hash the same.
+    pub fn zobrist(&self, to_move: &Color) -> u64 {
+        self.zobrist_pawns() ^ zobrist::side_key(to_move)
+    }
+
+    /// Zobrist hash of the pawns alone, without the player to move.
+    pub fn zobrist_pawns(&self) -> u64 {
+        let mut hash: u64 = 0;
+        for (clr, locs) in self.positions.iter() {
+            for (id, &loc) in locs.iter().enumerate() {
+                let nth: usize = locs[..id]
+                    .iter()
+                    .filter(|&&l| l == loc)
+                    .count();
+                hash ^= zobrist::pawn_key(clr, loc, nth);
+            }
+        }
+        hash
+    }
+
+    /// Update this board's Zobrist hash for a pawn moving to a
+    /// new location, without rehashing the rest of the board.
+    pub fn zobrist_move_pawn(&self, hash: u64, pawn: Pawn, to: Loc) -> u64 {
+        let locs: PawnLocs = self.get_pawns_by_color(&pawn.color);
+        let from: Loc = locs[pawn.id];
+        if from == to {
+            return hash;
+        }
+
+        let count = |loc: Loc| locs.iter().filter(|&&l| l == loc).count();
+        hash ^ zobrist::pawn_key(&pawn.color, from, count(from) - 1) ^
+        zobrist::pawn_key(&pawn.color, to, count(to))
+    }
+
     /// Determines blockades were moved together.
     pub fn is_valid_turn(&self,
                          end: &Board,
@@ -410,13 +647,41 @@ impl Board {
     /// Determines whether the given board, dice, and color has any valid moves left.
     /// TODO: Does this preserve legality within turns?
     pub fn has_valid_moves(board: &Board, dice: &Dice, color: &Color) -> bool {
-        // Check
+        Board::has_valid_moves_with(board, dice, color, &RuleSet::standard())
+    }
+
+    /// Determines whether the given board, dice, and color has any valid moves
+    /// left under the given rules.
+    pub fn has_valid_moves_with(board: &Board,
+                                dice: &Dice,
+                                color: &Color,
+                                rules: &RuleSet)
+                                -> bool {
+        Board::has_valid_moves_from(board, board, dice, color, rules)
+    }
 
+    /// Determines whether a player whose roll started from `start` has any
+    /// valid moves left on `board` under the given rules. Moves that would
+    /// move one of `start`'s blockades together don't count, since the
+    /// turn couldn't end with them.
+    pub fn has_valid_moves_from(start: &Board,
+                                board: &Board,
+                                dice: &Dice,
+                                color: &Color,
+                                rules: &RuleSet)
+                                -> bool {
 
         if dice.all_used() {
             return false;
         }
 
+        // In team play, a player whose pawns are all home moves
+        // their partner's pawns instead.
+        let partner: Color = Board::get_partner(color);
+        if board.can_move_pawns_of(color, &partner, rules) {
+            return Board::has_valid_moves_from(start, board, dice, &partner, rules);
+        }
+
         let pawns: PawnLocs = board.get_pawns_by_color(color);
 
         // Takes a roll, and checks whether any of the
@@ -457,7 +722,13 @@ impl Board {
                         pawn: pawn,
                     };
 
-                    Board::is_valid_move(board, dice, &mv)
+                    Board::is_valid_move_with(board, dice, &mv, rules) &&
+                    match board.handle_move_with(mv, rules) {
+                        Ok(MoveResult(next_board, _)) => {
+                            start.is_valid_turn(&next_board, dice, *color)
+                        }
+                        Err(_) => false,
+                    }
                 };
 
             // Finally iterate over all pawns and check whether they
@@ -475,6 +746,37 @@ impl Board {
 
     /// Determines whether an individual mini-move is valid, given some board and dice.
     pub fn is_valid_move(board: &Board, dice: &Dice, m: &Move) -> bool {
+        Board::is_valid_move_with(board, dice, m, &RuleSet::standard())
+    }
+
+    /// Determines whether an individual mini-move is valid under the given rules.
+    ///
+    /// The move is checked from the point of view of the pawn being moved,
+    /// so in team play this also validates moving a partner's pawn. Whether
+    /// the player may move that pawn at all is up to `can_move_pawns_of`.
+    pub fn is_valid_move_with(board: &Board,
+                              dice: &Dice,
+                              m: &Move,
+                              rules: &RuleSet)
+                              -> bool {
+        Board::explain_move_with(board, dice, m, rules).is_ok()
+    }
+
+    /// Explains why an individual mini-move is illegal, given some board and dice.
+    pub fn explain_move(board: &Board,
+                        dice: &Dice,
+                        m: &Move)
+                        -> Result<(), IllegalMove> {
+        Board::explain_move_with(board, dice, m, &RuleSet::standard())
+    }
+
+    /// Explains why an individual mini-move is illegal under the given rules.
+    /// Returns the first rule the move breaks.
+    pub fn explain_move_with(board: &Board,
+                             dice: &Dice,
+                             m: &Move,
+                             rules: &RuleSet)
+                             -> Result<(), IllegalMove> {
         let Move { pawn, m_type } = *m;
         let Pawn { color, id } = pawn;
 
@@ -484,28 +786,37 @@ impl Board {
                 // - Dice fulfill conditions for entering
                 // - Entered pawn was formerly at nest
                 // - No blockades on player's entrance
-                let is_dice_valid: bool = match dice.can_enter() {
-                    EntryMove::NoEntry => false,
-                    _ => true,
-                };
-
-                println!("is_dice_valid: {}", is_dice_valid);
+                if dice.can_enter_with(rules) == EntryMove::NoEntry {
+                    return Err(IllegalMove::NeedsFiveToEnter);
+                }
 
-                let pawn_in_nest: bool = match board.get_pawn_loc(&color, id) {
-                    Loc::Nest => true,
-                    _ => false,
-                };
+                let pawn_loc: Loc = board.get_pawn_loc(&color, id);
+                if pawn_loc != Loc::Nest {
+                    return Err(IllegalMove::PawnNotAtStart {
+                                   expected: Loc::Nest,
+                                   actual: pawn_loc,
+                               });
+                }
 
-                println!("pawn in nest: {}", pawn_in_nest);
-                let entrance = Board::get_entrance(&color);
-                let is_entrance_blockaded =
-                    board
-                        .get_blockades()
-                        .contains(&Loc::Spot { index: entrance });
+                let entrance: Loc = Loc::Spot { index: Board::get_entrance(&color) };
+                if board.get_blockades().contains(&entrance) {
+                    return Err(IllegalMove::EntranceBlockaded(entrance));
+                }
 
-                println!("is entrance blockaded: {}", is_entrance_blockaded);
+                // When the rules make entrances safe, an opponent on the
+                // entrance stays put, so it can fill up without a blockade.
+                let mut occupants: usize = board.positions
+                    .values()
+                    .map(|locs| locs.iter().filter(|&&loc| loc == entrance).count())
+                    .sum();
+                if board.can_bop_with(color, entrance, rules).is_some() {
+                    occupants -= 1;
+                }
+                if occupants >= 2 {
+                    return Err(IllegalMove::EntranceFull(entrance));
+                }
 
-                is_dice_valid && pawn_in_nest && !is_entrance_blockaded
+                Ok(())
             }
             MoveType::MoveMain { start, distance } |
             MoveType::MoveHome { start, distance } => {
@@ -522,66 +833,58 @@ impl Board {
                 // Pawn is currently at start location in the Main Ring.
                 let current_pawn_loc: Loc = board.get_pawn_loc(&color, id);
                 if current_pawn_loc != start_loc {
-                    return false;
+                    return Err(IllegalMove::PawnNotAtStart {
+                                   expected: start_loc,
+                                   actual: current_pawn_loc,
+                               });
                 }
-                // This will return false when the pawn attempts to bop on a safety square
+                // A pawn can't land on a safety square an opponent is on.
                 if Board::is_safety(finish_loc) {
-                    // board.full_safety_square(finish_loc, color) {
-                    for clr in [Color::Green,
-                                Color::Red,
-                                Color::Blue,
-                                Color::Yellow]
-                                .iter() {
-                        if *clr == color {
+                    for (clr, locs) in board.positions.iter() {
+                        if *clr == color ||
+                           (rules.partners && *clr == Board::get_partner(&color)) {
                             continue;
                         }
 
-                        for loc in board
-                                .get_pawns_by_color(&clr)
-                                .iter() {
-                            if *loc == finish_loc {
-                                return false;
-                            }
+                        if locs.contains(&finish_loc) {
+                            return Err(IllegalMove::SafetyOccupied(finish_loc));
                         }
                     }
                 }
 
                 // Chosen move distance is a valid mini-move.
                 if !dice.contains(&distance) {
-                    return false;
+                    return Err(IllegalMove::DistanceNotRolled(distance));
                 }
 
                 // Check for blockades along the path.
                 let blockades: Vec<Loc> = board.get_blockades();
                 let mut move_path = Path::started(color.clone(), start_loc)
                     .take(distance);
-                let has_blockade_on_path: bool =
-                    move_path.any(|path_loc| blockades.contains(&path_loc));
-
-                if has_blockade_on_path {
-                    return false;
+                if let Some(blockade) =
+                    move_path.find(|path_loc| blockades.contains(path_loc)) {
+                    return Err(IllegalMove::BlockadeOnPath(blockade));
                 }
 
                 // Make sure we don't overshoot home.
                 if start < 100 {
                     // Moving from main ring.
                     let start_loc: Loc = Loc::Spot { index: start };
-                    let mut move_path: Vec<Loc> = Path::started(color,
-                                                                start_loc)
-                            .take(distance)
-                            .collect();
+                    let move_path: Vec<Loc> = Path::started(color, start_loc)
+                        .take(distance)
+                        .collect();
                     if move_path.len() < distance &&
                        move_path[move_path.len() - 1] == Loc::Home {
-                        return false;
+                        return Err(IllegalMove::OvershootsHome);
                     }
                 } else {
                     let home_row_entrance: usize = Board::get_home_row(&color);
                     if start + distance > home_row_entrance + HOME_ROW_LENGTH {
-                        return false;
+                        return Err(IllegalMove::OvershootsHome);
                     }
                 }
 
-                true
+                Ok(())
             }
         }
     }
@@ -604,6 +907,74 @@ impl Board {
         }
     }
 
+    /// Checks if a team has won the game, i.e. all of both partners'
+    /// pawns are home, and returns the partners if so. A color whose
+    /// partner isn't on the board plays as a team of one.
+    pub fn has_team_winner(&self) -> Option<(Color, Color)> {
+        self.positions
+            .keys()
+            .map(|clr| (*clr, Board::get_partner(clr)))
+            .find(|&(clr, partner)| {
+                      self.has_finished(&clr) &&
+                      (!self.positions.contains_key(&partner) ||
+                       self.has_finished(&partner))
+                  })
+    }
+
+    /// Checks whether a player may move pawns of the given color.
+    ///
+    /// Players can always move their own pawns. In team play they can
+    /// also move their partner's pawns, once all of their own are home.
+    pub fn can_move_pawns_of(&self,
+                             mover: &Color,
+                             pawn_color: &Color,
+                             rules: &RuleSet)
+                             -> bool {
+        if mover == pawn_color {
+            return true;
+        }
+
+        rules.partners && *pawn_color == Board::get_partner(mover) &&
+        self.positions.contains_key(pawn_color) &&
+        self.has_finished(mover) && !self.has_finished(pawn_color)
+    }
+
+    /// Returns the colors that have pawns on the board.
+    pub fn colors(&self) -> Vec<Color> {
+        self.positions.keys().cloned().collect()
+    }
+
+    /// Checks whether all of a player's pawns are home.
+    pub fn has_finished(&self, color: &Color) -> bool {
+        self.get_pawns_by_color(color)
+            .iter()
+            .all(|&loc| loc == Loc::Home)
+    }
+
+    /// Administer a doubles penalty, returning a new board where the
+    /// player's furthest pawn on the main ring is sent back to the nest.
+    /// Pawns in the home row or at home are safe from the penalty.
+    pub fn apply_doubles_penalty(&self, color: &Color) -> Board {
+        let pawns: PawnLocs = self.get_pawns_by_color(color);
+        let furthest: Option<(usize, Loc)> =
+            Board::sort_player_locs(color, pawns)
+                .into_iter()
+                .filter(|&(_, loc)| match loc {
+                            Loc::Spot { .. } => !Board::is_home_row(*color, loc),
+                            _ => false,
+                        })
+                .last();
+
+        let mut next_positions = self.positions.clone();
+        if let Some((id, _)) = furthest {
+            let mut next_pawns: PawnLocs = pawns;
+            next_pawns[id] = Loc::Nest;
+            next_positions.insert(*color, next_pawns);
+        }
+
+        Board { positions: next_positions }
+    }
+
     /// Checks to see if a location has a pawn there already
     pub fn full_safety_square(&self, dest_loc: Loc, color: Color) -> bool {
         //TODO some how make this not reuse code from can bop
@@ -718,6 +1089,17 @@ impl Board {
         }
     }
 
+    /// Associated function to return a player's partner in team play,
+    /// i.e. the player sitting opposite them.
+    pub fn get_partner(color: &Color) -> Color {
+        match *color {
+            Color::Red => Color::Yellow,
+            Color::Blue => Color::Green,
+            Color::Yellow => Color::Red,
+            Color::Green => Color::Blue,
+        }
+    }
+
     /// Associated function to return the home row entrance index for a player.
     pub fn get_home_row(color: &Color) -> usize {
         match *color {
@@ -730,17 +1112,30 @@ impl Board {
 
     /// Determine whether a player can bop some pawn on a given destination spot.
     pub fn can_bop(&self, bopper_color: Color, dest_loc: Loc) -> Option<Pawn> {
+        self.can_bop_with(bopper_color, dest_loc, &RuleSet::standard())
+    }
+
+    /// Determine whether a player can bop some pawn on a given destination
+    /// spot under the given rules.
+    pub fn can_bop_with(&self,
+                        bopper_color: Color,
+                        dest_loc: Loc,
+                        rules: &RuleSet)
+                        -> Option<Pawn> {
         // A pawn can bop if all of the following are true:
         // - dest index is not a safety spot,
         // - dest contains one pawn of a different color.
 
         // 1. If dest_index is safety,
-        //    a. bopper's entrance => MIGHT BE ABLE TO BOP, KEEP CHECKING
+        //    a. bopper's entrance => MIGHT BE ABLE TO BOP, KEEP CHECKING,
+        //       unless the rules make entrances safe
         //    b. any other safety => CANNOT BOP
         let bopper_entrance_index: usize = Board::get_entrance(&bopper_color);
         let bopper_entrance: Loc = Loc::Spot { index: bopper_entrance_index };
+        let can_bop_here: bool = rules.bop_on_entrance &&
+                                 dest_loc == bopper_entrance;
 
-        if Board::is_safety(dest_loc) && dest_loc != bopper_entrance {
+        if Board::is_safety(dest_loc) && !can_bop_here {
             return None;
         }
 
@@ -754,7 +1149,9 @@ impl Board {
         let has_blockade = |l: Loc| blockades.contains(&l);
 
         for (c, locs) in self.positions.iter() {
-            if *c == bopper_color {
+            // Partners can't bop each other in team play.
+            if *c == bopper_color ||
+               (rules.partners && *c == Board::get_partner(&bopper_color)) {
                 continue;
             }
 
@@ -963,6 +1360,192 @@ mod tests {
         assert_eq!(r1.color, r2.color);
     }
 
+    #[test]
+    // A two-player board only has pawns for the chosen colors.
+    fn two_player_board() {
+        let board = Board::with_colors(&[Color::Red, Color::Yellow]);
+
+        assert_eq!(board.colors(), vec![Color::Red, Color::Yellow]);
+        assert!(!board.xmlify().contains("blue"));
+        assert!(!board.xmlify().contains("green"));
+        assert_eq!(board.has_winner(), None);
+
+        let mut finished = board.clone();
+        finished
+            .positions
+            .insert(Color::Yellow, [Loc::Home; 4]);
+        assert_eq!(finished.has_winner(), Some(Color::Yellow));
+    }
+
+    #[test]
+    // Illegal moves say which rule they break.
+    fn explain_illegal_moves() {
+        let board: Board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 10 }, Loc::Spot { index: 103 },
+                           Loc::Nest, Loc::Spot { index: 60 }],
+            Color::Blue => [Loc::Spot { index: 14 }, Loc::Spot { index: 14 },
+                            Loc::Spot { index: 16 }, Loc::Nest],
+            Color::Yellow => [Loc::Spot { index: 4 }, Loc::Spot { index: 4 },
+                              Loc::Nest, Loc::Nest]
+        });
+        let dice: Dice = Dice { rolls: vec![2, 6] };
+        let main = |id: usize, start: usize, distance: usize| {
+            Move {
+                pawn: Pawn::new(id, Color::Red),
+                m_type: MoveType::MoveMain {
+                    start: start,
+                    distance: distance,
+                },
+            }
+        };
+        let enter: Move = Move {
+            pawn: Pawn::new(2, Color::Red),
+            m_type: MoveType::EnterPiece,
+        };
+
+        assert_eq!(Board::explain_move(&board, &dice, &main(0, 10, 2)), Ok(()));
+        assert_eq!(Board::explain_move(&board, &dice, &main(0, 12, 2)),
+                   Err(IllegalMove::PawnNotAtStart {
+                           expected: Loc::Spot { index: 12 },
+                           actual: Loc::Spot { index: 10 },
+                       }));
+        assert_eq!(Board::explain_move(&board, &dice, &main(3, 60, 3)),
+                   Err(IllegalMove::DistanceNotRolled(3)));
+        assert_eq!(Board::explain_move(&board, &dice, &main(0, 10, 6)),
+                   Err(IllegalMove::SafetyOccupied(Loc::Spot { index: 16 })));
+        assert_eq!(Board::explain_move(&board,
+                                       &Dice { rolls: vec![5] },
+                                       &main(0, 10, 5)),
+                   Err(IllegalMove::BlockadeOnPath(Loc::Spot { index: 14 })));
+        assert_eq!(Board::explain_move(&board,
+                                       &Dice { rolls: vec![6] },
+                                       &Move {
+                                            pawn: Pawn::new(1, Color::Red),
+                                            m_type: MoveType::MoveHome {
+                                                start: 103,
+                                                distance: 6,
+                                            },
+                                        }),
+                   Err(IllegalMove::OvershootsHome));
+        assert_eq!(Board::explain_move(&board, &dice, &enter),
+                   Err(IllegalMove::NeedsFiveToEnter));
+        assert_eq!(Board::explain_move(&board, &Dice { rolls: vec![5] }, &enter),
+                   Err(IllegalMove::EntranceBlockaded(Loc::Spot { index: 4 })));
+    }
+
+    #[test]
+    // A move that would put a blockade back together after it split up
+    // isn't left to play, so a turn can end without it.
+    fn blockade_cant_rejoin_to_finish_turn() {
+        let start: Board = Board::from(map!{
+            Color::Yellow => [Loc::Spot { index: 38 }, Loc::Spot { index: 38 },
+                              Loc::Home, Loc::Home],
+            Color::Green => [Loc::Spot { index: 40 }, Loc::Spot { index: 40 },
+                             Loc::Nest, Loc::Nest]
+        });
+        let board: Board = Board::from(map!{
+            Color::Yellow => [Loc::Spot { index: 38 }, Loc::Spot { index: 39 },
+                              Loc::Home, Loc::Home],
+            Color::Green => [Loc::Spot { index: 40 }, Loc::Spot { index: 40 },
+                             Loc::Nest, Loc::Nest]
+        });
+        let dice: Dice = Dice { rolls: vec![6, 6, 1] };
+        let rules: RuleSet = RuleSet::standard();
+
+        assert!(Board::has_valid_moves_with(&board, &dice, &Color::Yellow, &rules));
+        assert!(!Board::has_valid_moves_from(&start, &board, &dice, &Color::Yellow, &rules));
+    }
+
+    #[test]
+    // When entrances are safe, an opponent on one can't be bopped, so
+    // only one more pawn fits there.
+    fn entrance_fills_up_when_safe() {
+        let enter = |id: usize| {
+            Move {
+                pawn: Pawn::new(id, Color::Red),
+                m_type: MoveType::EnterPiece,
+            }
+        };
+        let dice: Dice = Dice { rolls: vec![5, 5] };
+        let board: Board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 4 }, Loc::Nest, Loc::Nest, Loc::Nest],
+            Color::Blue => [Loc::Spot { index: 4 }, Loc::Nest, Loc::Nest, Loc::Nest]
+        });
+
+        assert_eq!(Board::explain_move_with(&board, &dice, &enter(1), &RuleSet::simple()),
+                   Err(IllegalMove::EntranceFull(Loc::Spot { index: 4 })));
+        assert_eq!(Board::explain_move_with(&board, &dice, &enter(1), &RuleSet::standard()),
+                   Ok(()));
+    }
+
+    #[test]
+    // Impossible boards report every violation, and real positions
+    // have none.
+    fn validate_board() {
+        assert_eq!(Board::new().validate(), Ok(()));
+        assert_eq!(Board::from(map!{
+            Color::Red => [Loc::Spot { index: 11 }, Loc::Spot { index: 40 },
+                           Loc::Spot { index: 40 }, Loc::Spot { index: 103 }],
+            Color::Blue => [Loc::Spot { index: 11 }, Loc::Home, Loc::Nest, Loc::Nest]
+        }).validate(), Ok(()));
+
+        let board: Board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 30 }, Loc::Spot { index: 30 },
+                           Loc::Spot { index: 30 }, Loc::Spot { index: 69 }],
+            Color::Blue => [Loc::Spot { index: 40 }, Loc::Spot { index: 108 },
+                            Loc::Spot { index: 103 }, Loc::Nest],
+            Color::Green => [Loc::Spot { index: 40 }, Loc::Nest, Loc::Nest, Loc::Nest]
+        });
+        assert_eq!(board.validate(),
+                   Err(vec![Violation::OffBoard(Pawn::new(3, Color::Red), 69),
+                            Violation::OffBoard(Pawn::new(1, Color::Blue), 108),
+                            Violation::WrongHomeRow(Pawn::new(2, Color::Blue), 103),
+                            Violation::TooManyPawns(Loc::Spot { index: 30 }),
+                            Violation::SharedSpot(Loc::Spot { index: 40 })]));
+    }
+
+    #[test]
+    // Hashing moves incrementally gives the same hash as hashing
+    // the resulting board, and moving the same pawns in either order
+    // reaches the same hash.
+    fn zobrist_incremental_matches_full() {
+        let board: Board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 10 }, Loc::Spot { index: 20 }, Loc::Nest, Loc::Nest],
+            Color::Blue => [Loc::Spot { index: 22 }, Loc::Nest, Loc::Nest, Loc::Nest]
+        });
+        let rules: RuleSet = RuleSet::standard();
+        let mv = |id: usize, start: usize, distance: usize| {
+            Move {
+                pawn: Pawn::new(id, Color::Red),
+                m_type: MoveType::MoveMain {
+                    start: start,
+                    distance: distance,
+                },
+            }
+        };
+        let hash: u64 = board.zobrist(&Color::Red);
+
+        // Pawn 1 bops Blue's pawn on 22, then pawn 0 moves.
+        let (MoveResult(b1, _), h1) =
+            board.handle_move_hashed(mv(1, 20, 2), &rules, hash).unwrap();
+        assert_eq!(h1, b1.zobrist(&Color::Red));
+        let (MoveResult(b2, _), h2) =
+            b1.handle_move_hashed(mv(0, 10, 3), &rules, h1).unwrap();
+        assert_eq!(h2, b2.zobrist(&Color::Red));
+
+        // The other order gets to the same position.
+        let (MoveResult(b3, _), h3) =
+            board.handle_move_hashed(mv(0, 10, 3), &rules, hash).unwrap();
+        let (MoveResult(b4, _), h4) =
+            b3.handle_move_hashed(mv(1, 20, 2), &rules, h3).unwrap();
+        assert_eq!(b4, b2);
+        assert_eq!(h4, h2);
+
+        assert!(h2 != b2.zobrist(&Color::Blue));
+        assert_eq!(zobrist::pass_turn(h2, &Color::Red, &Color::Blue),
+                   b2.zobrist(&Color::Blue));
+    }
+
     #[test]
     // Location sorting correctly handles the Home.
     fn sort_player_locs_with_home() {
@@ -1036,6 +1619,30 @@ mod tests {
                         (2, Loc::Spot { index: 49 })]);
     }
 
+    #[test]
+    // The doubles penalty sends the furthest pawn on the main ring home,
+    // skipping pawns in the home row.
+    fn doubles_penalty_furthest_main_ring_pawn() {
+        let board: Board = Board::from(map!{
+            Color::Green => [Loc::Spot { index: 57 },
+                             Loc::Spot { index: 402 },
+                             Loc::Spot { index: 10 },
+                             Loc::Home]
+        });
+
+        assert_eq!(board.apply_doubles_penalty(&Color::Green),
+                   Board::from(map!{
+                       Color::Green => [Loc::Spot { index: 57 },
+                                        Loc::Spot { index: 402 },
+                                        Loc::Nest,
+                                        Loc::Home]
+                   }));
+
+        // Nothing to penalize when no pawns are on the main ring.
+        let board: Board = Board::new();
+        assert_eq!(board.apply_doubles_penalty(&Color::Green), board);
+    }
+
     #[test]
     // A pawn of Color A can bop a single pawn of Color B.
     fn can_bop_other_pawn() {
@@ -1061,6 +1668,68 @@ mod tests {
                    });
     }
 
+    #[test]
+    // In team play, partners can't bop each other, but can still bop
+    // the other team.
+    fn partners_cannot_bop_each_other() {
+        let board: Board = Board::from(map!{
+            Color::Yellow => [Loc::Spot { index: 14 }, Loc::Nest, Loc::Nest, Loc::Nest],
+            Color::Blue => [Loc::Spot { index: 29 }, Loc::Nest, Loc::Nest, Loc::Nest]
+        });
+        let rules = RuleSet { partners: true, ..RuleSet::standard() };
+
+        assert!(board
+                    .can_bop_with(Color::Red, Loc::Spot { index: 14 }, &rules)
+                    .is_none());
+        assert!(board
+                    .can_bop_with(Color::Red, Loc::Spot { index: 29 }, &rules)
+                    .is_some());
+    }
+
+    #[test]
+    // A team wins once all eight of its pawns are home, and a player
+    // can only move their partner's pawns once their own are home.
+    fn team_winner_and_partner_moves() {
+        let rules = RuleSet { partners: true, ..RuleSet::standard() };
+        let mut board: Board = Board::from(map!{
+            Color::Red => [Loc::Home; 4],
+            Color::Blue => [Loc::Home; 4],
+            Color::Yellow => [Loc::Spot { index: 20 }, Loc::Home, Loc::Home, Loc::Home]
+        });
+
+        assert_eq!(board.has_winner(), Some(Color::Red));
+        assert_eq!(board.has_team_winner(), None);
+        assert!(board.can_move_pawns_of(&Color::Red, &Color::Yellow, &rules));
+        assert!(!board.can_move_pawns_of(&Color::Yellow, &Color::Red, &rules));
+        assert!(!board.can_move_pawns_of(&Color::Red,
+                                         &Color::Yellow,
+                                         &RuleSet::standard()));
+        assert!(Board::has_valid_moves_with(&board,
+                                            &Dice { rolls: vec![3] },
+                                            &Color::Red,
+                                            &rules));
+
+        board
+            .positions
+            .insert(Color::Yellow, [Loc::Home; 4]);
+        assert_eq!(board.has_team_winner(),
+                   Some((Color::Red, Color::Yellow)));
+    }
+
+    #[test]
+    // Under the simple rules, entrances are as safe as any other safety.
+    fn simple_rules_cannot_bop_off_entrance() {
+        let board: Board = Board::from(map!{
+            Color::Green => [Loc::Spot { index: 4 }, Loc::Nest, Loc::Nest, Loc::Nest]
+        });
+
+        assert!(board
+                    .can_bop_with(Color::Red,
+                                  Loc::Spot { index: 4 },
+                                  &RuleSet::simple())
+                    .is_none());
+    }
+
     #[test]
     // A pawn of Color A cannot bop another pawn of Color A.
     fn cannot_bop_own_pawn() {
@@ -1350,4 +2019,20 @@ mod tests {
 
         assert_eq!(tm.next(), (Loc::Home, Some(HOME_BONUS)));
     }
+
+    #[test]
+    // The simple rules don't award a bonus for getting home.
+    fn main_to_home_without_bonus() {
+        let tm = TestMove {
+            color: Color::Blue,
+            start_index: 4,
+            distance: 20,
+        };
+        let MoveResult(board, bonus) = tm.make_board()
+            .handle_move_with(tm.make_move(), &RuleSet::simple())
+            .unwrap();
+
+        assert_eq!(board.get_pawn_loc(&Color::Blue, 0), Loc::Home);
+        assert_eq!(bonus, None);
+    }
 }
@@ -0,0 +1,435 @@
+use super::board::{Board, Bonus, Color, Loc, Pawn, PawnLocs};
+use super::constants::*;
+use super::game::{Move, MoveType};
+use super::rules::RuleSet;
+use super::zobrist;
+
+/// Number of squares a pawn can stand on: the main ring,
+/// followed by each color's home row.
+const SQUARES: usize = 68 + 4 * 7;
+
+/// Square number for pawns in the nest.
+const NEST: u8 = 254;
+
+/// Square number for pawns that made it home.
+const HOME: u8 = 255;
+
+/// Colors in the order of their indices, which matches their
+/// order as `Board` keys.
+static INDEX_COLORS: [Color; 4] =
+    [Color::Red, Color::Green, Color::Blue, Color::Yellow];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// A fixed-size board for searching through many positions quickly.
+///
+/// Every location is a square number, and the board keeps a count of
+/// each color's pawns on every square. That makes blockade and bop
+/// checks constant time, and moves update the board in place instead
+/// of cloning a map. Convert to and from `Board` with `from_board`
+/// and `to_board`.
+pub struct CompactBoard {
+    /// Square of every pawn, indexed by color and then pawn id.
+    pawns: [[u8; 4]; 4],
+    /// Number of pawns of each color on every square.
+    occupancy: [[u8; 4]; SQUARES],
+    /// Bit set of the colors with pawns on the board.
+    present: u8,
+    /// Zobrist hash of the pawns, kept up to date as they move.
+    hash: u64,
+}
+
+impl CompactBoard {
+    /// Build a compact copy of a board.
+    ///
+    /// Panics if a pawn is off the board. Boards from the network or a
+    /// `Game` never are; check others with `Board::validate` first.
+    pub fn from_board(board: &Board) -> CompactBoard {
+        let mut compact: CompactBoard = CompactBoard {
+            pawns: [[NEST; 4]; 4],
+            occupancy: [[0; 4]; SQUARES],
+            present: 0,
+            hash: 0,
+        };
+
+        for (clr, locs) in board.positions.iter() {
+            let c: usize = color_index(clr);
+            compact.present |= 1 << c;
+
+            for (id, &loc) in locs.iter().enumerate() {
+                let sq: u8 = square(loc);
+                compact.pawns[c][id] = sq;
+                if is_square(sq) {
+                    compact.occupancy[sq as usize][c] += 1;
+                }
+            }
+        }
+
+        compact.hash = board.zobrist_pawns();
+        compact
+    }
+
+    /// Zobrist hash of the position with the given player to move.
+    /// Agrees with `Board::zobrist`.
+    pub fn zobrist(&self, to_move: &Color) -> u64 {
+        self.hash ^ zobrist::side_key(to_move)
+    }
+
+    /// Convert back to an ordinary board.
+    pub fn to_board(&self) -> Board {
+        let mut board: Board = Board::with_colors(&[]);
+
+        for (c, clr) in INDEX_COLORS.iter().enumerate() {
+            if self.present & (1 << c) == 0 {
+                continue;
+            }
+
+            let mut locs: PawnLocs = [Loc::Nest; 4];
+            for id in 0..4 {
+                locs[id] = to_loc(self.pawns[c][id]);
+            }
+            board.positions.insert(*clr, locs);
+        }
+
+        board
+    }
+
+    /// Checks the location of a pawn.
+    pub fn get_pawn_loc(&self, color: &Color, id: usize) -> Loc {
+        to_loc(self.pawns[color_index(color)][id])
+    }
+
+    /// Checks whether all of a player's pawns are home.
+    pub fn has_finished(&self, color: &Color) -> bool {
+        self.pawns[color_index(color)]
+            .iter()
+            .all(|&sq| sq == HOME)
+    }
+
+    /// Checks whether there is a blockade on a location.
+    pub fn is_blockade(&self, loc: Loc) -> bool {
+        match to_square(loc) {
+            Some(sq) if is_square(sq) => {
+                self.occupancy[sq as usize]
+                    .iter()
+                    .any(|&count| count >= 2)
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns a list of all blockades on the board.
+    pub fn get_blockades(&self) -> Vec<Loc> {
+        (0..SQUARES)
+            .filter(|&sq| self.occupancy[sq].iter().any(|&count| count >= 2))
+            .map(|sq| to_loc(sq as u8))
+            .collect()
+    }
+
+    /// Determine whether a player can bop some pawn on a given destination
+    /// spot under the given rules. Agrees with `Board::can_bop_with`.
+    pub fn can_bop_with(&self,
+                        bopper_color: Color,
+                        dest_loc: Loc,
+                        rules: &RuleSet)
+                        -> Option<Pawn> {
+        let sq: u8 = match to_square(dest_loc) {
+            Some(sq) if is_square(sq) => sq,
+            _ => return None,
+        };
+
+        let bopper_entrance: Loc =
+            Loc::Spot { index: Board::get_entrance(&bopper_color) };
+        let can_bop_here: bool = rules.bop_on_entrance &&
+                                 dest_loc == bopper_entrance;
+
+        if Board::is_safety(dest_loc) && !can_bop_here {
+            return None;
+        }
+
+        // Nobody can be bopped out of a blockade.
+        if self.is_blockade(dest_loc) {
+            return None;
+        }
+
+        let bopper: usize = color_index(&bopper_color);
+        let partner: usize = color_index(&Board::get_partner(&bopper_color));
+
+        for c in 0..4 {
+            if c == bopper || (rules.partners && c == partner) ||
+               self.occupancy[sq as usize][c] != 1 {
+                continue;
+            }
+
+            // Exactly one of this color's pawns is on the square.
+            let id: usize = self.pawns[c]
+                .iter()
+                .position(|&p| p == sq)
+                .unwrap();
+
+            return Some(Pawn::new(id, INDEX_COLORS[c]));
+        }
+
+        None
+    }
+
+    /// Move a pawn to a location, keeping the occupancy counts up to date.
+    ///
+    /// Panics if the location is off the board.
+    pub fn move_pawn(&mut self, pawn: Pawn, loc: Loc) -> () {
+        let c: usize = color_index(&pawn.color);
+        let from: u8 = self.pawns[c][pawn.id];
+        let to: u8 = square(loc);
+        if from == to {
+            return;
+        }
+
+        self.hash ^= zobrist::pawn_key(&pawn.color,
+                                       to_loc(from),
+                                       self.count(c, from) - 1) ^
+                     zobrist::pawn_key(&pawn.color, loc, self.count(c, to));
+
+        if is_square(from) {
+            self.occupancy[from as usize][c] -= 1;
+        }
+        if is_square(to) {
+            self.occupancy[to as usize][c] += 1;
+        }
+        self.pawns[c][pawn.id] = to;
+    }
+
+    /// Number of pawns of the color with the given index on a square.
+    fn count(&self, c: usize, sq: u8) -> usize {
+        if is_square(sq) {
+            self.occupancy[sq as usize][c] as usize
+        } else {
+            self.pawns[c]
+                .iter()
+                .filter(|&&p| p == sq)
+                .count()
+        }
+    }
+
+    /// Play a move in place, sending any bopped pawn to the nest, and
+    /// return the bonus it earned. Agrees with `Board::handle_move_with`.
+    pub fn apply_move(&mut self,
+                      m: Move,
+                      rules: &RuleSet)
+                      -> Result<Option<Bonus>, &'static str> {
+        let Move { pawn, m_type } = m;
+        let mut bonus: Option<Bonus> = None;
+
+        let next_loc: Loc = match m_type {
+            MoveType::EnterPiece => {
+                Loc::Spot { index: Board::get_entrance(&pawn.color) }
+            }
+            MoveType::MoveHome { start, distance } |
+            MoveType::MoveMain { start, distance } => {
+                if distance == 0 {
+                    return Err("Couldn't get move end");
+                }
+
+                let mut sq: u8 = match to_square(Loc::Spot { index: start }) {
+                    Some(sq) => sq,
+                    None => return Err("Spot is off the board"),
+                };
+                for _ in 0..distance {
+                    match step(&pawn.color, sq) {
+                        Some(next) => sq = next,
+                        None => break,
+                    }
+                }
+
+                let next_loc: Loc = to_loc(sq);
+                if next_loc == Loc::Home {
+                    bonus = rules.home_bonus();
+                }
+                next_loc
+            }
+        };
+
+        if let Some(bopped) = self.can_bop_with(pawn.color, next_loc, rules) {
+            self.move_pawn(bopped, Loc::Nest);
+            bonus = rules.bop_bonus();
+        }
+
+        self.move_pawn(pawn, next_loc);
+        Ok(bonus)
+    }
+}
+
+/// Index of a color in the compact board's tables.
+fn color_index(color: &Color) -> usize {
+    *color as usize
+}
+
+/// Whether a square number is on the board, rather than the nest or home.
+fn is_square(sq: u8) -> bool {
+    (sq as usize) < SQUARES
+}
+
+/// Square number of the first spot in a color's home row.
+fn home_row_square(color: &Color) -> u8 {
+    square(Loc::Spot { index: Board::get_home_row(color) })
+}
+
+/// Square number of a location, or `None` for a spot that isn't on the
+/// board.
+pub fn to_square(loc: Loc) -> Option<u8> {
+    match loc {
+        Loc::Nest => Some(NEST),
+        Loc::Home => Some(HOME),
+        Loc::Spot { index } if index < BOARD_SIZE => Some(index as u8),
+        Loc::Spot { index } if index < 100 => None,
+        Loc::Spot { index } => {
+            let row: usize = index / 100 - 1;
+            let offset: usize = index % 100;
+            if row < 4 && offset < HOME_ROW_LENGTH {
+                Some((BOARD_SIZE + row * HOME_ROW_LENGTH + offset) as u8)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Square number of a location that's known to be on the board.
+fn square(loc: Loc) -> u8 {
+    match to_square(loc) {
+        Some(sq) => sq,
+        None => panic!("{:?} is off the board", loc),
+    }
+}
+
+fn to_loc(sq: u8) -> Loc {
+    match sq {
+        NEST => Loc::Nest,
+        HOME => Loc::Home,
+        sq if (sq as usize) < BOARD_SIZE => Loc::Spot { index: sq as usize },
+        sq => {
+            let row: usize = (sq as usize - BOARD_SIZE) / HOME_ROW_LENGTH;
+            let offset: usize = (sq as usize - BOARD_SIZE) % HOME_ROW_LENGTH;
+            Loc::Spot { index: (row + 1) * 100 + offset }
+        }
+    }
+}
+
+/// The square after the given one on a color's path, like `Path::next`.
+fn step(color: &Color, sq: u8) -> Option<u8> {
+    match sq {
+        HOME => None,
+        NEST => Some(Board::get_entrance(color) as u8),
+        sq if (sq as usize) < BOARD_SIZE => {
+            if sq as usize == Board::get_exit(color) {
+                Some(home_row_square(color))
+            } else {
+                Some(((sq as usize + 1) % BOARD_SIZE) as u8)
+            }
+        }
+        sq => {
+            let offset: usize = (sq as usize - BOARD_SIZE) % HOME_ROW_LENGTH;
+            if offset + 1 == HOME_ROW_LENGTH {
+                Some(HOME)
+            } else {
+                Some(sq + 1)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use board::MoveResult;
+    use dice::Dice;
+    use gametree::GameTree;
+    use rand::{Rng, SeedableRng, StdRng};
+
+    #[test]
+    /// Converting to a compact board and back gives the same board.
+    fn compact_round_trip() {
+        let board: Board = Board::from(map!{
+            Color::Red => [Loc::Home, Loc::Spot { index: 4 }, Loc::Nest, Loc::Spot { index: 101 }],
+            Color::Blue => [Loc::Nest, Loc::Spot { index: 202 }, Loc::Home, Loc::Spot { index: 21 }],
+            Color::Green => [Loc::Spot { index: 55 }, Loc::Nest, Loc::Spot { index: 406 }, Loc::Home]
+        });
+        assert_eq!(CompactBoard::from_board(&board).to_board(), board);
+
+        let two_player: Board = Board::with_colors(&[Color::Red, Color::Yellow]);
+        assert_eq!(CompactBoard::from_board(&two_player).to_board(),
+                   two_player);
+    }
+
+    #[test]
+    /// Blockade and bop checks agree with the ordinary board.
+    fn compact_blockades_and_bops() {
+        let board: Board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 13 }, Loc::Spot { index: 13 }, Loc::Nest, Loc::Nest],
+            Color::Blue => [Loc::Spot { index: 14 }, Loc::Spot { index: 4 }, Loc::Nest, Loc::Nest]
+        });
+        let compact: CompactBoard = CompactBoard::from_board(&board);
+        let rules: RuleSet = RuleSet::standard();
+
+        assert_eq!(compact.get_blockades(), board.get_blockades());
+        assert!(compact.is_blockade(Loc::Spot { index: 13 }));
+        for &index in [4, 13, 14, 20].iter() {
+            let loc: Loc = Loc::Spot { index: index };
+            assert_eq!(compact.can_bop_with(Color::Green, loc, &rules),
+                       board.can_bop_with(Color::Green, loc, &rules));
+            assert_eq!(compact.can_bop_with(Color::Red, loc, &rules),
+                       board.can_bop_with(Color::Red, loc, &rules));
+        }
+    }
+
+    #[test]
+    /// Spots off the board have no square, and checks and moves that
+    /// ask about them fail without panicking.
+    fn off_board_spots() {
+        assert_eq!(to_square(Loc::Spot { index: 67 }), Some(67));
+        assert_eq!(to_square(Loc::Spot { index: 406 }), Some(95));
+        for &index in [68, 99, 107, 500].iter() {
+            assert_eq!(to_square(Loc::Spot { index: index }), None);
+        }
+
+        let mut compact: CompactBoard = CompactBoard::from_board(&Board::new());
+        let off_board: Loc = Loc::Spot { index: 500 };
+        assert!(!compact.is_blockade(off_board));
+        assert_eq!(compact.can_bop_with(Color::Red, off_board, &RuleSet::standard()),
+                   None);
+        assert!(compact.apply_move(Move {
+                                       pawn: Pawn::new(0, Color::Red),
+                                       m_type: MoveType::MoveMain {
+                                           start: 500,
+                                           distance: 3,
+                                       },
+                                   },
+                                   &RuleSet::standard())
+                    .is_err());
+    }
+
+    #[test]
+    /// Playing random legal moves on both boards keeps them in step.
+    fn compact_moves_match_board() {
+        let mut rng: StdRng = SeedableRng::from_seed(&[7][..]);
+        let rules: RuleSet = RuleSet::standard();
+        let mut board: Board = Board::new();
+        let mut compact: CompactBoard = CompactBoard::from_board(&board);
+
+        for turn in 0..200 {
+            let color: Color = INDEX_COLORS[turn % 4];
+            let (dice, _) = Dice::roll_with(&mut rng, true);
+            let options: Vec<Move> = GameTree::new(board.clone(), dice, color)
+                .collect();
+
+            let m: Move = match rng.choose(&options) {
+                Some(&m) => m,
+                None => continue,
+            };
+
+            let MoveResult(next_board, bonus) = board.handle_move(m).unwrap();
+            assert_eq!(compact.apply_move(m, &rules), Ok(bonus));
+            assert_eq!(compact.to_board(), next_board);
+            assert_eq!(compact.zobrist(&color), next_board.zobrist(&color));
+            board = next_board;
+        }
+    }
+}
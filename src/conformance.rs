@@ -0,0 +1,647 @@
+use std::cell::Cell;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use super::board::{Board, Color, Loc};
+use super::deserialize;
+use super::dice::Dice;
+use super::game::{Game, GameOutcome, Move};
+use super::player::Player;
+use super::serialize;
+
+/// How long a client has to answer each message by default.
+pub static DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to listen for a client wrongly answering a malformed message.
+pub static QUIET_PERIOD: Duration = Duration::from_millis(200);
+
+/// Messages no client should act on: an unknown message, a do-move cut
+/// off partway through, and text that isn't XML at all.
+pub static MALFORMED_MESSAGES: &'static [&'static str] = &["<hello> </hello>",
+                                                           "<do-move> <board>",
+                                                           "do-move"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// Something the protocol asks of every client.
+pub enum Requirement {
+    /// Connects to the server, or starts up, for every game.
+    Connects,
+    /// Answers `<start-game>` with a non-empty `<name>`.
+    AnswersStartGame,
+    /// Answers `<do-move>` with well-formed `<moves>`.
+    AnswersDoMove,
+    /// Answers `<doubles-penalty>` with `<void>`.
+    AnswersDoublesPenalty,
+    /// Answers every message within the time limit.
+    AnswersInTime,
+    /// Stays connected until the game is over.
+    StaysConnected,
+    /// Only makes legal moves, and uses every die it can.
+    PlaysLegalTurns,
+    /// Uses the bonus distance earned by bopping or moving home.
+    TakesBonuses,
+    /// Uses all four dice of doubles, and moves again after them.
+    PlaysDoubles,
+    /// Neither answers nor hangs up on a message it doesn't understand.
+    IgnoresMalformedMessages,
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Requirement::Connects => write!(f, "connects for every game"),
+            Requirement::AnswersStartGame => write!(f, "answers <start-game> with <name>"),
+            Requirement::AnswersDoMove => write!(f, "answers <do-move> with <moves>"),
+            Requirement::AnswersDoublesPenalty => {
+                write!(f, "answers <doubles-penalty> with <void>")
+            }
+            Requirement::AnswersInTime => write!(f, "answers in time"),
+            Requirement::StaysConnected => write!(f, "stays connected"),
+            Requirement::PlaysLegalTurns => write!(f, "plays legal turns"),
+            Requirement::TakesBonuses => write!(f, "takes bonuses"),
+            Requirement::PlaysDoubles => write!(f, "plays doubles"),
+            Requirement::IgnoresMalformedMessages => write!(f, "ignores malformed messages"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A requirement a client broke, and how.
+pub struct Failure {
+    pub requirement: Requirement,
+    pub detail: String,
+}
+
+impl Failure {
+    fn new(requirement: Requirement, detail: &str) -> Failure {
+        Failure {
+            requirement: requirement,
+            detail: detail.to_string(),
+        }
+    }
+}
+
+/// A scripted game: Red is dealt the given rolls from the given position
+/// for one turn. Every roll after the last repeats it.
+pub struct Scenario {
+    pub name: &'static str,
+    pub board: Board,
+    pub rolls: Vec<(Dice, bool)>,
+    /// Messages sent before the first `<do-move>`, which the client
+    /// should ignore.
+    pub malformed: Vec<&'static str>,
+    /// The requirement a turn that breaks the rules fails.
+    pub rule: Requirement,
+}
+
+/// The scenarios every client is put through.
+pub fn scenarios() -> Vec<Scenario> {
+    let spread: Board = Board::from(map!{
+        Color::Red => [Loc::Spot { index: 10 }, Loc::Spot { index: 30 },
+                       Loc::Nest, Loc::Nest],
+        Color::Yellow => [Loc::Spot { index: 20 }, Loc::Nest,
+                          Loc::Nest, Loc::Nest]
+    });
+    let entered: Board = Board::from(map!{
+        Color::Red => [Loc::Spot { index: 10 }, Loc::Spot { index: 30 },
+                       Loc::Spot { index: 45 }, Loc::Spot { index: 60 }]
+    });
+
+    vec![Scenario {
+             name: "legal turn",
+             board: spread.clone(),
+             rolls: vec![(Dice { rolls: vec![3, 4] }, false)],
+             malformed: Vec::new(),
+             rule: Requirement::PlaysLegalTurns,
+         },
+         Scenario {
+             // The 3 can only take the pawn in the home row home, and
+             // every turn then has the 10 to use.
+             name: "bonus",
+             board: Board::from(map!{
+                 Color::Red => [Loc::Spot { index: 104 }, Loc::Spot { index: 25 },
+                                Loc::Home, Loc::Home],
+                 Color::Yellow => [Loc::Spot { index: 28 }, Loc::Spot { index: 33 },
+                                   Loc::Nest, Loc::Nest]
+             }),
+             rolls: vec![(Dice { rolls: vec![3, 5] }, false)],
+             malformed: Vec::new(),
+             rule: Requirement::TakesBonuses,
+         },
+         Scenario {
+             name: "doubles",
+             board: entered.clone(),
+             rolls: vec![(Dice { rolls: vec![3, 3, 4, 4] }, true),
+                         (Dice { rolls: vec![1, 2] }, false)],
+             malformed: Vec::new(),
+             rule: Requirement::PlaysDoubles,
+         },
+         Scenario {
+             // Doubles three times running earn a penalty instead of
+             // a third move.
+             name: "doubles penalty",
+             board: entered,
+             rolls: vec![(Dice { rolls: vec![2, 2, 5, 5] }, true)],
+             malformed: Vec::new(),
+             rule: Requirement::PlaysDoubles,
+         },
+         Scenario {
+             name: "malformed messages",
+             board: spread,
+             rolls: vec![(Dice { rolls: vec![3, 4] }, false)],
+             malformed: MALFORMED_MESSAGES.to_vec(),
+             rule: Requirement::PlaysLegalTurns,
+         }]
+}
+
+/// A line-by-line connection to a client under test, either over a
+/// socket or over the standard input and output of a process.
+///
+/// Lines are read on a thread of their own, so reads can time out
+/// whatever the client is connected by.
+pub struct Connection {
+    writer: Box<Write + Send>,
+    lines: Receiver<String>,
+    stream: Option<TcpStream>,
+    child: Option<Child>,
+}
+
+impl Connection {
+    /// Start the command, and talk to it over its standard input and output.
+    pub fn spawn(command: &mut Command) -> io::Result<Connection> {
+        let mut child: Child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let mut connection: Connection = Connection::from_parts(Box::new(stdout), Box::new(stdin));
+        connection.child = Some(child);
+        Ok(connection)
+    }
+
+    /// Talk to a client over a socket.
+    pub fn from_stream(stream: TcpStream) -> io::Result<Connection> {
+        stream.set_nodelay(true)?;
+        let mut connection: Connection =
+            Connection::from_parts(Box::new(stream.try_clone()?), Box::new(stream.try_clone()?));
+        connection.stream = Some(stream);
+        Ok(connection)
+    }
+
+    /// Wait up to `timeout` for a client to connect to the listener.
+    pub fn accept(listener: &TcpListener, timeout: Duration) -> io::Result<Connection> {
+        let deadline: Instant = Instant::now() + timeout;
+        listener.set_nonblocking(true)?;
+        let accepted: io::Result<TcpStream> = loop {
+            match listener.accept() {
+                Ok((stream, _)) => break Ok(stream),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock &&
+                              Instant::now() < deadline => {
+                    thread::sleep(Duration::from_millis(10))
+                }
+                Err(e) => break Err(e),
+            }
+        };
+        listener.set_nonblocking(false)?;
+
+        let stream: TcpStream = accepted?;
+        stream.set_nonblocking(false)?;
+        Connection::from_stream(stream)
+    }
+
+    fn from_parts(reader: Box<Read + Send>, writer: Box<Write + Send>) -> Connection {
+        let (line, lines) = mpsc::channel();
+        thread::spawn(move || for read in BufReader::new(reader).lines() {
+                          let text: String = match read {
+                              Ok(text) => text.trim().to_string(),
+                              Err(_) => break,
+                          };
+                          if !text.is_empty() && line.send(text).is_err() {
+                              break;
+                          }
+                      });
+
+        Connection {
+            writer: writer,
+            lines: lines,
+            stream: None,
+            child: None,
+        }
+    }
+
+    /// Send a message to the client, on a line of its own.
+    pub fn send(&mut self, msg: &str) -> Result<(), &'static str> {
+        debug!("Sending {}", msg);
+        self.writer
+            .write_all((msg.to_string() + "\n").as_bytes())
+            .and_then(|_| self.writer.flush())
+            .map_err(|_| "Player disconnected")
+    }
+
+    /// Wait up to `timeout` for the client's next message, returning
+    /// `None` if they don't send one in time.
+    pub fn receive(&mut self, timeout: Duration) -> Result<Option<String>, &'static str> {
+        match self.lines.recv_timeout(timeout) {
+            Ok(msg) => {
+                debug!("Received {}", msg);
+                Ok(Some(msg))
+            }
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err("Player disconnected"),
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Some(ref stream) = self.stream {
+            stream.shutdown(Shutdown::Both).ok();
+        }
+        if let Some(ref mut child) = self.child {
+            child.kill().ok();
+            child.wait().ok();
+        }
+    }
+}
+
+/// Plays Red in a scenario's game over the connection, noting the
+/// first requirement the client breaks.
+struct Subject<'a> {
+    connection: &'a mut Connection,
+    timeout: Duration,
+    malformed: Vec<&'static str>,
+    failure: Option<Failure>,
+}
+
+impl<'a> Subject<'a> {
+    /// Note a broken requirement, and fail the call that broke it.
+    fn fail<T>(&mut self, requirement: Requirement, detail: &str) -> Result<T, &'static str> {
+        info!("Client failed to meet \"{}\": {}", requirement, detail);
+        self.failure = Some(Failure::new(requirement, detail));
+        Err("Client broke the protocol")
+    }
+
+    /// Send a message and wait for the client's answer.
+    fn ask(&mut self, msg: &str) -> Result<String, &'static str> {
+        if self.connection.send(msg).is_err() {
+            return self.fail(Requirement::StaysConnected, "hung up");
+        }
+        let timeout: Duration = self.timeout;
+        match self.connection.receive(timeout) {
+            Ok(Some(answer)) => Ok(answer),
+            Ok(None) => self.fail(Requirement::AnswersInTime, &format!("no answer to {}", msg)),
+            Err(_) => self.fail(Requirement::StaysConnected, "hung up"),
+        }
+    }
+
+    /// Send the malformed messages, if they haven't been sent yet, and
+    /// make sure the client doesn't answer them.
+    fn send_malformed(&mut self) -> Result<(), &'static str> {
+        let malformed: Vec<&'static str> = self.malformed.drain(..).collect();
+        if malformed.is_empty() {
+            return Ok(());
+        }
+
+        for msg in malformed.iter() {
+            if self.connection.send(msg).is_err() {
+                return self.fail(Requirement::IgnoresMalformedMessages, "hung up");
+            }
+        }
+        match self.connection.receive(QUIET_PERIOD) {
+            Ok(None) => Ok(()),
+            Ok(Some(answer)) => {
+                self.fail(Requirement::IgnoresMalformedMessages,
+                          &format!("answered with {}", answer))
+            }
+            Err(_) => self.fail(Requirement::IgnoresMalformedMessages, "hung up"),
+        }
+    }
+}
+
+impl<'a> Player for Subject<'a> {
+    fn start_game(&mut self, color: Color) -> Result<String, &'static str> {
+        let answer: String = self.ask(&serialize::xml_start_game(&color))?;
+        match deserialize::deserialize_name(&answer) {
+            Ok(name) => Ok(name),
+            Err(e) => self.fail(Requirement::AnswersStartGame, &format!("{}: {}", e, answer)),
+        }
+    }
+
+    fn do_move(&mut self, board: Board, dice: Dice) -> Result<Vec<Move>, &'static str> {
+        self.send_malformed()?;
+        let answer: String = self.ask(&serialize::xml_protocol_do_move(&board, &dice))?;
+        match deserialize::try_deserialize_protocol_moves(&answer) {
+            Ok(moves) => Ok(moves),
+            Err(e) => self.fail(Requirement::AnswersDoMove, &format!("{}: {}", e, answer)),
+        }
+    }
+
+    fn doubles_penalty(&mut self) -> Result<(), &'static str> {
+        let answer: String = self.ask(&serialize::xml_doubles_penalty())?;
+        match deserialize::deserialize_void(&answer) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                self.fail(Requirement::AnswersDoublesPenalty,
+                          &format!("{}: {}", e, answer))
+            }
+        }
+    }
+}
+
+/// Plays the server's side of the protocol against a client, putting it
+/// through every scenario and reporting the requirements it breaks.
+///
+/// Each scenario is a game of its own, played over a new connection.
+pub struct Harness {
+    connect: Box<FnMut() -> io::Result<Connection>>,
+    timeout: Duration,
+}
+
+impl Harness {
+    /// Test a client that connects to the listener for every game.
+    pub fn listen(listener: TcpListener) -> Harness {
+        Harness {
+            connect: Box::new(move || Connection::accept(&listener, DEFAULT_TIMEOUT)),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Test a client that's a program, started afresh for every game
+    /// and spoken to over its standard input and output.
+    pub fn spawn(program: String, args: Vec<String>) -> Harness {
+        Harness {
+            connect: Box::new(move || Connection::spawn(Command::new(&program).args(&args))),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Give the client this long to answer each message instead of
+    /// `DEFAULT_TIMEOUT`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Harness {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Put the client through every scenario.
+    pub fn run(&mut self) -> Report {
+        Report {
+            results: scenarios()
+                .iter()
+                .map(|scenario| (scenario.name, self.run_scenario(scenario)))
+                .collect(),
+        }
+    }
+
+    /// Play one scenario, returning the requirement the client broke,
+    /// if any.
+    pub fn run_scenario(&mut self, scenario: &Scenario) -> Option<Failure> {
+        let mut connection: Connection = match (self.connect)() {
+            Ok(connection) => connection,
+            Err(e) => return Some(Failure::new(Requirement::Connects, &e.to_string())),
+        };
+        let mut subject: Subject = Subject {
+            connection: &mut connection,
+            timeout: self.timeout,
+            malformed: scenario.malformed.clone(),
+            failure: None,
+        };
+
+        let outcome: GameOutcome = {
+            let mut game: Game = Game::from_board(scenario.board.clone())
+                .expect("Scenarios start from possible positions");
+            game.seat_player(Box::new(&mut subject), Color::Red)
+                .expect("Red is in every scenario");
+
+            let next: Cell<usize> = Cell::new(0);
+            let roll = |_| {
+                let i: usize = next.get();
+                next.set(i + 1);
+                scenario.rolls[i.min(scenario.rolls.len() - 1)].clone()
+            };
+            game.play(roll, 1)
+        };
+
+        match (subject.failure, outcome.stats[&Color::Red].violation.as_ref()) {
+            (Some(failure), _) => Some(failure),
+            (None, Some(violation)) => Some(Failure::new(scenario.rule, violation)),
+            (None, None) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// How a client fared in each scenario.
+pub struct Report {
+    /// Each scenario's name, and the requirement the client broke in it.
+    pub results: Vec<(&'static str, Option<Failure>)>,
+}
+
+impl Report {
+    /// Whether the client met every requirement.
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|&(_, ref failure)| failure.is_none())
+    }
+
+    /// Every requirement the client broke, each listed once.
+    pub fn violations(&self) -> Vec<Requirement> {
+        let mut violations: Vec<Requirement> = self.results
+            .iter()
+            .filter_map(|&(_, ref failure)| failure.as_ref().map(|f| f.requirement))
+            .collect();
+        violations.sort();
+        violations.dedup();
+        violations
+    }
+
+    /// A line per scenario saying how the client did, followed by the
+    /// requirements it broke.
+    pub fn report(&self) -> String {
+        let mut report: String = String::new();
+        for &(name, ref failure) in self.results.iter() {
+            report = report +
+                     &match *failure {
+                          Some(ref f) => {
+                              format!("{:<20} FAILED {}: {}\n", name, f.requirement, f.detail)
+                          }
+                          None => format!("{:<20} ok\n", name),
+                      };
+        }
+
+        if self.passed() {
+            report + "\nAll requirements met.\n"
+        } else {
+            report = report + "\nRequirements broken:\n";
+            for requirement in self.violations() {
+                report = report + &format!("  {}\n", requirement);
+            }
+            report
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use autoplayers::GreedyPlayer;
+    use gametree;
+    use game::MoveType;
+    use rules::RuleSet;
+    use zobrist::TranspositionTable;
+
+    /// A client that connects for every game, and answers the way
+    /// `answer` says to. `None` means not to answer at all.
+    fn client<F>(listener: &TcpListener, games: usize, answer: F) -> thread::JoinHandle<()>
+        where F: Fn(&str) -> Option<String> + Send + 'static
+    {
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || for _ in 0..games {
+                          let stream: TcpStream = TcpStream::connect(addr).unwrap();
+                          let mut writer: TcpStream = stream.try_clone().unwrap();
+                          for line in BufReader::new(stream).lines() {
+                              let line: String = match line {
+                                  Ok(line) => line,
+                                  Err(_) => break,
+                              };
+                              // The harness can hang up before it reads an
+                              // answer, e.g. once the client is out of time.
+                              if let Some(msg) = answer(line.trim()) {
+                                  if writer.write_all((msg + "\n").as_bytes()).is_err() {
+                                      break;
+                                  }
+                              }
+                          }
+                      })
+    }
+
+    /// Answers like a client should, moving like a `GreedyPlayer`.
+    fn greedy(msg: &str) -> Option<String> {
+        if msg.starts_with("<start-game>") {
+            Some("<name> Greedy </name>".to_string())
+        } else if msg.starts_with("<do-move>") && msg.ends_with("</do-move>") {
+            let (board, dice) = deserialize::deserialize_do_move(msg.to_string());
+            let moves: Vec<Move> = GreedyPlayer::new("Greedy".to_string(), Color::Red)
+                .do_move(board, dice)
+                .unwrap();
+            Some(serialize::xml_moves(&moves))
+        } else if msg.starts_with("<doubles-penalty>") {
+            Some(serialize::xml_void())
+        } else {
+            None
+        }
+    }
+
+    #[test]
+    /// Every scenario's position survives the trip to the client, and
+    /// every complete turn in the bonus scenario takes the home bonus.
+    fn scenarios_test_what_they_say() {
+        for scenario in scenarios() {
+            let xml: String = serialize::xml_protocol_do_move(&scenario.board,
+                                                              &scenario.rolls[0].0);
+            assert_eq!(deserialize::deserialize_do_move(xml).0, scenario.board);
+        }
+
+        let bonus: Scenario = scenarios().into_iter().find(|s| s.name == "bonus").unwrap();
+        let mut table: TranspositionTable<()> = TranspositionTable::new(1 << 12);
+        let outcomes = gametree::turn_outcomes(&bonus.board,
+                                               &bonus.rolls[0].0,
+                                               Color::Red,
+                                               &RuleSet::standard(),
+                                               &mut table);
+        assert!(!outcomes.is_empty());
+        for (moves, board) in outcomes {
+            let takes_bonus: bool = moves.iter().any(|m| match m.m_type {
+                MoveType::MoveMain { distance, .. } |
+                MoveType::MoveHome { distance, .. } => distance == 10,
+                MoveType::EnterPiece => false,
+            });
+            assert!(takes_bonus, "{:?}", moves);
+            assert_eq!(board.get_pawn_loc(&Color::Red, 0), Loc::Home);
+        }
+    }
+
+    #[test]
+    /// A client that follows the protocol meets every requirement.
+    fn well_behaved_client_passes() {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let games = client(&listener, scenarios().len(), greedy);
+
+        let report: Report = Harness::listen(listener).run();
+        games.join().unwrap();
+        assert!(report.passed(), "{}", report.report());
+        assert!(report.report().contains("All requirements met"));
+    }
+
+    #[test]
+    /// A client that always passes is caught breaking the rules, and
+    /// answering the do-move that's cut off.
+    fn passing_client_fails() {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let games = client(&listener, scenarios().len(), |msg| if msg.starts_with("<start-game>") {
+            Some("<name> Passer </name>".to_string())
+        } else if msg.starts_with("<do-move>") {
+            Some(serialize::xml_moves(&Vec::new()))
+        } else {
+            greedy(msg)
+        });
+
+        let report: Report = Harness::listen(listener).run();
+        games.join().unwrap();
+        assert_eq!(report.results[0].1,
+                   Some(Failure::new(Requirement::PlaysLegalTurns, "Invalid turn")));
+        assert_eq!(report.violations(),
+                   vec![Requirement::PlaysLegalTurns,
+                        Requirement::TakesBonuses,
+                        Requirement::PlaysDoubles,
+                        Requirement::IgnoresMalformedMessages]);
+        assert!(report.report().contains("FAILED takes bonuses"));
+    }
+
+    #[test]
+    /// A client that moves well, but answers every message it's sent
+    /// with moves, is caught on the messages it answers wrongly.
+    fn careless_client_fails() {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let games = client(&listener, scenarios().len(), |msg| match greedy(msg) {
+            Some(ref answer) if answer == &serialize::xml_void() => {
+                Some(serialize::xml_moves(&Vec::new()))
+            }
+            Some(answer) => Some(answer),
+            None => Some(serialize::xml_moves(&Vec::new())),
+        });
+
+        let report: Report = Harness::listen(listener).run();
+        games.join().unwrap();
+        assert_eq!(report.violations(),
+                   vec![Requirement::AnswersDoublesPenalty,
+                        Requirement::IgnoresMalformedMessages]);
+    }
+
+    #[test]
+    /// A client that never connects, or stops answering, is reported.
+    fn silent_clients_fail() {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let legal_turn: Scenario = scenarios().remove(0);
+        let mut harness: Harness = Harness {
+            connect: Box::new(move || Connection::accept(&listener, Duration::from_millis(50))),
+            timeout: Duration::from_millis(50),
+        };
+        assert_eq!(harness.run_scenario(&legal_turn).map(|f| f.requirement),
+                   Some(Requirement::Connects));
+
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let games = client(&listener, 1, |msg| if msg.starts_with("<start-game>") {
+            Some("<name> Silent </name>".to_string())
+        } else {
+            None
+        });
+        let mut harness: Harness = Harness::listen(listener).with_timeout(Duration::from_millis(100));
+        assert_eq!(harness.run_scenario(&legal_turn).map(|f| f.requirement),
+                   Some(Requirement::AnswersInTime));
+        games.join().unwrap();
+    }
+}
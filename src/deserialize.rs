@@ -59,7 +59,7 @@ pub fn deserialize_start_game(request: String) -> Color {
         match reader.read_event(&mut buf) {
             Ok(Event::Start(ref e)) => {
                 match e.name() {
-                    b"start-game" => println!("start game"),
+                    b"start-game" => trace!("start game"),
                     _ => (),
                 }
             }
@@ -109,7 +109,7 @@ pub fn move_string_to_vec_string(xml: String) -> Vec<String> {
                 // <enter-piece> yadadada </enter-piece> becomes Vec<String> => vec![enter-piece, enterpiece args, ...]
                 // This vector is then concatenated with the other move types
                 match e.name() {
-                    b"moves" => println!("test"),
+                    b"moves" => trace!("moves"),
                     b"enter-piece" => {
                         txt.push(e.unescape_and_decode(&reader)
                                      .unwrap())
@@ -276,6 +276,16 @@ pub fn split_up_vec_xml_string(vec_xml_string: Vec<String>) -> Board {
     let mut start = vec_xml_string.clone();
 
     let mut main = start.split_off(start_end_index);
+
+    // Colors without any pawns on the board aren't playing, so keep track
+    // of the colors we see. Pawns in the nest only tell us their color.
+    let mut seen: Vec<Color> = trim_xml(&start)
+        .iter()
+        .skip_while(|x| **x != "start".to_string())
+        .skip(1)
+        .step_by(2)
+        .map(|color_string| string_to_color(color_string.clone()))
+        .collect();
     let mut main_end_index = main.clone()
         .iter()
         .position(|x| *x == "home-rows".to_string())
@@ -311,6 +321,7 @@ pub fn split_up_vec_xml_string(vec_xml_string: Vec<String>) -> Board {
                     let curr_element = it.next().unwrap();
                     let curr_color: Color = string_to_color(curr_element
                                                                 .clone());
+                    seen.push(curr_color);
                     let mut curr_id = it.next()
                         .unwrap()
                         .parse::<usize>()
@@ -344,6 +355,7 @@ pub fn split_up_vec_xml_string(vec_xml_string: Vec<String>) -> Board {
                     let curr_element = it.next().unwrap();
                     let curr_color: Color = string_to_color(curr_element
                                                                 .clone());
+                    seen.push(curr_color);
                     let mut curr_id = it.next()
                         .unwrap()
                         .parse::<usize>()
@@ -377,8 +389,9 @@ pub fn split_up_vec_xml_string(vec_xml_string: Vec<String>) -> Board {
     // Skip the "main" tag in the vector of strings
     loop {
         if let Some(color_string) = it.next() {
-            println!("{}", color_string);
+            trace!("Piece color {}", color_string);
             let curr_color: Color = string_to_color(color_string.clone());
+            seen.push(curr_color);
             let mut curr_id = it.next()
                 .unwrap()
                 .parse::<usize>()
@@ -396,6 +409,11 @@ pub fn split_up_vec_xml_string(vec_xml_string: Vec<String>) -> Board {
         }
     }
 
+    board.positions = board
+        .positions
+        .into_iter()
+        .filter(|&(clr, _)| seen.contains(&clr))
+        .collect();
     board
 
 
@@ -412,6 +430,25 @@ pub fn deserialize_do_move(xml: String) -> (Board, Dice) {
     (board, dice)
 }
 
+/// Deserialize a do-move message, rejecting boards that couldn't
+/// happen in a real game. Returns an error instead of panicking if the
+/// message is malformed, and names every violation if the board is
+/// impossible.
+pub fn deserialize_valid_do_move(xml: String) -> Result<(Board, Dice), String> {
+    let (board, dice) = ::std::panic::catch_unwind(|| deserialize_do_move(xml))
+        .map_err(|_| "Malformed board or dice".to_string())?;
+    board
+        .validate()
+        .map_err(|violations| {
+                     violations
+                         .iter()
+                         .map(|v| v.to_string())
+                         .collect::<Vec<String>>()
+                         .join("; ")
+                 })?;
+    Ok((board, dice))
+}
+
 pub fn deserialize_dice(xml: String) -> Dice {
 
     /*
@@ -514,6 +551,192 @@ pub fn xml_board_to_vec_xml_string(xml: String) -> Vec<String> {
 }
 
 
+/// A request a client sends to the lobby before its game starts.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LobbyRequest {
+    /// List the games waiting for players.
+    ListGames,
+    /// Host a new game, optionally asking for a seat color.
+    CreateGame(Option<Color>),
+    /// Join the game with the given id, optionally asking for a seat color.
+    JoinGame(usize, Option<Color>),
+    /// Watch the game with the given id, whether it's waiting for
+    /// players or already underway.
+    WatchGame(usize),
+    /// Start the game now, without waiting for every seat to fill.
+    /// Only the host of a game can start it.
+    BeginGame,
+    /// Reconnect to a game in progress, with the session token from
+    /// its start-game.
+    Resume(String),
+}
+
+/// Deserialize a lobby request. Unlike the in-game messages, requests
+/// come from clients we don't control, so malformed ones are reported
+/// rather than trusted.
+pub fn deserialize_lobby_request(xml: &str) -> Result<LobbyRequest, &'static str> {
+    let (root, fields) = message_fields(xml)?;
+    let color: Option<Color> = match fields.get("color") {
+        Some(clr) => Some(parse_color(clr)?),
+        None => None,
+    };
+
+    match root.as_ref() {
+        "list-games" => Ok(LobbyRequest::ListGames),
+        "create-game" => Ok(LobbyRequest::CreateGame(color)),
+        "join-game" => {
+            let id: usize = fields
+                .get("id")
+                .and_then(|id| id.parse().ok())
+                .ok_or("Joining needs a game id")?;
+            Ok(LobbyRequest::JoinGame(id, color))
+        }
+        "watch-game" => {
+            fields
+                .get("id")
+                .and_then(|id| id.parse().ok())
+                .map(LobbyRequest::WatchGame)
+                .ok_or("Watching needs a game id")
+        }
+        "begin-game" => Ok(LobbyRequest::BeginGame),
+        "resume" => {
+            fields
+                .get("session")
+                .map(|token| LobbyRequest::Resume(token.clone()))
+                .ok_or("Resuming needs a session token")
+        }
+        _ => Err("Unknown lobby request"),
+    }
+}
+
+/// The session token in a start-game, if the server sent one.
+pub fn deserialize_session(xml: &str) -> Option<String> {
+    match message_fields(xml) {
+        Ok((ref root, ref fields)) if root == "start-game" => fields.get("session").cloned(),
+        _ => None,
+    }
+}
+
+/// Deserialize a player's answer to start-game, e.g. `<name> Sven </name>`.
+pub fn deserialize_name(xml: &str) -> Result<String, &'static str> {
+    match message_fields(xml)? {
+        (ref root, ref fields) if root == "name" => {
+            match fields.get("name") {
+                Some(name) if !name.is_empty() => Ok(name.clone()),
+                _ => Err("Player sent an empty name"),
+            }
+        }
+        _ => Err("Player didn't send a name"),
+    }
+}
+
+/// Deserialize a player's answer to a doubles penalty, which must be
+/// `<void> </void>`.
+pub fn deserialize_void(xml: &str) -> Result<(), &'static str> {
+    match message_fields(xml)? {
+        (ref root, ref fields) if root == "void" && fields.is_empty() => Ok(()),
+        _ => Err("Player didn't acknowledge the message"),
+    }
+}
+
+/// Deserialize a player's moves like `deserialize_moves`, returning an
+/// error instead of panicking if the message is malformed.
+pub fn try_deserialize_moves(xml: &str) -> Result<Vec<Move>, &'static str> {
+    match message_fields(xml)? {
+        (ref root, _) if root == "moves" => (),
+        _ => return Err("Player didn't send moves"),
+    }
+
+    let xml: String = xml.to_string();
+    ::std::panic::catch_unwind(|| deserialize_moves(xml))
+        .map_err(|_| "Player sent malformed moves")
+}
+
+/// Deserialize a client's moves like `try_deserialize_moves`, translating
+/// their spots from the numbering clients use into ours. This undoes
+/// `Move::xmlify`; see `serialize::xml_protocol_board`.
+pub fn try_deserialize_protocol_moves(xml: &str) -> Result<Vec<Move>, &'static str> {
+    let moves: Vec<Move> = try_deserialize_moves(xml)?;
+    Ok(moves
+           .into_iter()
+           .map(|m| {
+                    let m_type: MoveType = match m.m_type {
+                        MoveType::MoveMain { start, distance } => {
+                            MoveType::MoveMain {
+                                start: (start + 50) % BOARD_SIZE,
+                                distance: distance,
+                            }
+                        }
+                        MoveType::MoveHome { start, distance } => {
+                            MoveType::MoveHome {
+                                start: start + Board::get_home_row(&m.pawn.color),
+                                distance: distance,
+                            }
+                        }
+                        MoveType::EnterPiece => MoveType::EnterPiece,
+                    };
+                    Move {
+                        m_type: m_type,
+                        pawn: m.pawn,
+                    }
+                })
+           .collect())
+}
+
+/// The name of a message's outermost tag, and the text inside each tag
+/// within it. Text directly inside the outermost tag is filed under its
+/// name. Only the first occurrence of each tag is kept.
+fn message_fields(xml: &str) -> Result<(String, BTreeMap<String, String>), &'static str> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+    let mut buf = Vec::new();
+    let mut root: Option<String> = None;
+    let mut open: Option<String> = None;
+    let mut fields: BTreeMap<String, String> = BTreeMap::new();
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(ref e)) => {
+                let name: String = String::from_utf8_lossy(e.name()).into_owned();
+                if root.is_none() {
+                    root = Some(name);
+                } else {
+                    open = Some(name);
+                }
+            }
+            Ok(Event::Text(e)) => {
+                let text: String = e.unescape_and_decode(&reader)
+                    .map_err(|_| "Message is not valid XML")?;
+                let tag: Option<String> = open.take().or_else(|| root.clone());
+                match tag {
+                    Some(tag) => {
+                        fields.entry(tag).or_insert(text);
+                    }
+                    None => return Err("Message is not XML"),
+                }
+            }
+            Ok(Event::End(_)) => open = None,
+            Ok(Event::Eof) => break,
+            Err(_) => return Err("Message is not valid XML"),
+            _ => (),
+        }
+        buf.clear();
+    }
+
+    root.map(|root| (root, fields))
+        .ok_or("Message is empty")
+}
+
+fn parse_color(string: &str) -> Result<Color, &'static str> {
+    match string.to_lowercase().as_ref() {
+        "red" => Ok(Color::Red),
+        "blue" => Ok(Color::Blue),
+        "yellow" => Ok(Color::Yellow),
+        "green" => Ok(Color::Green),
+        _ => Err("Unknown color"),
+    }
+}
+
+
 
 mod tests {
     use super::*;
@@ -577,6 +800,17 @@ mod tests {
         assert!(Board::new() == deserialize_board(Board::new().xmlify()));
     }
 
+    #[test]
+    /// Colors missing from the xml have no pawns on the board.
+    fn deserialize_board_missing_colors() {
+        let mut two_player: Board = Board::with_colors(&[Color::Blue, Color::Green]);
+        two_player
+            .positions
+            .insert(Color::Blue, [Loc::Home, Loc::Home, Loc::Nest, Loc::Nest]);
+
+        assert_eq!(deserialize_board(two_player.xmlify()), two_player);
+    }
+
     #[test]
     /// Deserialize the board given to us on the website
     fn deserialize_board_real_test() {
@@ -629,4 +863,83 @@ mod tests {
                 deserialize_do_move(serialize::xml_do_move(&board, &dice)));
     }
 
+    #[test]
+    /// Every lobby request reads back as itself, and anything else is
+    /// an error.
+    fn deserialize_lobby_request_test() {
+        assert_eq!(deserialize_lobby_request(&serialize::xml_list_games()),
+                   Ok(LobbyRequest::ListGames));
+        assert_eq!(deserialize_lobby_request(&serialize::xml_create_game(None)),
+                   Ok(LobbyRequest::CreateGame(None)));
+        assert_eq!(deserialize_lobby_request(&serialize::xml_join_game(3, Some(Color::Green))),
+                   Ok(LobbyRequest::JoinGame(3, Some(Color::Green))));
+        assert_eq!(deserialize_lobby_request(&serialize::xml_watch_game(4)),
+                   Ok(LobbyRequest::WatchGame(4)));
+        assert_eq!(deserialize_lobby_request(&serialize::xml_begin_game()),
+                   Ok(LobbyRequest::BeginGame));
+        assert_eq!(deserialize_lobby_request(&serialize::xml_resume("abc123")),
+                   Ok(LobbyRequest::Resume("abc123".to_string())));
+
+        assert!(deserialize_lobby_request("<join-game> </join-game>").is_err());
+        assert!(deserialize_lobby_request("<watch-game> </watch-game>").is_err());
+        assert!(deserialize_lobby_request("<create-game> <color> pink </color> </create-game>")
+                    .is_err());
+        assert!(deserialize_lobby_request("<dance> </dance>").is_err());
+        assert!(deserialize_lobby_request("hello").is_err());
+    }
+
+    #[test]
+    /// The session token is read from a start-game, if it has one.
+    fn deserialize_session_test() {
+        let start: String = serialize::xml_start_game_with_session(&Color::Blue, "abc123");
+        assert_eq!(deserialize_session(&start), Some("abc123".to_string()));
+        assert_eq!(deserialize_start_game(start), Color::Blue);
+        assert_eq!(deserialize_session(&serialize::xml_start_game(&Color::Blue)), None);
+    }
+
+    #[test]
+    /// Answers only read as the message they were asked for.
+    fn deserialize_player_answers_test() {
+        assert_eq!(deserialize_name("<name> Sven </name>"), Ok("Sven".to_string()));
+        assert!(deserialize_name("<name> </name>").is_err());
+        assert!(deserialize_name("<void> </void>").is_err());
+
+        assert_eq!(deserialize_void(&serialize::xml_void()), Ok(()));
+        assert!(deserialize_void("<moves> </moves>").is_err());
+
+        let moves: Vec<Move> = vec![Move {
+                                        m_type: MoveType::EnterPiece,
+                                        pawn: Pawn::new(0, Color::Red),
+                                    }];
+        assert_eq!(try_deserialize_moves(&serialize::xml_moves(&moves)), Ok(moves));
+        assert_eq!(try_deserialize_moves("<moves> </moves>"), Ok(Vec::new()));
+        assert!(try_deserialize_moves("<moves> <enter-piece> </enter-piece> </moves>").is_err());
+        assert!(try_deserialize_moves("<name> Sven </name>").is_err());
+    }
+
+    #[test]
+    /// Moves written the way clients write them read back as they were.
+    fn deserialize_protocol_moves_test() {
+        let moves: Vec<Move> = vec![Move {
+                                        m_type: MoveType::EnterPiece,
+                                        pawn: Pawn::new(2, Color::Red),
+                                    },
+                                    Move {
+                                        m_type: MoveType::MoveHome {
+                                            start: 302,
+                                            distance: 3,
+                                        },
+                                        pawn: Pawn::new(1, Color::Yellow),
+                                    },
+                                    Move {
+                                        m_type: MoveType::MoveMain {
+                                            start: 60,
+                                            distance: 11,
+                                        },
+                                        pawn: Pawn::new(0, Color::Green),
+                                    }];
+        let xml: String = serialize::xml_moves(&moves);
+        assert_eq!(try_deserialize_protocol_moves(&xml), Ok(moves));
+    }
+
 }
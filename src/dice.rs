@@ -3,6 +3,8 @@
 extern crate rand;
 
 use super::game::{Move, MoveType};
+use super::rules::RuleSet;
+use super::notation;
 
 use self::rand::Rng;
 
@@ -43,6 +45,17 @@ impl Dice {
         Dice { rolls: Vec::new() }
     }
 
+    /// Write the rolls in position notation, e.g. `3,5`, or `-` if
+    /// there are none.
+    pub fn to_notation(&self) -> String {
+        notation::format_dice(self)
+    }
+
+    /// Read rolls written in position notation.
+    pub fn from_notation(s: &str) -> Result<Dice, &'static str> {
+        notation::parse_dice(s)
+    }
+
     /// Returns xml instance of Dice
     pub fn xmlify(&self) -> String {
         let xml_response: String = "<dice> ".to_string();
@@ -62,8 +75,17 @@ impl Dice {
     /// Takes a predicate which denotes whether or not to apply
     /// the doubles bonus, if doubles are rolled.
     pub fn roll(apply_doubles_bonus: bool) -> (Dice, bool) {
-        let d1: usize = rand::thread_rng().gen_range(1, 7);
-        let d2: usize = rand::thread_rng().gen_range(1, 7);
+        Dice::roll_with(&mut rand::thread_rng(), apply_doubles_bonus)
+    }
+
+    /// Initialize a new instance, drawing from the given random
+    /// number generator. Passing a seeded generator makes the
+    /// sequence of rolls reproducible.
+    pub fn roll_with<R: Rng>(rng: &mut R,
+                             apply_doubles_bonus: bool)
+                             -> (Dice, bool) {
+        let d1: usize = rng.gen_range(1, 7);
+        let d2: usize = rng.gen_range(1, 7);
 
         let is_doubles: bool = d1 == d2;
         let rolls: Vec<usize> = if is_doubles && apply_doubles_bonus {
@@ -80,6 +102,11 @@ impl Dice {
 
     /// Check whether the player can enter a new pawn.
     pub fn can_enter(&self) -> EntryMove {
+        self.can_enter_with(&RuleSet::standard())
+    }
+
+    /// Check whether the player can enter a new pawn under the given rules.
+    pub fn can_enter_with(&self, rules: &RuleSet) -> EntryMove {
         for (i, &d1) in self.rolls
                 .iter()
                 .enumerate() {
@@ -88,7 +115,11 @@ impl Dice {
                 return EntryMove::WithFive;
             }
 
-            // ...or any two rolls sum to 5.
+            // ...or, if the rules allow it, any two rolls sum to 5.
+            if !rules.enter_with_sum {
+                continue;
+            }
+
             for (j, &d2) in self.rolls
                     .iter()
                     .enumerate() {
@@ -103,8 +134,13 @@ impl Dice {
 
     /// Consume a game move from the available rolls.
     pub fn consume_move(&self, mv: &Move) -> Dice {
+        self.consume_move_with(mv, &RuleSet::standard())
+    }
+
+    /// Consume a game move from the available rolls under the given rules.
+    pub fn consume_move_with(&self, mv: &Move, rules: &RuleSet) -> Dice {
         match mv.m_type {
-            MoveType::EnterPiece => self.consume_entry_move(),
+            MoveType::EnterPiece => self.consume_entry_move_with(rules),
             MoveType::MoveHome { distance, .. } |
             MoveType::MoveMain { distance, .. } => {
                 self.consume_normal_move(distance)
@@ -132,7 +168,13 @@ impl Dice {
     /// Consume mini-move(s) necessary to enter a pawn.
     /// Returns a new struct with updated lists.
     pub fn consume_entry_move(&self) -> Dice {
-        match self.can_enter() {
+        self.consume_entry_move_with(&RuleSet::standard())
+    }
+
+    /// Consume mini-move(s) necessary to enter a pawn under the given rules.
+    /// Returns a new struct with updated lists.
+    pub fn consume_entry_move_with(&self, rules: &RuleSet) -> Dice {
+        match self.can_enter_with(rules) {
             EntryMove::WithFive => self.consume_normal_move(5),
             EntryMove::WithSum(x, y) => {
                 self.consume_normal_move(x)
@@ -188,6 +230,18 @@ mod tests {
         assert_eq!(max_index, 7);
     }
 
+    #[test]
+    /// Rolling with the same seed gives the same sequence of dice.
+    fn seeded_rolls_repeat() {
+        let mut rng_1: rand::StdRng = rand::SeedableRng::from_seed(&[42][..]);
+        let mut rng_2: rand::StdRng = rand::SeedableRng::from_seed(&[42][..]);
+
+        for _ in 0..100 {
+            assert_eq!(Dice::roll_with(&mut rng_1, true),
+                       Dice::roll_with(&mut rng_2, true));
+        }
+    }
+
     #[test]
     /// Enter with 1, 4.
     fn enter_1_4() {
@@ -223,4 +277,14 @@ mod tests {
         let dice = Dice { rolls: vec![3, 3, 6] };
         assert!(dice.can_enter() == EntryMove::NoEntry);
     }
+
+    #[test]
+    /// Parchís rules only enter with a 5 on one die.
+    fn parchis_cannot_enter_with_sum() {
+        let rules = RuleSet::parchis();
+        assert!(Dice { rolls: vec![1, 4] }.can_enter_with(&rules) ==
+                EntryMove::NoEntry);
+        assert!(Dice { rolls: vec![5, 4] }.can_enter_with(&rules) ==
+                EntryMove::WithFive);
+    }
 }
@@ -1,89 +1,396 @@
 #![allow(dead_code, unused_variables)]
 
 use std::collections::BTreeMap;
-use super::player::Player;
+use std::time::{Duration, Instant};
+use super::player::{Player, TurnEvent};
 use super::dice::Dice;
-use super::board::{Color, Board, Pawn, Loc, MoveResult};
+use super::board::{Color, Board, Pawn, PawnLocs, Loc, MoveResult};
 use super::constants::*;
 use super::serialize;
+use super::rules::RuleSet;
+use super::spectator::Spectator;
+use super::gametree;
+use super::timecontrol::{OnTimeout, TimeControl};
+use super::zobrist::TranspositionTable;
 
 
 /// Represents a game instance with connected Players.
 pub struct Game<'a> {
-    players: BTreeMap<Color, &'a (Player + 'a)>, // Players won't outlive game
+    players: BTreeMap<Color, Box<Player + 'a>>,
+    /// Watchers who see the whole game but don't play.
+    spectators: Vec<Box<Spectator + 'a>>,
     dice: Dice,
     board: Board,
+    /// Whether to keep playing for 2nd place and beyond once
+    /// someone has won.
+    play_to_completion: bool,
+    rules: RuleSet,
+    time_control: TimeControl,
 }
 
 impl<'a> Game<'a> {
     pub fn new() -> Game<'a> {
         Game {
             players: BTreeMap::new(),
+            spectators: Vec::new(),
             board: Board::new(),
             dice: Dice::new(),
+            play_to_completion: false,
+            rules: RuleSet::standard(),
+            time_control: TimeControl::unlimited(),
         }
     }
 
-    /// Register a new player with the game.
-    /// If there are no remaining colors available, return an error.
-    fn register_player<T: Player + 'a>(&mut self, p: &'a T) -> () {
-        let num_players_before = self.players.len();
+    /// Create a game for only the given colors, e.g. a two or
+    /// three player game. Other colors have no pawns on the board.
+    pub fn with_colors(colors: &[Color]) -> Game<'a> {
+        Game {
+            board: Board::with_colors(colors),
+            ..Game::new()
+        }
+    }
 
-        if self.players.len() <= COLORS.len() {
-            // Assign a color to the new player.
-            for color in COLORS.iter() {
-                if !self.players
-                        .contains_key(color) {
-                    self.players
-                        .insert(color.clone(), p);
-                }
-            }
-            assert!(self.players.len() >= num_players_before);
-            println!("Added player to the game. Now there are {} players.",
-                     self.players.len());
+    /// Create a game that starts from a given position, e.g. a saved
+    /// game. The colors on the board are the colors in the game.
+    /// Returns an error if the board is physically impossible.
+    pub fn from_board(board: Board) -> Result<Game<'a>, &'static str> {
+        Game::from_board_with(board, RuleSet::standard())
+    }
+
+    /// Create a game that starts from a given position and is played
+    /// under the given rules, e.g. a saved team game.
+    pub fn from_board_with(board: Board, rules: RuleSet) -> Result<Game<'a>, &'static str> {
+        if board.validate_with(&rules).is_err() {
+            return Err("Board is not a possible position");
+        }
+
+        Ok(Game { board: board, rules: rules, ..Game::new() })
+    }
+
+    /// Play by the given rules instead of the standard ones.
+    pub fn set_rules(&mut self, rules: RuleSet) -> () {
+        self.rules = rules;
+    }
+
+    /// Choose whether opposite colors play as partners.
+    /// See `RuleSet::partners` for how team play works.
+    pub fn set_team_play(&mut self, partners: bool) -> () {
+        self.rules.partners = partners;
+    }
+
+    /// Limit how long players can take to choose their moves.
+    /// See `TimeControl` for how the limits work.
+    pub fn set_time_control(&mut self, time_control: TimeControl) -> () {
+        self.time_control = time_control;
+    }
+
+    /// Choose whether the game ends as soon as someone wins, or keeps
+    /// going until the finishing order of every player is decided.
+    pub fn set_play_to_completion(&mut self, play_to_completion: bool) -> () {
+        self.play_to_completion = play_to_completion;
+    }
+
+    /// Register a new player with the game, seating them at the first
+    /// free color on the board.
+    /// If there are no remaining colors available, the player isn't added.
+    fn register_player<T: Player + 'a>(&mut self, p: T) -> () {
+        let free_color: Option<Color> = COLORS
+            .iter()
+            .cloned()
+            .find(|color| {
+                      self.board.positions.contains_key(color) &&
+                      !self.players.contains_key(color)
+                  });
+
+        if let Some(color) = free_color {
+            self.players.insert(color, Box::new(p));
+            info!("Added player to the game. Now there are {} players.",
+                  self.players.len());
         } else {
-            println!("Game is full :( Unable to add player. Sad!");
+            warn!("Game is full, unable to add player");
+        }
+    }
+
+    /// Seat a player at the given color. To keep using a player after
+    /// the game, seat a mutable reference to it.
+    /// If the color is already taken, or isn't playing in this game,
+    /// return an error.
+    pub fn seat_player(&mut self,
+                       p: Box<Player + 'a>,
+                       color: Color)
+                       -> Result<(), &'static str> {
+        if !self.board.positions.contains_key(&color) {
+            return Err("Color is not in this game");
+        }
+
+        if self.players.contains_key(&color) {
+            return Err("Color is already taken");
         }
+
+        self.players.insert(color, p);
+        Ok(())
+    }
+
+    /// Add a spectator, who is shown the start of the game, every turn
+    /// and the outcome. To keep using a spectator after the game, add
+    /// a mutable reference to it.
+    pub fn add_spectator(&mut self, s: Box<Spectator + 'a>) -> () {
+        self.spectators.push(s);
     }
 
     /// Start a game with the currently registered players.
-    fn start_game(&mut self) -> () {
+    fn start_game(&mut self) -> GameOutcome {
         // Notify all registered players.
         if self.players.is_empty() {
             panic!("Can't start a game with zero players");
         }
-        for (clr, p) in self.players.iter() {
-            // Send the xml request to magic black box that will
-            // send it over the wire. Wait, for a response. The response
-            // will be the players name. Since we don't have anything implementing
-            // the players name. We will drop it.
-            let xml_request = serialize::xml_start_game(&clr);
-            let xml_response = ""; /* receive magic */
-            // Use tcp to send xml response
-            // p.start_game("Place Holder".to_string());
-        }
-        println!("Starting game.");
-
-        while !self.is_game_over() {
-            for (clr, p) in self.players.iter() {
-                // TODO: How to make this work with mutable references?
-                // self.give_turn(clr, *p);
-                self.give_turn(clr, *p, Dice::roll);
-            }
-        }
+        info!("Starting game.");
+
+        let outcome: GameOutcome = self.play(Dice::roll, usize::max_value());
 
         // When Game is over, print an announcement.
-        println!("Game over.");
+        info!("Game over.");
+        outcome
     }
 
-    /// Inform player of a doubles penalty, and administer any changes
-    /// to the board.
-    fn give_doubles_penalty(&self, color: &Color) {
-        if let Some(&player) = self.players.get(color) {
-            player.doubles_penalty();
+    /// Play the game to the end with the currently registered players,
+    /// using `roll` to roll the dice.
+    ///
+    /// Players who break the rules are ejected from the game, and players
+    /// who have brought all their pawns home are no longer asked to move.
+    /// Players are shown every other player's turn as it ends, and the
+    /// outcome once the game is over. Spectators are shown the same,
+    /// including each player's own turns.
+    /// Players who run out of time are ejected or have a turn played
+    /// for them, depending on the game's `TimeControl`.
+    /// If the game isn't over after `turn_limit` turns, it is abandoned.
+    pub fn play<F>(&mut self, roll: F, turn_limit: usize) -> GameOutcome
+        where F: Fn(bool) -> (Dice, bool)
+    {
+        let mut outcome: GameOutcome = GameOutcome {
+            winner: None,
+            winning_team: None,
+            finishing_order: Vec::new(),
+            names: BTreeMap::new(),
+            turns: 0,
+            stats: BTreeMap::new(),
+            record: Vec::new(),
+        };
+
+        let colors: Vec<Color> = self.players.keys().cloned().collect();
+        let mut failed_to_start: Vec<(Color, &'static str)> = Vec::new();
+        for clr in colors.iter() {
+            outcome.stats.insert(*clr, PlayerStats::default());
+            let started: Result<String, &'static str> = match self.players.get_mut(clr) {
+                Some(p) => p.start_game(*clr),
+                None => continue,
+            };
+            match started {
+                Ok(name) => {
+                    outcome.names.insert(*clr, name);
+                }
+                Err(reason) => failed_to_start.push((*clr, reason)),
+            }
+        }
+
+        // Spectators see the game as it was set up, then anyone who
+        // couldn't start being ejected.
+        let (board, names, rules) = (self.board.clone(), outcome.names.clone(), self.rules);
+        self.spectators
+            .retain_mut(|s| match s.game_started(&board, &names, &rules) {
+                        Ok(()) => true,
+                        Err(e) => {
+                            warn!("Dropping a spectator who couldn't be shown the start: {}", e);
+                            false
+                        }
+                    });
+        for &(clr, reason) in failed_to_start.iter() {
+            self.eject(&clr, reason.to_string(), &mut outcome);
+        }
+
+        'game: while !self.is_game_over() {
+            for color in colors.iter() {
+                // Skip players that were ejected earlier in the game,
+                // and players who have already finished.
+                if !self.players.contains_key(color) || self.is_done(color) {
+                    continue;
+                }
+
+                if outcome.turns >= turn_limit {
+                    break 'game;
+                }
+                outcome.turns += 1;
+
+                // Take the player out of the game for their turn, so
+                // they can change while the game looks on.
+                let mut player: Box<Player + 'a> = match self.players.remove(color) {
+                    Some(p) => p,
+                    None => continue,
+                };
+                let mut clock: Duration = outcome.stats[color].time_used;
+                let result: Result<TurnSummary, String> =
+                    self.play_turn(color, &mut *player, &roll, &mut clock);
+                self.players.insert(*color, player);
+                if let Some(stats) = outcome.stats.get_mut(color) {
+                    stats.time_used = clock;
+                }
+
+                match result {
+                    Ok(mut summary) => {
+                        self.notify(&TurnEvent::Played {
+                                        color: *color,
+                                        rolls: summary.rolls.clone(),
+                                        doubles_penalty: summary.doubles_penalty,
+                                        board: summary.board.clone(),
+                                    },
+                                    Some(color));
+
+                        let timeouts: usize = summary
+                            .rolls
+                            .iter()
+                            .filter(|r| r.timed_out)
+                            .count();
+                        outcome.record.append(&mut summary.rolls);
+                        self.board = summary.board;
+                        self.dice = summary.dice;
+
+                        if let Some(stats) = outcome.stats.get_mut(color) {
+                            stats.turns += 1;
+                            stats.bops_made += summary.bopped.len();
+                            stats.timeouts += timeouts;
+                            if summary.doubles_penalty {
+                                stats.doubles_penalties += 1;
+                            }
+                        }
+
+                        for pawn in summary.bopped.iter() {
+                            if let Some(stats) = outcome
+                                   .stats
+                                   .get_mut(&pawn.color) {
+                                stats.bops_suffered += 1;
+                            }
+                        }
+
+                        // In team play a player can finish their
+                        // partner, so check everyone still playing.
+                        for clr in colors.iter() {
+                            if !self.players.contains_key(clr) ||
+                               outcome.finishing_order.contains(clr) ||
+                               !self.board.has_finished(clr) {
+                                continue;
+                            }
+
+                            outcome.finishing_order.push(*clr);
+                            if let Some(stats) = outcome
+                                   .stats
+                                   .get_mut(clr) {
+                                stats.finished_on_turn = Some(outcome.turns);
+                            }
+                        }
+                    }
+                    Err(violation) => {
+                        if let Some(stats) = outcome.stats.get_mut(color) {
+                            stats.turns += 1;
+                        }
+                        self.eject(color, violation, &mut outcome);
+                    }
+                }
+
+                if self.is_game_over() {
+                    break 'game;
+                }
+            }
+        }
+
+        if self.rules.partners {
+            // The winner is whichever partner finished first.
+            outcome.winning_team = self.board.has_team_winner();
+            if let Some((clr, partner)) = outcome.winning_team {
+                let first: Option<&Color> = outcome
+                    .finishing_order
+                    .iter()
+                    .find(|&&f| f == clr || f == partner);
+                outcome.winner = Some(*first.unwrap_or(&clr));
+            }
         } else {
-            panic!("No player with color {:?}", color);
+            outcome.winner = match outcome.finishing_order.first() {
+                Some(&clr) => Some(clr),
+                None => self.board.has_winner(),
+            };
+        }
+
+        for (clr, p) in self.players.iter_mut() {
+            if let Err(e) = p.game_over(&outcome) {
+                warn!("{:?} couldn't be told the game is over: {}", clr, e);
+            }
+        }
+        for s in self.spectators.iter_mut() {
+            if let Err(e) = s.game_over(&outcome) {
+                warn!("A spectator couldn't be told the game is over: {}", e);
+            }
+        }
+        outcome
+    }
+
+    /// Eject a player who broke the rules, telling everyone why.
+    /// Their pawns go back to the nest, so they can't block the
+    /// remaining players.
+    fn eject(&mut self,
+             color: &Color,
+             reason: String,
+             outcome: &mut GameOutcome)
+             -> () {
+        info!("Ejecting {:?}: {}", color, reason);
+        if let Some(stats) = outcome.stats.get_mut(color) {
+            stats.violation = Some(reason.clone());
+        }
+
+        self.notify(&TurnEvent::Ejected {
+                        color: *color,
+                        reason: reason,
+                    },
+                    None);
+        self.players.remove(color);
+        self.board
+            .positions
+            .insert(*color, [Loc::Nest; 4]);
+    }
+
+    /// Show an event to every player, except the one it's about,
+    /// and to every spectator. Spectators who can't be shown it are
+    /// dropped.
+    fn notify(&mut self, event: &TurnEvent, except: Option<&Color>) -> () {
+        for (clr, p) in self.players.iter_mut() {
+            if Some(clr) == except {
+                continue;
+            }
+            if let Err(e) = p.observe_turn(event) {
+                warn!("{:?} couldn't be shown {:?}: {}", clr, event, e);
+            }
+        }
+
+        self.spectators
+            .retain_mut(|s| match s.observe_turn(event) {
+                        Ok(()) => true,
+                        Err(e) => {
+                            warn!("Dropping a spectator who couldn't be shown {:?}: {}",
+                                  event,
+                                  e);
+                            false
+                        }
+                    });
+    }
+
+    /// Checks whether a player has nothing left to do. In team play,
+    /// players whose pawns are all home keep playing for their partner.
+    fn is_done(&self, color: &Color) -> bool {
+        if !self.board.has_finished(color) {
+            return false;
         }
+
+        let partner: Color = Board::get_partner(color);
+        !(self.rules.partners && self.players.contains_key(&partner) &&
+          !self.board.has_finished(&partner))
     }
 
     /// Predicate to check whether the game is over.
@@ -91,15 +398,58 @@ impl<'a> Game<'a> {
     ///
     /// - No players remaining (e.g. all of them cheated)
     /// - There is a winner (i.e. one player has all pawns home)
+    ///
+    /// In team play, the game ends when a team has won instead.
+    ///
+    /// When playing to completion, a winner doesn't end the game.
+    /// Instead it ends once at most one player is left who hasn't
+    /// finished, and somebody has.
     fn is_game_over(&self) -> bool {
-        self.board
-            .has_winner()
-            .is_some() || self.players.is_empty()
+        if self.players.is_empty() {
+            return true;
+        }
+
+        if self.rules.partners {
+            return self.board
+                       .has_team_winner()
+                       .is_some();
+        }
+
+        if !self.play_to_completion {
+            return self.board
+                       .has_winner()
+                       .is_some();
+        }
+
+        let unfinished: usize = self.players
+            .keys()
+            .filter(|clr| !self.board.has_finished(clr))
+            .count();
+        let finished: usize = self.players.len() - unfinished;
+
+        unfinished == 0 || (unfinished == 1 && finished > 0)
     }
 
     /// Give a turn to a player, keeping track of dice rolls
     /// and changes to the board.
     ///
+    /// Panics if the player breaks the rules; see `play_turn`.
+    fn give_turn<F>(&self,
+                    color: &Color,
+                    player: &mut Player,
+                    roll: F)
+                    -> (Board, Dice)
+        where F: Fn(bool) -> (Dice, bool)
+    {
+        match self.play_turn(color, player, &roll, &mut Duration::default()) {
+            Ok(summary) => (summary.board, summary.dice),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Play out a turn for a player, keeping track of dice rolls
+    /// and changes to the board. The game itself is not modified.
+    ///
     /// Chosen moves are validated at two points:
     ///
     /// (1) Every individual move is checked for validity with respect
@@ -109,108 +459,228 @@ impl<'a> Game<'a> {
     ///     are compared to check for cross-turn validity.
     ///     For instance, we can only enforce that blockades don't move
     ///     together if we validate across the entire turn.
-    fn give_turn<F>(&self,
+    ///
+    /// Returns an error describing the broken rule if either check fails,
+    /// or the player's own error if they fail to answer.
+    ///
+    /// `clock` holds the time the player has used so far in the game,
+    /// and is charged for the time they take this turn.
+    fn play_turn<F>(&self,
                     color: &Color,
-                    player: &Player,
-                    roll: F)
-                    -> (Board, Dice)
+                    player: &mut Player,
+                    roll: &F,
+                    clock: &mut Duration)
+                    -> Result<TurnSummary, String>
         where F: Fn(bool) -> (Dice, bool)
     {
+        let mut summary: TurnSummary = TurnSummary {
+            board: self.board.clone(),
+            dice: Dice::new(),
+            bopped: Vec::new(),
+            doubles_penalty: false,
+            rolls: Vec::new(),
+        };
         let mut doubles_rolled: i32 = 0;
 
         loop {
+            // Every roll after doubles starts from where the last one ended.
+            let start_board: Board = summary.board.clone();
+
             // Check if all the player's pawns are on the board.
             // This determines how the dice roll is handled.
-            let give_doubles_bonus: bool = self.board
-                .all_pawns_entered(color);
+            let give_doubles_bonus: bool = self.rules.doubles_bonus &&
+                                           start_board.all_pawns_entered(color);
             let (rolled_dice, is_doubles): (Dice, bool) =
                 roll(give_doubles_bonus);
 
             if is_doubles {
                 doubles_rolled += 1;
-                if doubles_rolled > 2 {
+                if doubles_rolled > 2 && self.rules.doubles_penalty {
                     // Assign doubles penalty.
-                    self.give_doubles_penalty(&color);
-                    break;
+                    player.doubles_penalty()?;
+                    summary.board = start_board.apply_doubles_penalty(color);
+                    summary.dice = Dice::new();
+                    summary.doubles_penalty = true;
+                    return Ok(summary);
                 }
             }
 
-            // To prevent invalid moves from messing up the game
-            // state, we capture and play individual moves on a
-            // copy of the board and game state.
-            let mut temp_board: Board = self.board.clone();
-            let mut temp_dice: Dice = rolled_dice;
-
-            let mut chosen_moves: Vec<Move> =
-                player.do_move(temp_board.clone(), temp_dice.clone());
-
-            // Check moves one at a time, using the temp board.
-            for &chosen_move in chosen_moves.iter() {
-                let is_valid_mini_move: bool =
-                    Board::is_valid_move(&temp_board, &temp_dice, &chosen_move);
+            let (chosen_moves, time_used, timed_out): (Vec<Move>, Duration, bool) =
+                self.choose_moves(color, player, &start_board, &rolled_dice, clock)?;
+            let (end_board, end_dice, mut bopped): (Board, Dice, Vec<Pawn>) =
+                self.check_roll(color, &start_board, &rolled_dice, &chosen_moves)?;
 
-                let move_result: Result<MoveResult,
-                                        &'static str> =
-                    temp_board.handle_move(chosen_move);
+            summary.bopped.append(&mut bopped);
+            summary.rolls.push(RecordedRoll {
+                                   color: *color,
+                                   board: start_board,
+                                   dice: rolled_dice,
+                                   moves: chosen_moves,
+                                   time_used: time_used,
+                                   timed_out: timed_out,
+                               });
+            summary.board = end_board;
+            summary.dice = end_dice;
 
-                if !is_valid_mini_move {
-                    panic!("Cheater cheater pumpkin eater");
-                }
+            // If the player rolled doubles, give another turn.
+            // Otherwise, return the summary of the turn.
+            if !is_doubles {
+                return Ok(summary);
+            }
+        }
+    }
 
-                // Check if the mini-move is valid at the turn level.
-                match move_result {
-                    Ok(MoveResult(next_board, bonus)) => {
-                        let is_valid_for_turn: bool =
-                            temp_board.is_valid_turn(&next_board,
-                                                     &temp_dice,
-                                                     *color);
+    /// Ask a player for their moves, within whatever time they have
+    /// left. `clock` is the time they've used so far, and is charged
+    /// for this move. A player with no time left isn't asked at all.
+    ///
+    /// Returns the moves to play, how long the player took, and whether
+    /// they ran out of time, in which case the moves were chosen for
+    /// them. Returns an error if the player fails to answer, or runs
+    /// out of time and forfeits.
+    fn choose_moves(&self,
+                    color: &Color,
+                    player: &mut Player,
+                    board: &Board,
+                    dice: &Dice,
+                    clock: &mut Duration)
+                    -> Result<(Vec<Move>, Duration, bool), &'static str> {
+        let allowance: Option<Duration> = self.time_control.allowance(*clock);
+        let mut time_used: Duration = Duration::default();
 
-                        if !is_valid_for_turn {
-                            panic!("Cheated");
-                        }
+        if allowance != Some(Duration::default()) {
+            player.set_time_limit(allowance);
+            let asked: Instant = Instant::now();
+            let answer: Result<Vec<Move>, &'static str> =
+                player.do_move(board.clone(), dice.clone());
 
-                        // Update temp_board, and add bonus if it exists.
-                        temp_board = next_board;
+            // Untimed games don't keep time, so that they play out
+            // the same way every time.
+            if !self.time_control.is_limited() {
+                return answer.map(|moves| (moves, time_used, false));
+            }
+            time_used = asked.elapsed();
+            *clock += time_used;
 
-                        match chosen_move.m_type {
-                            MoveType::EnterPiece => {
-                                temp_dice = temp_dice.consume_entry_move();
-                            }
-                            MoveType::MoveMain { distance, .. } |
-                            MoveType::MoveHome { distance, .. } => {
-                                temp_dice = temp_dice
-                                    .consume_normal_move(distance);
-                            }
-                        };
+            if allowance.map_or(true, |allowed| time_used <= allowed) {
+                return answer.map(|moves| (moves, time_used, false));
+            }
+        }
 
-                        if let Some(amt) = bonus {
-                            temp_dice = temp_dice.give_bonus(amt);
-                        }
-                    }
-                    Err(e) => panic!("Move failed"),
-                };
+        info!("{:?} ran out of time", color);
+        match self.time_control.on_timeout {
+            OnTimeout::Forfeit => Err("Out of time"),
+            OnTimeout::DefaultTurn => {
+                self.default_turn(color, board, dice)
+                    .map(|moves| (moves, time_used, true))
+                    .ok_or("Out of time")
             }
+        }
+    }
+
+    /// The turn played for a player who runs out of time: the first
+    /// complete turn from `GameTree` that passes every check.
+    fn default_turn(&self, color: &Color, board: &Board, dice: &Dice) -> Option<Vec<Move>> {
+        let mut table: TranspositionTable<()> = TranspositionTable::new(1 << 12);
+        gametree::turn_outcomes(board, dice, *color, &self.rules, &mut table)
+            .into_iter()
+            .map(|(moves, _)| moves)
+            .find(|moves| self.check_roll(color, board, dice, moves).is_ok())
+    }
 
-            // Now we want to validate the entire turn.
-            let has_moves_remaining: bool =
-                Board::has_valid_moves(&temp_board, &temp_dice, color);
-            let is_valid_turn: bool =
-                self.board
-                    .is_valid_turn(&temp_board, &temp_dice, *color);
+    /// Play a player's chosen moves for one roll on a copy of the
+    /// board, checking each move and then the roll as a whole.
+    /// Returns the board and dice the moves end on and the pawns they
+    /// bopped, or an error describing the broken rule.
+    fn check_roll(&self,
+                  color: &Color,
+                  start_board: &Board,
+                  rolled_dice: &Dice,
+                  chosen_moves: &[Move])
+                  -> Result<(Board, Dice, Vec<Pawn>), String> {
+        // To prevent invalid moves from messing up the game
+        // state, we capture and play individual moves on a
+        // copy of the board and game state.
+        let mut temp_board: Board = start_board.clone();
+        let mut temp_dice: Dice = rolled_dice.clone();
+        let mut bopped: Vec<Pawn> = Vec::new();
 
-            if has_moves_remaining || !is_valid_turn {
-                panic!("Invalid turn");
+        // Check moves one at a time, using the temp board.
+        for &chosen_move in chosen_moves.iter() {
+            if !temp_board.can_move_pawns_of(color,
+                                             &chosen_move.pawn.color,
+                                             &self.rules) {
+                return Err("Moved another player's pawn".to_string());
             }
 
-            // If the player rolled doubles, give another turn.
-            // Otherwise, return the temp board and dice.
-            if !is_doubles {
-                return (temp_board, temp_dice);
+            if let Err(reason) = Board::explain_move_with(&temp_board,
+                                                          &temp_dice,
+                                                          &chosen_move,
+                                                          &self.rules) {
+                warn!("{:?} made an illegal move {:?}: {}",
+                      color,
+                      chosen_move,
+                      reason);
+                return Err(reason.to_string());
             }
+
+            let move_result: Result<MoveResult,
+                                    &'static str> =
+                temp_board.handle_move_with(chosen_move, &self.rules);
+
+            // Check if the mini-move is valid at the turn level.
+            match move_result {
+                Ok(MoveResult(next_board, bonus)) => {
+                    let is_valid_for_turn: bool =
+                        temp_board.is_valid_turn(&next_board,
+                                                 &temp_dice,
+                                                 chosen_move.pawn.color);
+
+                    if !is_valid_for_turn {
+                        return Err("Moved a blockade together".to_string());
+                    }
+
+                    bopped.append(&mut bopped_pawns(&temp_board, &next_board, color));
+
+                    // Update temp_board, and add bonus if it exists.
+                    temp_board = next_board;
+                    temp_dice =
+                        temp_dice.consume_move_with(&chosen_move,
+                                                    &self.rules);
+
+                    if let Some(amt) = bonus {
+                        temp_dice = temp_dice.give_bonus(amt);
+                    }
+                }
+                Err(e) => return Err(e.to_string()),
+            };
         }
 
-        // this should never happen...
-        unreachable!();
+        // Now we want to validate the entire turn.
+        let has_moves_remaining: bool =
+            Board::has_valid_moves_from(start_board,
+                                        &temp_board,
+                                        &temp_dice,
+                                        color,
+                                        &self.rules);
+        let partner: Color = Board::get_partner(color);
+        let is_valid_turn: bool =
+            start_board.is_valid_turn(&temp_board, &temp_dice, *color) &&
+            (!self.rules.partners ||
+             !start_board.positions.contains_key(&partner) ||
+             start_board.is_valid_turn(&temp_board, &temp_dice, partner));
+
+        if has_moves_remaining || !is_valid_turn {
+            return Err("Invalid turn".to_string());
+        }
+
+        // A turn that passed every move check should never leave
+        // the board impossible, but don't let it into the game if it does.
+        if temp_board.validate_with(&self.rules).is_err() {
+            return Err("Turn left the board in an impossible state".to_string());
+        }
+
+        Ok((temp_board, temp_dice, bopped))
     }
 
     fn is_blockaded(&self, index: usize) -> bool {
@@ -220,6 +690,148 @@ impl<'a> Game<'a> {
     }
 }
 
+/// Returns the opponents' pawns that were sent back to the nest
+/// when a pawn of the given color moved from `before` to `after`.
+pub fn bopped_pawns(before: &Board, after: &Board, color: &Color) -> Vec<Pawn> {
+    let mut bopped: Vec<Pawn> = Vec::new();
+
+    for (clr, locs) in before.positions.iter() {
+        if clr == color {
+            continue;
+        }
+
+        let next_locs: PawnLocs = after.get_pawns_by_color(clr);
+        for (id, &loc) in locs.iter().enumerate() {
+            if loc != Loc::Nest && next_locs[id] == Loc::Nest {
+                bopped.push(Pawn::new(id, *clr));
+            }
+        }
+    }
+
+    bopped
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Records the effects of a single player's turn.
+struct TurnSummary {
+    board: Board,
+    dice: Dice,
+    bopped: Vec<Pawn>,
+    doubles_penalty: bool,
+    rolls: Vec<RecordedRoll>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// One roll of the dice in a recorded game: the board before the roll,
+/// what was rolled, and the moves the player chose.
+pub struct RecordedRoll {
+    pub color: Color,
+    pub board: Board,
+    pub dice: Dice,
+    pub moves: Vec<Move>,
+    /// How long the player took to choose their moves. Only timed
+    /// games keep time, so it's zero otherwise.
+    pub time_used: Duration,
+    /// Whether the player ran out of time, so the moves were played
+    /// for them.
+    pub timed_out: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// Per-player statistics collected over the course of a game.
+pub struct PlayerStats {
+    pub turns: usize,
+    pub bops_made: usize,
+    pub bops_suffered: usize,
+    pub doubles_penalties: usize,
+    /// The rule the player broke, if they were ejected.
+    pub violation: Option<String>,
+    /// The game turn on which the player brought their last pawn home.
+    pub finished_on_turn: Option<usize>,
+    /// Total time the player took to choose their moves, in a timed game.
+    pub time_used: Duration,
+    /// Number of rolls the player ran out of time on, and had their
+    /// moves played for them.
+    pub timeouts: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Represents the result of a finished game.
+pub struct GameOutcome {
+    pub winner: Option<Color>,
+    /// In team play, the partners who won together.
+    pub winning_team: Option<(Color, Color)>,
+    /// Players who brought all their pawns home, first finisher first.
+    pub finishing_order: Vec<Color>,
+    /// The name each player gave when the game started.
+    pub names: BTreeMap<Color, String>,
+    /// Total number of turns taken by all players.
+    pub turns: usize,
+    pub stats: BTreeMap<Color, PlayerStats>,
+    /// Every roll that was played, in order. Rolls ending in a doubles
+    /// penalty or an ejection aren't recorded.
+    pub record: Vec<RecordedRoll>,
+}
+
+impl GameOutcome {
+    /// Each player's finishing place, counting from 0 for first place.
+    ///
+    /// Players who brought all their pawns home are placed in the order
+    /// they finished, starting with the winner. Everyone else who stayed
+    /// in the game shares the next place, and ejected players share
+    /// last place.
+    ///
+    /// In team play, both winning partners share first place.
+    pub fn placings(&self) -> BTreeMap<Color, usize> {
+        let mut placings: BTreeMap<Color, usize> = BTreeMap::new();
+        let mut next_place: usize = 0;
+
+        if let Some((clr, partner)) = self.winning_team {
+            for winner in [clr, partner].iter() {
+                if self.stats.contains_key(winner) {
+                    placings.insert(*winner, next_place);
+                }
+            }
+            next_place += 1;
+        } else {
+            for clr in self.finishing_order.iter() {
+                placings.insert(*clr, next_place);
+                next_place += 1;
+            }
+        }
+
+        if let Some(winner) = self.winner {
+            if !placings.contains_key(&winner) {
+                placings.insert(winner, next_place);
+                next_place += 1;
+            }
+        }
+
+        let survivors: Vec<Color> = self.stats
+            .iter()
+            .filter(|&(clr, stats)| {
+                        !placings.contains_key(clr) && stats.violation.is_none()
+                    })
+            .map(|(clr, _)| *clr)
+            .collect();
+
+        if !survivors.is_empty() {
+            for clr in survivors.iter() {
+                placings.insert(*clr, next_place);
+            }
+            next_place += 1;
+        }
+
+        for (clr, _) in self.stats.iter() {
+            if !placings.contains_key(clr) {
+                placings.insert(*clr, next_place);
+            }
+        }
+
+        placings
+    }
+}
+
 
 #[derive(Debug,Copy, Clone, PartialEq)]
 /// Represents a move selected by a player.
@@ -279,8 +891,11 @@ pub fn xmlify_distance(distance: usize) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::board::IllegalMove;
+    use std::thread;
 
     /// Test player.
+    #[derive(Clone)]
     struct TestPlayer {
         color: Color,
         chosen_move: Move,
@@ -302,16 +917,17 @@ mod tests {
     }
 
     impl Player for TestPlayer {
-        fn start_game(&self) -> String {
-            "TestPlayer".to_string()
+        fn start_game(&mut self, color: Color) -> Result<String, &'static str> {
+            Ok("TestPlayer".to_string())
         }
 
-        fn do_move(&self, board: Board, dice: Dice) -> Vec<Move> {
-            vec![self.chosen_move.clone()]
+        fn do_move(&mut self, board: Board, dice: Dice) -> Result<Vec<Move>, &'static str> {
+            Ok(vec![self.chosen_move.clone()])
         }
 
-        fn doubles_penalty(&self) -> () {
+        fn doubles_penalty(&mut self) -> Result<(), &'static str> {
             println!("TestPlayer {:?} suffered a doubles penalty", self.color);
+            Ok(())
         }
     }
 
@@ -321,18 +937,312 @@ mod tests {
         let m: MoveType = MoveType::EnterPiece;
         let p_1 = TestPlayer::new(m.clone(), Color::Green);
         let game: Game = Game {
-            players: map!{ Color::Green => &p_1 as &Player },
+            players: map!{ Color::Green => Box::new(p_1.clone()) as Box<Player> },
             dice: Dice::new(),
             board: Board::new(),
+            ..Game::new()
         };
         let roll_fn = |_| (Dice { rolls: vec![1, 4] }, false);
         let (next_board, next_dice) =
-            game.give_turn(&Color::Green, &p_1, roll_fn);
+            game.give_turn(&Color::Green, &mut p_1.clone(), roll_fn);
         let green_entry = Board::get_entrance(&Color::Green);
         assert!(next_board.get_pawn_loc(&Color::Green, 0) ==
                 Loc::Spot { index: green_entry });
     }
 
+    #[test]
+    /// Under Parchís rules, a 1 and a 4 can't enter a pawn.
+    fn parchis_cannot_enter_1_4() {
+        let p_1 = TestPlayer::new(MoveType::EnterPiece, Color::Green);
+        let mut game: Game = Game::new();
+        game.set_rules(RuleSet::parchis());
+        game.seat_player(Box::new(p_1.clone()), Color::Green).unwrap();
+
+        let roll_fn = |_| (Dice { rolls: vec![1, 4] }, false);
+        let outcome: GameOutcome = game.play(roll_fn, 100);
+
+        assert_eq!(outcome.stats[&Color::Green].violation,
+                   Some(IllegalMove::NeedsFiveToEnter.to_string()));
+    }
+
+    #[test]
+    /// A player who breaks the rules is ejected, their pawns are
+    /// sent back to the nest, and the violation is recorded.
+    fn play_ejects_cheater() {
+        let p_1 = TestPlayer::new(MoveType::EnterPiece, Color::Green);
+        let mut game: Game = Game {
+            players: BTreeMap::new(),
+            dice: Dice::new(),
+            board: Board::from(map!{
+                Color::Green => [Loc::Nest,
+                                 Loc::Spot { index: 60 },
+                                 Loc::Nest,
+                                 Loc::Nest]
+            }),
+            ..Game::new()
+        };
+        game.seat_player(Box::new(p_1.clone()), Color::Green).unwrap();
+        assert!(game.seat_player(Box::new(p_1.clone()), Color::Green).is_err());
+
+        // Can't enter with a 2 and a 6.
+        let roll_fn = |_| (Dice { rolls: vec![2, 6] }, false);
+        let outcome: GameOutcome = game.play(roll_fn, 100);
+
+        assert_eq!(outcome.winner, None);
+        assert_eq!(outcome.turns, 1);
+        assert_eq!(outcome.names.get(&Color::Green),
+                   Some(&"TestPlayer".to_string()));
+        assert_eq!(outcome.stats[&Color::Green].violation,
+                   Some(IllegalMove::NeedsFiveToEnter.to_string()));
+        assert_eq!(game.board, Board::new());
+    }
+
+    /// Remembers everything it's shown, and always passes.
+    struct Observer {
+        color: Option<Color>,
+        events: Vec<TurnEvent>,
+        outcome: Option<GameOutcome>,
+    }
+
+    impl Player for Observer {
+        fn start_game(&mut self, color: Color) -> Result<String, &'static str> {
+            self.color = Some(color);
+            Ok("Observer".to_string())
+        }
+
+        fn do_move(&mut self, board: Board, dice: Dice) -> Result<Vec<Move>, &'static str> {
+            Ok(Vec::new())
+        }
+
+        fn observe_turn(&mut self, event: &TurnEvent) -> Result<(), &'static str> {
+            self.events.push(event.clone());
+            Ok(())
+        }
+
+        fn game_over(&mut self, outcome: &GameOutcome) -> Result<(), &'static str> {
+            self.outcome = Some(outcome.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    /// Players learn their color, see the other players' turns and
+    /// ejections but not their own turns, and hear how the game ended.
+    fn players_observe_the_game() {
+        let mut observer = Observer {
+            color: None,
+            events: Vec::new(),
+            outcome: None,
+        };
+        let cheater = TestPlayer::new(MoveType::EnterPiece, Color::Red);
+        {
+            let mut game: Game = Game::with_colors(&[Color::Red, Color::Blue]);
+            game.seat_player(Box::new(cheater), Color::Red).unwrap();
+            game.seat_player(Box::new(&mut observer), Color::Blue).unwrap();
+
+            // Red can't enter with a 1 and a 2; Blue passes.
+            let roll_fn = |_| (Dice { rolls: vec![1, 2] }, false);
+            game.play(roll_fn, 3);
+        }
+
+        assert_eq!(observer.color, Some(Color::Blue));
+        assert_eq!(observer.events,
+                   vec![TurnEvent::Ejected {
+                            color: Color::Red,
+                            reason: IllegalMove::NeedsFiveToEnter.to_string(),
+                        }]);
+        let outcome: GameOutcome = observer.outcome.unwrap();
+        assert_eq!(outcome.turns, 3);
+        assert!(outcome.stats[&Color::Red].violation.is_some());
+    }
+
+    /// Passes every turn, but only after sleeping for a while.
+    struct Sleeper {
+        delay: Duration,
+        asked: usize,
+    }
+
+    impl Player for Sleeper {
+        fn start_game(&mut self, color: Color) -> Result<String, &'static str> {
+            Ok("Sleeper".to_string())
+        }
+
+        fn do_move(&mut self, board: Board, dice: Dice) -> Result<Vec<Move>, &'static str> {
+            self.asked += 1;
+            thread::sleep(self.delay);
+            Ok(Vec::new())
+        }
+    }
+
+    /// Plays a one player game of `turns` turns, rolling a 5 and a 2.
+    fn play_timed(player: &mut Sleeper, time_control: TimeControl, turns: usize) -> GameOutcome {
+        let mut game: Game = Game::with_colors(&[Color::Red]);
+        game.set_time_control(time_control);
+        game.seat_player(Box::new(player), Color::Red).unwrap();
+        game.play(|_| (Dice { rolls: vec![5, 2] }, false), turns)
+    }
+
+    #[test]
+    /// A player who runs out of time has their answer ignored, and a
+    /// legal turn played for them.
+    fn default_turn_for_slow_player() {
+        let mut sleeper = Sleeper {
+            delay: Duration::from_millis(30),
+            asked: 0,
+        };
+        let time_control = TimeControl {
+            per_move: Some(Duration::from_millis(5)),
+            on_timeout: OnTimeout::DefaultTurn,
+            ..TimeControl::unlimited()
+        };
+        let outcome: GameOutcome = play_timed(&mut sleeper, time_control, 2);
+
+        // Passing with a 5 and a 2 would be an invalid turn.
+        let stats: &PlayerStats = &outcome.stats[&Color::Red];
+        assert_eq!(stats.violation, None);
+        assert_eq!(stats.timeouts, 2);
+        assert!(stats.time_used >= Duration::from_millis(60));
+        assert_eq!(outcome.record.len(), 2);
+        assert!(outcome.record[0].timed_out);
+        assert!(outcome.record[0].time_used >= Duration::from_millis(30));
+        assert!(!outcome.record[0].moves.is_empty());
+        assert_eq!(sleeper.asked, 2);
+    }
+
+    #[test]
+    /// Under the forfeit policy, running out of time ejects the player.
+    fn slow_player_forfeits() {
+        let mut sleeper = Sleeper {
+            delay: Duration::from_millis(30),
+            asked: 0,
+        };
+        let time_control = TimeControl {
+            per_move: Some(Duration::from_millis(5)),
+            ..TimeControl::unlimited()
+        };
+        let outcome: GameOutcome = play_timed(&mut sleeper, time_control, 2);
+
+        assert_eq!(outcome.stats[&Color::Red].violation,
+                   Some("Out of time".to_string()));
+        assert_eq!(outcome.turns, 1);
+        assert!(outcome.record.is_empty());
+    }
+
+    #[test]
+    /// A player who has used up their time for the game isn't asked
+    /// to move again.
+    fn game_clock_runs_out() {
+        let mut sleeper = Sleeper {
+            delay: Duration::from_millis(30),
+            asked: 0,
+        };
+        let time_control = TimeControl {
+            per_game: Some(Duration::from_millis(20)),
+            on_timeout: OnTimeout::DefaultTurn,
+            ..TimeControl::unlimited()
+        };
+        let outcome: GameOutcome = play_timed(&mut sleeper, time_control, 3);
+
+        assert_eq!(sleeper.asked, 1);
+        assert_eq!(outcome.stats[&Color::Red].timeouts, 3);
+        assert_eq!(outcome.record[1].time_used, Duration::default());
+    }
+
+    #[test]
+    /// The winner places first, then everyone still playing,
+    /// then anyone who was ejected.
+    fn placings_rank_winner_then_survivors() {
+        let mut stats: BTreeMap<Color, PlayerStats> = BTreeMap::new();
+        for clr in COLORS.iter() {
+            stats.insert(*clr, PlayerStats::default());
+        }
+        stats.get_mut(&Color::Green).unwrap().violation = Some("Cheated".to_string());
+
+        let outcome = GameOutcome {
+            winner: Some(Color::Blue),
+            winning_team: None,
+            finishing_order: vec![Color::Blue],
+            names: BTreeMap::new(),
+            turns: 40,
+            stats: stats,
+            record: Vec::new(),
+        };
+
+        assert_eq!(outcome.placings(),
+                   map!{ Color::Blue => 0,
+                         Color::Red => 1,
+                         Color::Yellow => 1,
+                         Color::Green => 2 });
+    }
+
+    #[test]
+    /// When playing to completion, the game carries on after the first
+    /// player finishes, and records the order players finished in.
+    fn play_to_completion_records_finishing_order() {
+        let p_red = TestPlayer::new(MoveType::MoveHome {
+                                        start: RED_HOME_ROW + 6,
+                                        distance: 1,
+                                    },
+                                    Color::Red);
+        let p_green = TestPlayer::new(MoveType::MoveHome {
+                                          start: GREEN_HOME_ROW + 6,
+                                          distance: 1,
+                                      },
+                                      Color::Green);
+        let p_blue = TestPlayer::new(MoveType::MoveHome {
+                                         start: BLUE_HOME_ROW + 6,
+                                         distance: 1,
+                                     },
+                                     Color::Blue);
+        let board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: RED_HOME_ROW + 6 },
+                           Loc::Home, Loc::Home, Loc::Home],
+            Color::Green => [Loc::Spot { index: GREEN_HOME_ROW + 6 },
+                             Loc::Home, Loc::Home, Loc::Home],
+            Color::Blue => [Loc::Spot { index: BLUE_HOME_ROW + 6 },
+                            Loc::Home, Loc::Home, Loc::Home]
+        });
+        let roll_fn = |_| (Dice { rolls: vec![1, 2] }, false);
+
+        // Without playing to completion, the first finisher ends the game.
+        let mut game: Game = Game {
+            board: board.clone(),
+            ..Game::new()
+        };
+        game.seat_player(Box::new(p_red.clone()), Color::Red).unwrap();
+        game.seat_player(Box::new(p_green.clone()), Color::Green).unwrap();
+        game.seat_player(Box::new(p_blue.clone()), Color::Blue).unwrap();
+        let outcome: GameOutcome = game.play(roll_fn, 100);
+        assert_eq!(outcome.winner, Some(Color::Red));
+        assert_eq!(outcome.finishing_order, vec![Color::Red]);
+        assert_eq!(outcome.turns, 1);
+        assert_eq!(outcome.record,
+                   vec![RecordedRoll {
+                            color: Color::Red,
+                            board: board.clone(),
+                            dice: Dice { rolls: vec![1, 2] },
+                            moves: vec![p_red.chosen_move],
+                            time_used: Duration::default(),
+                            timed_out: false,
+                        }]);
+
+        let mut game: Game = Game {
+            board: board,
+            ..Game::new()
+        };
+        game.set_play_to_completion(true);
+        game.seat_player(Box::new(p_red.clone()), Color::Red).unwrap();
+        game.seat_player(Box::new(p_green.clone()), Color::Green).unwrap();
+        game.seat_player(Box::new(p_blue.clone()), Color::Blue).unwrap();
+        let outcome: GameOutcome = game.play(roll_fn, 100);
+        assert_eq!(outcome.winner, Some(Color::Red));
+        assert_eq!(outcome.finishing_order, vec![Color::Red, Color::Green]);
+        assert_eq!(outcome.turns, 2);
+        assert_eq!(outcome.stats[&Color::Green].finished_on_turn, Some(2));
+        assert_eq!(outcome.placings(),
+                   map!{ Color::Red => 0, Color::Green => 1, Color::Blue => 2 });
+    }
+
     #[test]
     /// Test functionality to add new players. Players should
     /// all be assigned different colors, and no more than 4
@@ -351,7 +1261,7 @@ mod tests {
 
         for i in 0..4 {
             let p = players[i];
-            game.register_player(players[i]);
+            game.register_player(players[i].clone());
             assert!(game.players
                         .contains_key(&colors[i]));
         }
@@ -359,7 +1269,7 @@ mod tests {
         // Inserting the fifth player should result
         // in no change to the game state.
         let num_players = game.players.len();
-        game.register_player(&p5);
+        game.register_player(p5.clone());
         assert_eq!(game.players.len(), num_players);
 
         // All colors were used.
@@ -368,6 +1278,114 @@ mod tests {
                         .contains_key(clr));
         }
     }
+
+    #[test]
+    /// In team play, a player whose pawns are all home brings their
+    /// partner's last pawn home, and the partners win together.
+    fn team_play_partners_win_together() {
+        let p_red = TestPlayer::new(MoveType::MoveHome {
+                                        start: YELLOW_HOME_ROW + 6,
+                                        distance: 1,
+                                    },
+                                    Color::Yellow);
+        let p_other = TestPlayer::new(MoveType::EnterPiece, Color::Blue);
+        let mut game: Game = Game {
+            board: Board::from(map!{
+                Color::Red => [Loc::Home; 4],
+                Color::Yellow => [Loc::Spot { index: YELLOW_HOME_ROW + 6 },
+                                  Loc::Home, Loc::Home, Loc::Home]
+            }),
+            ..Game::new()
+        };
+        game.set_team_play(true);
+        game.seat_player(Box::new(p_red.clone()), Color::Red).unwrap();
+        game.seat_player(Box::new(p_other.clone()), Color::Yellow).unwrap();
+        game.seat_player(Box::new(p_other.clone()), Color::Blue).unwrap();
+        game.seat_player(Box::new(p_other.clone()), Color::Green).unwrap();
+
+        let roll_fn = |_| (Dice { rolls: vec![1, 2] }, false);
+        let outcome: GameOutcome = game.play(roll_fn, 100);
+
+        assert_eq!(outcome.turns, 1);
+        assert_eq!(outcome.winning_team, Some((Color::Red, Color::Yellow)));
+        assert_eq!(outcome.winner, Some(Color::Red));
+        assert_eq!(outcome.placings(),
+                   map!{ Color::Red => 0, Color::Yellow => 0,
+                         Color::Blue => 1, Color::Green => 1 });
+    }
+
+    #[test]
+    /// Games can't start from a board that couldn't happen.
+    fn game_from_impossible_board() {
+        let board: Board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 30 }, Loc::Nest, Loc::Nest, Loc::Nest],
+            Color::Blue => [Loc::Spot { index: 30 }, Loc::Nest, Loc::Nest, Loc::Nest]
+        });
+        assert!(Game::from_board(board).is_err());
+        assert!(Game::from_board(Board::with_colors(&[Color::Red, Color::Blue]))
+                    .is_ok());
+    }
+
+    #[test]
+    /// Under team play a pawn can land on its partner's pawn, and the
+    /// board that leaves behind is a possible one.
+    fn team_play_move_onto_partner() {
+        let board: Board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 12 }, Loc::Nest, Loc::Nest, Loc::Nest],
+            Color::Yellow => [Loc::Spot { index: 14 }, Loc::Nest, Loc::Nest, Loc::Nest]
+        });
+        let rules: RuleSet = RuleSet { partners: true, ..RuleSet::standard() };
+        let game: Game = Game::from_board_with(board.clone(), rules).unwrap();
+        let dice: Dice = Dice { rolls: vec![2] };
+        let moves: Vec<Move> = vec![Move {
+                                        pawn: Pawn::new(0, Color::Red),
+                                        m_type: MoveType::MoveMain { start: 12, distance: 2 },
+                                    }];
+
+        let (end_board, _, _) = game.check_roll(&Color::Red, &board, &dice, &moves).unwrap();
+        assert!(end_board.validate_with(&rules).is_ok());
+        assert!(end_board.validate().is_err());
+        assert!(Game::from_board_with(end_board.clone(), rules).is_ok());
+        assert!(Game::from_board(end_board).is_err());
+    }
+
+    #[test]
+    /// A two player game only seats players at the chosen colors,
+    /// and plays to a winner among them.
+    fn two_player_game() {
+        let p_red = TestPlayer::new(MoveType::MoveHome {
+                                        start: RED_HOME_ROW + 6,
+                                        distance: 1,
+                                    },
+                                    Color::Red);
+        let p_yellow = TestPlayer::new(MoveType::EnterPiece, Color::Yellow);
+        let mut game: Game = Game::with_colors(&[Color::Red, Color::Yellow]);
+
+        game.register_player(p_red.clone());
+        game.register_player(p_yellow.clone());
+        game.register_player(p_yellow.clone());
+        assert_eq!(game.players.keys().cloned().collect::<Vec<Color>>(),
+                   vec![Color::Red, Color::Yellow]);
+
+        let mut game: Game = Game::with_colors(&[Color::Red, Color::Yellow]);
+        assert!(game.seat_player(Box::new(p_red.clone()), Color::Blue).is_err());
+        game.seat_player(Box::new(p_red.clone()), Color::Red).unwrap();
+        game.seat_player(Box::new(p_yellow.clone()), Color::Yellow).unwrap();
+        game.board
+            .positions
+            .insert(Color::Red,
+                    [Loc::Spot { index: RED_HOME_ROW + 6 },
+                     Loc::Home,
+                     Loc::Home,
+                     Loc::Home]);
+
+        let roll_fn = |_| (Dice { rolls: vec![1, 2] }, false);
+        let outcome: GameOutcome = game.play(roll_fn, 100);
+
+        assert_eq!(outcome.winner, Some(Color::Red));
+        assert_eq!(game.board.colors(), vec![Color::Red, Color::Yellow]);
+    }
+
     #[test]
     #[should_panic]
     /// Blockade Test
@@ -393,10 +1411,11 @@ mod tests {
             players: BTreeMap::new(),
             dice: Dice { rolls: vec![1, 4] },
             board: b0,
+            ..Game::new()
         };
         for i in 0..4 {
             let p = players[i];
-            game.register_player(players[i]);
+            game.register_player(players[i].clone());
             assert!(game.players
                         .contains_key(&colors[i]));
         }
@@ -404,7 +1423,7 @@ mod tests {
         assert!(game.is_blockaded(RED_ENTRANCE));
         // This should panic because the Red player is trying to enter where there
         // is a blockade
-        let (board, dice) = game.give_turn(&Color::Red, &p1, roll_fn);
+        let (board, dice) = game.give_turn(&Color::Red, &mut p1.clone(), roll_fn);
         //println!("{:#?}, {:#?}",board,dice);
         // When player tries to enter on blockade with move m, same board is returned
     }
@@ -432,15 +1451,16 @@ mod tests {
             players: BTreeMap::new(),
             dice: Dice { rolls: vec![1, 4] },
             board: b0,
+            ..Game::new()
         };
         for i in 0..1 {
             let p = players[i];
-            game.register_player(players[i]);
+            game.register_player(players[i].clone());
             assert!(game.players
                         .contains_key(&colors[i]));
         }
         assert!(game.is_blockaded(RED_ENTRANCE + 7));
-        let (board, dice) = game.give_turn(&Color::Green, &p1, roll_fn);
+        let (board, dice) = game.give_turn(&Color::Green, &mut p1.clone(), roll_fn);
     }
 
     #[test]
@@ -473,15 +1493,16 @@ mod tests {
             players: BTreeMap::new(),
             dice: Dice { rolls: vec![2, 4] },
             board: b0,
+            ..Game::new()
         };
         for i in 0..1 {
             let p = players[i];
-            game.register_player(players[i]);
+            game.register_player(players[i].clone());
             assert!(game.players
                         .contains_key(&colors[i]));
         }
         assert!(game.is_blockaded(RED_ENTRANCE + 7));
-        let (board, dice) = game.give_turn(&Color::Red, &p1, roll_fn);
+        let (board, dice) = game.give_turn(&Color::Red, &mut p1.clone(), roll_fn);
     }
     #[test]
     #[should_panic]
@@ -507,15 +1528,16 @@ mod tests {
             players: BTreeMap::new(),
             dice: Dice { rolls: vec![2, 4] },
             board: b0,
+            ..Game::new()
         };
         for i in 0..1 {
             let p = players[i];
-            game.register_player(players[i]);
+            game.register_player(players[i].clone());
             assert!(game.players
                         .contains_key(&colors[i]));
         }
         assert!(game.is_blockaded(RED_ENTRANCE + 7));
-        let (board, dice) = game.give_turn(&Color::Red, &p1, roll_fn);
+        let (board, dice) = game.give_turn(&Color::Red, &mut p1.clone(), roll_fn);
     }
     #[test]
     #[should_panic]
@@ -542,15 +1564,16 @@ mod tests {
             players: BTreeMap::new(),
             dice: Dice { rolls: vec![2, 4] },
             board: b0,
+            ..Game::new()
         };
         for i in 0..1 {
             let p = players[i];
-            game.register_player(players[i]);
+            game.register_player(players[i].clone());
             assert!(game.players
                         .contains_key(&colors[i]));
         }
         assert!(game.is_blockaded(RED_HOME_ROW + 1));
-        let (board, dice) = game.give_turn(&Color::Red, &p1, roll_fn);
+        let (board, dice) = game.give_turn(&Color::Red, &mut p1.clone(), roll_fn);
 
     }
 
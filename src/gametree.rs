@@ -2,6 +2,8 @@ use super::board::{Board, PawnLocs, Color, Pawn, Loc, MoveResult};
 use super::game::{Move, MoveType};
 use super::dice::{Dice, EntryMove};
 use super::deserialize;
+use super::rules::RuleSet;
+use super::zobrist::{self, TranspositionTable};
 
 /// Given some board and dice, iterate over the possible
 /// next states, and yield the legal ones.
@@ -17,6 +19,8 @@ pub struct GameTree {
     pawns: PawnLocs,
     current_pawn: usize,
     current_roll: usize,
+    tried_entry: bool,
+    rules: RuleSet,
 }
 
 impl GameTree {
@@ -29,6 +33,8 @@ impl GameTree {
             dice: dice,
             current_pawn: 0,
             current_roll: 0,
+            tried_entry: false,
+            rules: RuleSet::standard(),
         }
     }
 
@@ -45,45 +51,55 @@ impl GameTree {
             dice: dice,
             current_pawn: 0,
             current_roll: 0,
+            tried_entry: false,
+            rules: RuleSet::standard(),
         }
     }
+
+    /// Generate moves under the given rules instead of the standard ones.
+    pub fn with_rules(mut self, rules: RuleSet) -> GameTree {
+        // In team play, a player whose pawns are all home moves
+        // their partner's pawns instead.
+        let partner: Color = Board::get_partner(&self.color);
+        if self.board
+               .can_move_pawns_of(&self.color, &partner, &rules) {
+            self.color = partner;
+            self.pawns = self.board.get_pawns_by_color(&partner);
+        }
+
+        self.rules = rules;
+        self
+    }
 }
 
 impl Iterator for GameTree {
     type Item = Move;
 
     fn next(&mut self) -> Option<Move> {
-        println!("========GAMETREE NEXT=============");
-        println!("DICE: {:#?}", self.dice);
+        trace!("Next move for {:?} with dice {:?}", self.color, self.dice.rolls);
         // We don't know how Rust implements generators, so we're using
         // an iterator and manually saving the state.
 
         // Try entering.
-        let entry: EntryMove = self.dice.can_enter();
+        let entry: EntryMove = self.dice.can_enter_with(&self.rules);
         let entry_roll: Vec<usize> = match entry {
             EntryMove::WithFive => vec![5],
             EntryMove::WithSum(a, b) => vec![a, b], // (2)
             EntryMove::NoEntry => vec![],
         };
 
-        if !entry_roll.is_empty() {
+        // Only offer to enter once; otherwise we'd yield the same
+        // entry move forever.
+        if !entry_roll.is_empty() && !self.tried_entry {
+            self.tried_entry = true;
+
             // Get pawns.
             for (i, &loc) in self.pawns
                     .iter()
                     .enumerate() {
-                if Loc::Nest == loc &&
-                   !(self.board
-                         .get_blockades()
-                         .iter()
-                         .any(|&x| {
-                                  x ==
-                                  Loc::Spot {
-                                      index: Board::get_entrance(&self.color),
-                                  }
-                              }))
-                //Wonky closure to make sure we aren't going into our entrance that contains a blockade
-                {
-                    // Schedule pawn to enter.
+                if Loc::Nest == loc {
+                    // Schedule pawn to enter, unless the entrance is
+                    // blockaded or full.
                     let entry = Move {
                         pawn: Pawn {
                             id: i,
@@ -92,7 +108,10 @@ impl Iterator for GameTree {
                         m_type: MoveType::EnterPiece,
                     };
 
-                    return Some(entry);
+                    if Board::explain_move_with(&self.board, &self.dice, &entry, &self.rules)
+                           .is_ok() {
+                        return Some(entry);
+                    }
                     // TODO: Cache state for subsequent iterations.
                 }
             }
@@ -148,11 +167,14 @@ impl Iterator for GameTree {
                 // as well as on the turn level.
                 let temp_board: Board = self.board.clone();
                 let is_valid_mini_move: bool =
-                    Board::is_valid_move(&self.board, &self.dice, &mv);
+                    Board::is_valid_move_with(&self.board,
+                                              &self.dice,
+                                              &mv,
+                                              &self.rules);
 
                 let move_result: Result<MoveResult,
                                         &'static str> = temp_board
-                    .handle_move(mv);
+                    .handle_move_with(mv, &self.rules);
 
                 let is_valid_for_turn: bool = match move_result {
                     Ok(MoveResult(next_board, _)) => {
@@ -183,10 +205,112 @@ impl Iterator for GameTree {
     }
 }
 
+/// Find every distinct board a player can end their turn on, along with
+/// a sequence of moves that reaches it.
+///
+/// Playing the same mini-moves in a different order often leads to the
+/// same position, so states already in `table` are skipped. The table is
+/// keyed by the board's Zobrist hash combined with the unused dice, and
+/// can be shared between searches; clear it between turns.
+pub fn turn_outcomes(board: &Board,
+                     dice: &Dice,
+                     color: Color,
+                     rules: &RuleSet,
+                     table: &mut TranspositionTable<()>)
+                     -> Vec<(Vec<Move>, Board)> {
+    let mut outcomes: Vec<(Vec<Move>, Board)> = Vec::new();
+    let hash: u64 = board.zobrist(&color);
+    table.insert(hash ^ zobrist::dice_key(dice), ());
+    search_turn(board,
+                board.clone(),
+                dice.clone(),
+                hash,
+                &mut Vec::new(),
+                color,
+                rules,
+                table,
+                &mut outcomes);
+    outcomes
+}
+
+fn search_turn(original: &Board,
+               board: Board,
+               dice: Dice,
+               hash: u64,
+               moves: &mut Vec<Move>,
+               color: Color,
+               rules: &RuleSet,
+               table: &mut TranspositionTable<()>,
+               outcomes: &mut Vec<(Vec<Move>, Board)>)
+               -> () {
+    let options: GameTree =
+        GameTree::from(board.clone(), dice.clone(), color, original.clone())
+            .with_rules(*rules);
+    let mut has_moves: bool = false;
+
+    for mv in options {
+        has_moves = true;
+        if let Ok((MoveResult(next_board, bonus), next_hash)) =
+            board.handle_move_hashed(mv, rules, hash) {
+            let mut next_dice: Dice = dice.consume_move_with(&mv, rules);
+            if let Some(amt) = bonus {
+                next_dice = next_dice.give_bonus(amt);
+            }
+
+            let key: u64 = next_hash ^ zobrist::dice_key(&next_dice);
+            if table.contains(key) {
+                continue;
+            }
+            table.insert(key, ());
+
+            moves.push(mv);
+            search_turn(original,
+                        next_board,
+                        next_dice,
+                        next_hash,
+                        moves,
+                        color,
+                        rules,
+                        table,
+                        outcomes);
+            moves.pop();
+        }
+    }
+
+    if !has_moves {
+        outcomes.push((moves.clone(), board));
+    }
+}
+
 
 mod test {
     use super::*;
 
+    #[test]
+    /// Moving two pawns 1 and 2 gives eight move orders but only four
+    /// distinct positions.
+    fn turn_outcomes_skip_transpositions() {
+        let board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 10 },
+                           Loc::Spot { index: 20 },
+                           Loc::Home,
+                           Loc::Home]
+        });
+        let dice = Dice { rolls: vec![1, 2] };
+        let mut table: TranspositionTable<()> = TranspositionTable::new(1024);
+
+        let outcomes = turn_outcomes(&board,
+                                     &dice,
+                                     Color::Red,
+                                     &RuleSet::standard(),
+                                     &mut table);
+        assert_eq!(outcomes.len(), 4);
+        for &(ref moves, ref end) in outcomes.iter() {
+            assert_eq!(moves.len(), 2);
+            assert!(board.is_valid_turn(end, &dice, Color::Red));
+        }
+    }
+
     #[test]
     fn frick() {
         let response = "<do-move><board><start><pawn><color>yellow</color><id>1</id></pawn><pawn><color>green</color><id>1</id></pawn><pawn><color>blue</color><id>3</id></pawn></start><main><piece-loc><pawn><color>green</color><id>2</id></pawn><loc>62</loc></piece-loc><piece-loc><pawn><color>yellow</color><id>0</id></pawn><loc>60</loc></piece-loc><piece-loc><pawn><color>blue</color><id>1</id></pawn><loc>59</loc></piece-loc><piece-loc><pawn><color>red</color><id>1</id></pawn><loc>41</loc></piece-loc><piece-loc><pawn><color>red</color><id>3</id></pawn><loc>40</loc></piece-loc><piece-loc><pawn><color>blue</color><id>2</id></pawn><loc>39</loc></piece-loc><piece-loc><pawn><color>green</color><id>3</id></pawn><loc>36</loc></piece-loc><piece-loc><pawn><color>red</color><id>2</id></pawn><loc>28</loc></piece-loc><piece-loc><pawn><color>red</color><id>0</id></pawn><loc>22</loc></piece-loc><piece-loc><pawn><color>yellow</color><id>2</id></pawn><loc>20</loc></piece-loc><piece-loc><pawn><color>blue</color><id>0</id></pawn><loc>10</loc></piece-loc><piece-loc><pawn><color>yellow</color><id>3</id></pawn><loc>9</loc></piece-loc></main><home-rows></home-rows><home><pawn><color>green</color><id>0</id></pawn></home></board><dice><die>6</die><die>6</die></dice></do-move>";
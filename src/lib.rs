@@ -0,0 +1,33 @@
+#[macro_use]
+mod macros;
+
+#[macro_use]
+extern crate log;
+extern crate quick_xml;
+extern crate rand;
+
+pub mod board;
+pub mod game;
+pub mod dice;
+pub mod constants;
+pub mod player;
+pub mod gametree;
+pub mod autoplayers;
+pub mod serialize;
+pub mod deserialize;
+pub mod networkplayer;
+pub mod networkgame;
+pub mod tournament;
+pub mod rating;
+pub mod rules;
+pub mod compactboard;
+pub mod zobrist;
+pub mod notation;
+pub mod analysis;
+pub mod review;
+pub mod probability;
+pub mod race;
+pub mod parallel;
+pub mod spectator;
+pub mod timecontrol;
+pub mod conformance;
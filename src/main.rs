@@ -1,29 +1,76 @@
-#[macro_use]
-mod macros;
-
-extern crate quick_xml;
-
-mod board;
-mod game;
-mod dice;
-mod constants;
-mod player;
-mod gametree;
-mod autoplayers;
-mod serialize;
-mod deserialize;
-mod networkplayer;
-mod networkgame;
+extern crate env_logger;
+extern crate parcheesi;
 
 use std::net::TcpStream;
-use networkplayer::NetworkPlayer;
-use board::{Board, Color, Loc};
-use dice::Dice;
-use player::Player;
+use parcheesi::{autoplayers, parallel, probability, race, rating, review};
+use parcheesi::race::RaceSolver;
+use parcheesi::networkplayer::NetworkPlayer;
+use parcheesi::board::{Board, Color, Loc};
+use parcheesi::dice::Dice;
+use parcheesi::player::Player;
 use std::env;
+use parcheesi::tournament::Tournament;
+use parcheesi::rating::Ledger;
+use parcheesi::rules::RuleSet;
+use parcheesi::timecontrol::{OnTimeout, TimeControl};
+use parcheesi::notation::Position;
+use parcheesi::gametree;
+use parcheesi::zobrist::TranspositionTable;
+use parcheesi::analysis::Analyzer;
+use parcheesi::spectator::Broadcast;
+use parcheesi::conformance::{self, Harness};
+use parcheesi::networkgame::{self, Lobby};
+use parcheesi::{deserialize, serialize};
+use std::io::{BufRead, BufReader};
+use std::net::TcpListener;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use parcheesi::tournament::PlayerFactory;
+use std::time::Duration;
+use std::path::PathBuf;
 
 fn main() {
+    // Only errors are logged unless RUST_LOG is set, e.g. RUST_LOG=parcheesi=debug.
+    env_logger::init();
+
     let args: Vec<_> = env::args().collect();
+    if args.len() > 1 && args[1] == "tournament" {
+        run_tournament(&args[2..]);
+        return;
+    }
+    if args.len() > 1 && args[1] == "moves" {
+        show_moves(&args[2..]);
+        return;
+    }
+    if args.len() > 1 && args[1] == "hint" {
+        show_hints(&args[2..]);
+        return;
+    }
+    if args.len() > 1 && args[1] == "review" {
+        review_game(&args[2..]);
+        return;
+    }
+    if args.len() > 1 && args[1] == "ratings" {
+        show_ratings(&args[2..]);
+        return;
+    }
+    if args.len() > 1 && args[1] == "serve" {
+        serve_lobby(&args[2..]);
+        return;
+    }
+    if args.len() > 1 && args[1] == "play" {
+        play_online(&args[2..]);
+        return;
+    }
+    if args.len() > 1 && args[1] == "broadcast" {
+        broadcast_games(&args[2..]);
+        return;
+    }
+    if args.len() > 1 && args[1] == "conformance" {
+        check_conformance(&args[2..]);
+        return;
+    }
     println!("Hello, world!");
     let mut test_player = autoplayers::XMLTestPlayer {
         color: Color::Red, //This is meaningless
@@ -65,3 +112,405 @@ fn main() {
     //  println!("Player received: {}", moves);
     //}
 }
+
+/// Play a tournament between the built-in players and print the report.
+/// With `move_ms`, players who take longer than that over a move have
+/// a turn played for them.
+///
+/// Usage: parcheesi tournament [games] [seed] [table|csv|json]
+///                              [standard|parchis|simple] [threads] [move_ms]
+fn run_tournament(args: &[String]) {
+    let games: usize = args.get(0)
+        .map(|s| s.parse().expect("games must be a number"))
+        .unwrap_or(100);
+    let seed: usize = args.get(1)
+        .map(|s| s.parse().expect("seed must be a number"))
+        .unwrap_or(0);
+    let rules: RuleSet = args.get(3)
+        .map(|s| RuleSet::from_name(s).expect("unknown rules"))
+        .unwrap_or_default();
+
+    let threads: usize = args.get(4)
+        .map(|s| s.parse().expect("threads must be a number"))
+        .unwrap_or_else(parallel::default_threads);
+    let time_control: TimeControl = timed_moves(args.get(5).map(|s| {
+        Duration::from_millis(s.parse().expect("move_ms must be a number"))
+    }));
+
+    let mut tournament: Tournament = builtin_tournament(games, seed, rules);
+    tournament.set_time_control(time_control);
+    let report = tournament.run_parallel(threads);
+
+    let path: PathBuf = ledger_path();
+    let mut ledger: Ledger = Ledger::load(&path).expect("Could not load ratings");
+    for outcome in report.outcomes.iter() {
+        ledger.record(outcome);
+    }
+    ledger
+        .save(&path)
+        .expect("Could not save ratings");
+
+    match args.get(2).map(|s| s.as_str()) {
+        Some("csv") => print!("{}", report.csv()),
+        Some("json") => println!("{}", report.json()),
+        _ => print!("{}", report.table()),
+    }
+}
+
+/// Host games for network players until stopped. Empty seats are
+/// filled with the given bots, e.g. `first,greedy,random`, and the first
+/// of them also stands in for players who disconnect and don't come
+/// back within the grace period. With `move_secs`, players who take
+/// longer than that over a move have a turn played for them.
+///
+/// Usage: parcheesi serve [addr] [standard|parchis|simple] [bots] [grace_secs]
+///                        [move_secs]
+fn serve_lobby(args: &[String]) {
+    let addr: &str = args.get(0).map(|s| s.as_str()).unwrap_or("127.0.0.1:8000");
+    let rules: RuleSet = args.get(1)
+        .map(|s| RuleSet::from_name(s).expect("unknown rules"))
+        .unwrap_or_default();
+    let bots: Vec<PlayerFactory> = args.get(2)
+        .map(|s| s.split(',').map(|name| bot(name, rules)).collect())
+        .unwrap_or_default();
+    let grace: Duration = args.get(3)
+        .map(|s| Duration::from_secs(s.parse().expect("grace_secs must be a number")))
+        .unwrap_or(networkgame::DEFAULT_GRACE_PERIOD);
+    let time_control: TimeControl = timed_moves(args.get(4).map(|s| {
+        Duration::from_secs(s.parse().expect("move_secs must be a number"))
+    }));
+
+    let (results, finished) = mpsc::channel();
+    let mut lobby: Lobby = Lobby::new()
+        .with_rules(rules)
+        .with_grace_period(grace)
+        .with_time_control(time_control)
+        .with_results(results);
+    if let Some(name) = args.get(2).and_then(|s| s.split(',').next()) {
+        lobby = lobby.with_substitute(bot(name, rules));
+    }
+    lobby = lobby.with_bots(bots);
+    let local_addr = lobby.listen(addr).expect("Could not listen for players");
+    println!("Lobby open on {}", local_addr);
+
+    for (id, outcome) in finished.iter() {
+        println!("Game {}: {:?} won in {} turns",
+                 id,
+                 outcome.winner,
+                 outcome.turns);
+    }
+}
+
+/// Time limits of `per_move` for every move, if given. Players who run
+/// out of time have a turn played for them rather than forfeiting.
+fn timed_moves(per_move: Option<Duration>) -> TimeControl {
+    TimeControl {
+        per_move: per_move,
+        on_timeout: OnTimeout::DefaultTurn,
+        ..TimeControl::unlimited()
+    }
+}
+
+/// A server-side bot, by name.
+fn bot(name: &str, rules: RuleSet) -> PlayerFactory {
+    match name {
+        "first" => {
+            Box::new(move |clr| {
+                Box::new(autoplayers::move_first_pawn_player("MoveFirst".to_string(), clr)
+                             .with_rules(rules)) as Box<Player>
+            })
+        }
+        "last" => {
+            Box::new(move |clr| {
+                Box::new(autoplayers::move_last_pawn_player("MoveLast".to_string(), clr)
+                             .with_rules(rules)) as Box<Player>
+            })
+        }
+        "random" => {
+            // Give every bot its own seed, so they don't all play alike.
+            let seeds: AtomicUsize = AtomicUsize::new(1);
+            Box::new(move |clr| {
+                let seed: usize = seeds.fetch_add(1, Ordering::SeqCst);
+                Box::new(autoplayers::RandomPlayer::new("Random".to_string(), clr, seed)
+                             .with_rules(rules)) as Box<Player>
+            })
+        }
+        "greedy" => {
+            Box::new(move |clr| {
+                Box::new(autoplayers::GreedyPlayer::new("Greedy".to_string(), clr)
+                             .with_rules(rules)) as Box<Player>
+            })
+        }
+        _ => panic!("unknown bot {}, expected first, last, random or greedy", name),
+    }
+}
+
+/// Create or join a game in a lobby, and play it with the XML test player.
+///
+/// Usage: parcheesi play [addr] [new|game id] [color]
+fn play_online(args: &[String]) {
+    let addr: &str = args.get(0).map(|s| s.as_str()).unwrap_or("127.0.0.1:8000");
+    let color: Option<Color> = args.get(2).map(|s| deserialize::string_to_color(s.to_string()));
+    let request: String = match args.get(1).map(|s| s.as_str()) {
+        None | Some("new") => serialize::xml_create_game(color),
+        Some(id) => serialize::xml_join_game(id.parse().expect("game id must be a number"), color),
+    };
+
+    let mut test_player = autoplayers::XMLTestPlayer {
+        color: Color::Red,
+        name: "Lloyd".to_string(),
+        stream: TcpStream::connect(addr).expect("Could not connect to the lobby"),
+    };
+    test_player.send(request);
+
+    let mut reader = BufReader::new(&test_player.stream);
+    let mut answer: String = String::new();
+    reader
+        .read_line(&mut answer)
+        .expect("Could not read from the lobby");
+    println!("{}", answer.trim_end());
+    if !answer.starts_with("<joined>") {
+        return;
+    }
+
+    loop {
+        test_player.receive();
+    }
+}
+
+/// Play games between the built-in players one after another, streaming
+/// them to anyone who connects to watch.
+///
+/// Usage: parcheesi broadcast [addr] [games] [seed] [pace_ms]
+fn broadcast_games(args: &[String]) {
+    let addr: &str = args.get(0).map(|s| s.as_str()).unwrap_or("127.0.0.1:8001");
+    let games: usize = args.get(1)
+        .map(|s| s.parse().expect("games must be a number"))
+        .unwrap_or(1);
+    let seed: usize = args.get(2)
+        .map(|s| s.parse().expect("seed must be a number"))
+        .unwrap_or(0);
+    let pace: u64 = args.get(3)
+        .map(|s| s.parse().expect("pace must be a number"))
+        .unwrap_or(500);
+
+    let (broadcast, local_addr) = Broadcast::listen(addr).expect("Could not listen for spectators");
+    let broadcast: Broadcast = broadcast.with_pace(Duration::from_millis(pace));
+    println!("Broadcasting on {}", local_addr);
+
+    let tournament: Tournament = builtin_tournament(games, seed, RuleSet::standard());
+    let seatings = tournament.seatings();
+    for i in 0..games {
+        let seating = &seatings[i % seatings.len()];
+        let outcome = tournament.play_watched_game(seating,
+                                                   seed + i,
+                                                   vec![Box::new(broadcast.clone())]);
+        println!("Game {}: {:?} won in {} turns",
+                 i + 1,
+                 outcome.winner,
+                 outcome.turns);
+    }
+}
+
+/// Check a client against the protocol, and report the requirements it
+/// breaks. The client either connects to `addr` for every game, or is a
+/// program that's started for every game and plays over its standard
+/// input and output. Exits with an error if the client breaks any.
+///
+/// Usage: parcheesi conformance listen [addr] [timeout_secs]
+///        parcheesi conformance exec <program> [args...]
+fn check_conformance(args: &[String]) {
+    let mut harness: Harness = match args.get(0).map(|s| s.as_str()) {
+        Some("exec") => {
+            let program: String = args.get(1).cloned().expect("exec needs a program to run");
+            Harness::spawn(program, args[2..].to_vec())
+        }
+        Some("listen") => {
+            let addr: &str = args.get(1).map(|s| s.as_str()).unwrap_or("127.0.0.1:8000");
+            let timeout: Duration = args.get(2)
+                .map(|s| Duration::from_secs(s.parse().expect("timeout_secs must be a number")))
+                .unwrap_or(conformance::DEFAULT_TIMEOUT);
+            let listener: TcpListener = TcpListener::bind(addr).expect("Could not listen for the client");
+            println!("Waiting for the client on {}", listener.local_addr().unwrap());
+            Harness::listen(listener).with_timeout(timeout)
+        }
+        _ => panic!("Usage: parcheesi conformance listen [addr] | exec <program> [args...]"),
+    };
+
+    let report = harness.run();
+    print!("{}", report.report());
+    if !report.passed() {
+        process::exit(1);
+    }
+}
+
+/// A tournament between the built-in players.
+fn builtin_tournament(games: usize, seed: usize, rules: RuleSet) -> Tournament {
+    let mut tournament = Tournament::new(games, seed);
+    tournament.set_rules(rules);
+    tournament.enter(Box::new(move |clr| {
+        Box::new(autoplayers::RandomPlayer::new("Random".to_string(), clr, 1)
+                     .with_rules(rules)) as Box<Player>
+    }));
+    tournament.enter(Box::new(move |clr| {
+        Box::new(autoplayers::move_first_pawn_player("MoveFirst".to_string(), clr)
+                     .with_rules(rules)) as Box<Player>
+    }));
+    tournament.enter(Box::new(move |clr| {
+        Box::new(autoplayers::move_last_pawn_player("MoveLast".to_string(), clr)
+                     .with_rules(rules)) as Box<Player>
+    }));
+    tournament
+}
+
+/// Play one game between the built-in players and print the engine's
+/// review of it.
+///
+/// Usage: parcheesi review [seed] [depth]
+fn review_game(args: &[String]) {
+    let seed: usize = args.get(0)
+        .map(|s| s.parse().expect("seed must be a number"))
+        .unwrap_or(0);
+    let depth: usize = args.get(1)
+        .map(|s| s.parse().expect("depth must be a number"))
+        .unwrap_or(1);
+
+    let tournament: Tournament = builtin_tournament(1, seed, RuleSet::standard());
+    let seating = vec![(0, Color::Red), (1, Color::Blue), (2, Color::Yellow)];
+    let outcome = tournament.play_game(&seating, seed);
+
+    let analyzer: Analyzer = Analyzer::new().with_depth(depth);
+    print!("{}", review::review(&outcome.record, &analyzer).report());
+}
+
+/// List every distinct way the player to move can end their turn
+/// from a position given in notation.
+///
+/// Usage: parcheesi moves <position> [standard|parchis|simple]
+fn show_moves(args: &[String]) {
+    let position: Position = match args.get(0).map(|s| Position::parse(s)) {
+        Some(Ok(position)) => position,
+        Some(Err(e)) => {
+            println!("Invalid position: {}", e);
+            return;
+        }
+        None => {
+            println!("Usage: parcheesi moves <position> [rules]");
+            return;
+        }
+    };
+    let rules: RuleSet = args.get(1)
+        .map(|s| RuleSet::from_name(s).expect("unknown rules"))
+        .unwrap_or_default();
+
+    let mut table: TranspositionTable<()> = TranspositionTable::new(1 << 16);
+    let outcomes = gametree::turn_outcomes(&position.board,
+                                           &position.dice,
+                                           position.to_move,
+                                           &rules,
+                                           &mut table);
+    for (moves, board) in outcomes {
+        println!("{}", board.to_notation());
+        for m in moves.iter() {
+            println!("    {:?} {:?}", m.pawn, m.m_type);
+        }
+    }
+}
+
+/// Rank the turns the player to move can take from a position given
+/// in notation, best first, looking `depth` turns ahead.
+///
+/// Usage: parcheesi hint <position> [depth] [standard|parchis|simple]
+fn show_hints(args: &[String]) {
+    let position: Position = match args.get(0).map(|s| Position::parse(s)) {
+        Some(Ok(position)) => position,
+        Some(Err(e)) => {
+            println!("Invalid position: {}", e);
+            return;
+        }
+        None => {
+            println!("Usage: parcheesi hint <position> [depth] [rules]");
+            return;
+        }
+    };
+    let depth: usize = args.get(1)
+        .map(|s| s.parse().expect("depth must be a number"))
+        .unwrap_or(1);
+    let rules: RuleSet = args.get(2)
+        .map(|s| RuleSet::from_name(s).expect("unknown rules"))
+        .unwrap_or_default();
+
+    if race::is_race(&position.board) {
+        show_race(&position, rules);
+        return;
+    }
+
+    let analyses = Analyzer::new()
+        .with_rules(rules)
+        .with_depth(depth)
+        .analyze(&position.board, &position.dice, position.to_move);
+    for (rank, analysis) in analyses.iter().enumerate() {
+        println!("{:>3}. {:>7.2}  {}",
+                 rank + 1,
+                 analysis.score,
+                 analysis.board.to_notation());
+        for m in analysis.moves.iter() {
+            println!("             {:?} {:?}", m.pawn, m.m_type);
+        }
+        let chances = probability::chance_bopped_with(&analysis.board, &position.to_move, &rules);
+        for (id, chance) in chances.iter().enumerate().filter(|&(_, &c)| c > 0.0) {
+            println!("             pawn {} bopped {:.1}%", id, chance * 100.0);
+        }
+    }
+}
+
+/// Print the perfect turn in a race, and everyone's chance of winning.
+fn show_race(position: &Position, rules: RuleSet) {
+    let mut solver: RaceSolver = RaceSolver::new().with_rules(rules);
+    match solver.best_turn(&position.board, &position.dice, position.to_move) {
+        Some((moves, after)) => {
+            println!("Best: {}", after.to_notation());
+            for m in moves.iter() {
+                println!("      {:?} {:?}", m.pawn, m.m_type);
+            }
+        }
+        None => println!("No moves"),
+    }
+
+    match solver.win_chances(&position.board, &position.to_move) {
+        Ok(chances) => {
+            for (clr, chance) in chances.iter() {
+                println!("{:<8} {:>6.2} turns {:>6.1}% to win",
+                         clr.to_string(),
+                         solver.expected_turns(&position.board, clr),
+                         chance * 100.0);
+            }
+        }
+        Err(e) => println!("{}", e),
+    }
+}
+
+/// Print the rating leaderboard, or the rating history of one or
+/// all players.
+///
+/// Usage: parcheesi ratings [leaderboard | history [name]]
+fn show_ratings(args: &[String]) {
+    let ledger: Ledger = Ledger::load(&ledger_path())
+        .expect("Could not load ratings");
+
+    match args.get(0).map(|s| s.as_str()) {
+        Some("history") => {
+            print!("{}",
+                   ledger.history_table(args.get(1).map(|s| s.as_str())))
+        }
+        _ => print!("{}", ledger.leaderboard_table()),
+    }
+}
+
+/// Where ratings are kept. Set PARCHEESI_RATINGS to use another file.
+fn ledger_path() -> PathBuf {
+    match env::var("PARCHEESI_RATINGS") {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => PathBuf::from(rating::DEFAULT_LEDGER_PATH),
+    }
+}
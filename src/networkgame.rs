@@ -1,43 +1,1004 @@
-use std::net::{TcpListener, TcpStream};
-use std::io::{Write, BufReader, BufWriter, BufRead};
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
 use std::thread;
+use std::time::Duration;
+use super::board::Color;
+use super::constants::*;
+use super::deserialize::{self, LobbyRequest};
+use super::dice::Dice;
+use super::board::Board;
+use super::game::{Game, GameOutcome, Move};
+use super::networkplayer::{RemotePlayer, Session};
+use super::player::{Player, TurnEvent};
+use super::rules::RuleSet;
+use super::serialize;
+use super::spectator::Broadcast;
+use super::timecontrol::TimeControl;
+use super::tournament::{PlayerFactory, DEFAULT_TURN_LIMIT};
 
+/// How often a game waiting for players checks for new arrivals and
+/// for its host starting it.
+pub static POLL_INTERVAL: Duration = Duration::from_millis(50);
 
-// Mapping between colors and TCP Streams will be needed
+/// The fewest players a host can start a game with, counting bots.
+pub static MIN_PLAYERS: usize = 2;
 
+/// How long a player who loses their connection mid-game has to
+/// reconnect before they lose their seat.
+pub static DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+/// Serve a lobby on the default port, forever.
 pub fn start_server() {
     let listener = TcpListener::bind("127.0.0.1:8000").unwrap();
+    Lobby::new().serve(listener);
+}
+
+/// A game waiting for players, as shown to clients looking for one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameListing {
+    pub id: usize,
+    pub open_seats: Vec<Color>,
+}
+
+/// A game waiting for players, as the lobby sees it.
+struct OpenGame {
+    taken: Vec<Color>,
+    /// Hands players who join over to the game's thread.
+    seats: Sender<(Color, RemotePlayer)>,
+}
+
+struct LobbyState {
+    next_id: usize,
+    open: BTreeMap<usize, OpenGame>,
+    /// Hands reconnecting players over to their seats, by session token.
+    sessions: BTreeMap<String, Sender<TcpStream>>,
+    /// Each game's broadcast, from when it's created until it's over.
+    broadcasts: BTreeMap<usize, Broadcast>,
+}
+
+/// Hosts any number of games at once.
+///
+/// Clients connect to the lobby and send lobby requests (see
+/// `LobbyRequest`), one per line, until they're seated in a game:
+///
+/// - `<list-games>` is answered with `<games>`, the games waiting for
+///   players and their open seats,
+/// - `<create-game>` hosts a new game and seats the host in it,
+/// - `<join-game>` seats the client in the game with the given id,
+/// - `<watch-game>` follows the game with the given id without playing
+///   in it. It's answered with `<watching>`, and from then on the client
+///   is sent the game as a spectator sees it (see `Broadcast`).
+///
+/// Seated clients are answered with `<joined>`, naming the game and
+/// their color; a color can be asked for, otherwise the first free one
+/// is given. Requests that can't be met are answered with `<error>`.
+///
+/// Every game runs on a thread of its own. It starts as soon as all four
+/// seats are taken, or when its host sends `<begin-game>`. From then
+/// on, its players speak the usual game protocol (see `RemotePlayer`).
+/// If the host leaves before then, the game is closed, and everyone who
+/// joined it is sent an `<error>` saying so.
+///
+/// If the lobby has bots, seats still empty when a game starts are
+/// filled with them, so a host can start a game on their own.
+///
+/// Every player is given a session token when their game starts. A
+/// player who loses their connection can connect to the lobby again and
+/// send `<resume>` with their token to get their seat back, as long as
+/// they do so within the grace period. Otherwise they forfeit, or a
+/// substitute bot plays out the game in their place.
+#[derive(Clone)]
+pub struct Lobby {
+    state: Arc<Mutex<LobbyState>>,
+    rules: RuleSet,
+    bots: Arc<Vec<PlayerFactory>>,
+    results: Option<Sender<(usize, GameOutcome)>>,
+    grace: Duration,
+    substitute: Option<Arc<PlayerFactory>>,
+    time_control: TimeControl,
+}
+
+impl Lobby {
+    pub fn new() -> Lobby {
+        Lobby {
+            state: Arc::new(Mutex::new(LobbyState {
+                                           next_id: 1,
+                                           open: BTreeMap::new(),
+                                           sessions: BTreeMap::new(),
+                                           broadcasts: BTreeMap::new(),
+                                       })),
+            rules: RuleSet::standard(),
+            bots: Arc::new(Vec::new()),
+            results: None,
+            grace: DEFAULT_GRACE_PERIOD,
+            substitute: None,
+            time_control: TimeControl::unlimited(),
+        }
+    }
+
+    /// Play games by the given rules instead of the standard ones.
+    pub fn with_rules(mut self, rules: RuleSet) -> Lobby {
+        self.rules = rules;
+        self
+    }
+
+    /// Fill the empty seats of every game with bots when it starts.
+    /// Empty seats take turns using each of the given bots, in order.
+    pub fn with_bots(mut self, bots: Vec<PlayerFactory>) -> Lobby {
+        self.bots = Arc::new(bots);
+        self
+    }
+
+    /// Hold a disconnected player's seat for this long instead of
+    /// `DEFAULT_GRACE_PERIOD`.
+    pub fn with_grace_period(mut self, grace: Duration) -> Lobby {
+        self.grace = grace;
+        self
+    }
+
+    /// Replace players who don't reconnect in time with a bot built by
+    /// the factory, instead of having them forfeit.
+    pub fn with_substitute(mut self, substitute: PlayerFactory) -> Lobby {
+        self.substitute = Some(Arc::new(substitute));
+        self
+    }
+
+    /// Limit how long players can take to choose their moves, so a
+    /// client who stops answering can't stall a game. Bots and
+    /// substitutes are held to the same limits.
+    pub fn with_time_control(mut self, time_control: TimeControl) -> Lobby {
+        self.time_control = time_control;
+        self
+    }
+
+    /// Send the id and outcome of every game once it's over.
+    pub fn with_results(mut self, results: Sender<(usize, GameOutcome)>) -> Lobby {
+        self.results = Some(results);
+        self
+    }
+
+    /// Start accepting clients on the given address, on a thread of its
+    /// own. Returns the address the lobby listens on.
+    pub fn listen(&self, addr: &str) -> io::Result<SocketAddr> {
+        let listener: TcpListener = TcpListener::bind(addr)?;
+        let local_addr: SocketAddr = listener.local_addr()?;
+        let lobby: Lobby = self.clone();
+        thread::spawn(move || lobby.serve(listener));
+        Ok(local_addr)
+    }
+
+    /// Accept clients on the listener, forever.
+    pub fn serve(&self, listener: TcpListener) -> () {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let lobby: Lobby = self.clone();
+                    thread::spawn(move || lobby.welcome(stream));
+                }
+                Err(e) => warn!("Client couldn't connect: {}", e),
+            }
+        }
+    }
+
+    /// The games waiting for players, oldest first.
+    pub fn open_games(&self) -> Vec<GameListing> {
+        let state = self.state.lock().expect("Lobby poisoned");
+        state
+            .open
+            .iter()
+            .map(|(&id, game)| {
+                     GameListing {
+                         id: id,
+                         open_seats: COLORS
+                             .iter()
+                             .cloned()
+                             .filter(|clr| !game.taken.contains(clr))
+                             .collect(),
+                     }
+                 })
+            .collect()
+    }
+
+    /// Answer a client's lobby requests until they're seated in a game
+    /// or leave.
+    fn welcome(&self, stream: TcpStream) -> () {
+        info!("Client connected");
+        let mut client: RemotePlayer = match RemotePlayer::new(stream) {
+            Ok(client) => client,
+            Err(e) => {
+                warn!("Couldn't talk to client: {}", e);
+                return;
+            }
+        };
+
+        loop {
+            let request: String = match client.receive() {
+                Ok(request) => request,
+                Err(_) => return,
+            };
+
+            let answer: String = match deserialize::deserialize_lobby_request(&request) {
+                Ok(LobbyRequest::ListGames) => serialize::xml_games(&self.open_games()),
+                Ok(LobbyRequest::CreateGame(color)) => {
+                    self.create_game(client, color.unwrap_or(Color::Red));
+                    return;
+                }
+                Ok(LobbyRequest::JoinGame(id, color)) => {
+                    match self.join_game(client, id, color) {
+                        Ok(()) => return,
+                        Err((returned, reason)) => {
+                            client = returned;
+                            serialize::xml_error(reason)
+                        }
+                    }
+                }
+                Ok(LobbyRequest::WatchGame(id)) => {
+                    match self.watch_game(client, id) {
+                        Ok(()) => return,
+                        Err((returned, reason)) => {
+                            client = returned;
+                            serialize::xml_error(reason)
+                        }
+                    }
+                }
+                Ok(LobbyRequest::BeginGame) => serialize::xml_error("Not hosting a game"),
+                Ok(LobbyRequest::Resume(token)) => {
+                    match self.resume(client, &token) {
+                        Ok(()) => return,
+                        Err((returned, reason)) => {
+                            client = returned;
+                            serialize::xml_error(reason)
+                        }
+                    }
+                }
+                Err(reason) => serialize::xml_error(reason),
+            };
+
+            if client.send(&answer).is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Open a new game with the host in it, and start its thread.
+    fn create_game(&self, mut host: RemotePlayer, color: Color) -> () {
+        let (seats, arrivals) = mpsc::channel();
+        let broadcast: Broadcast = Broadcast::new();
+        let id: usize = {
+            let mut state = self.state.lock().expect("Lobby poisoned");
+            let id: usize = state.next_id;
+            state.next_id += 1;
+            state.open.insert(id,
+                              OpenGame {
+                                  taken: vec![color],
+                                  seats: seats,
+                              });
+            state.broadcasts.insert(id, broadcast.clone());
+            id
+        };
+        info!("Game {} created", id);
+
+        if host.send(&serialize::xml_joined(id, &color)).is_err() {
+            self.close_game(id);
+            self.end_broadcast(id);
+            return;
+        }
+
+        let lobby: Lobby = self.clone();
+        thread::spawn(move || lobby.run_game(id, color, host, arrivals, broadcast));
+    }
+
+    /// Seat a player in an open game, handing them over to the game's
+    /// thread. If they can't be seated, they're handed back with the
+    /// reason why.
+    fn join_game(&self,
+                 mut player: RemotePlayer,
+                 id: usize,
+                 color: Option<Color>)
+                 -> Result<(), (RemotePlayer, &'static str)> {
+        let mut state = self.state.lock().expect("Lobby poisoned");
+        let full: bool = {
+            let game: &mut OpenGame = match state.open.get_mut(&id) {
+                Some(game) => game,
+                None => return Err((player, "No such game")),
+            };
+
+            let seat: Color = match color {
+                Some(clr) if game.taken.contains(&clr) => {
+                    return Err((player, "Color is already taken"))
+                }
+                Some(clr) => clr,
+                None => {
+                    match COLORS.iter().find(|clr| !game.taken.contains(clr)) {
+                        Some(&clr) => clr,
+                        None => return Err((player, "Game is full")),
+                    }
+                }
+            };
+
+            // Games stop taking players while holding the same lock, so
+            // a player handed over here is always picked up.
+            if player.send(&serialize::xml_joined(id, &seat)).is_err() {
+                return Ok(());
+            }
+            if let Err(mpsc::SendError((_, player))) = game.seats.send((seat, player)) {
+                return Err((player, "Game has already started"));
+            }
+            game.taken.push(seat);
+            game.taken.len() == COLORS.len()
+        };
+
+        // Full games stop taking players right away.
+        if full {
+            state.open.remove(&id);
+        }
+        Ok(())
+    }
+
+    /// Hand a reconnecting player's new connection over to their seat,
+    /// which answers them. If there's no such session, the player is
+    /// handed back.
+    fn resume(&self, player: RemotePlayer, token: &str) -> Result<(), (RemotePlayer, &'static str)> {
+        let state = self.state.lock().expect("Lobby poisoned");
+        match state.sessions.get(token) {
+            Some(reconnect) => {
+                info!("Player resuming a session");
+                reconnect.send(player.into_stream()).ok();
+                Ok(())
+            }
+            None => Err((player, "No such session")),
+        }
+    }
+
+    /// Hand a client over to the broadcast of the game they want to
+    /// watch. If there's no such game, the client is handed back.
+    fn watch_game(&self,
+                  mut client: RemotePlayer,
+                  id: usize)
+                  -> Result<(), (RemotePlayer, &'static str)> {
+        let broadcast: Broadcast = match self.state
+                  .lock()
+                  .expect("Lobby poisoned")
+                  .broadcasts
+                  .get(&id) {
+            Some(broadcast) => broadcast.clone(),
+            None => return Err((client, "No such game")),
+        };
+
+        if client.send(&serialize::xml_watching(id)).is_ok() {
+            info!("Spectator watching game {}", id);
+            broadcast.add_watcher(client.into_stream());
+        }
+        Ok(())
+    }
+
+    /// Stop listing a game, so nobody else can join it.
+    fn close_game(&self, id: usize) -> () {
+        self.state.lock().expect("Lobby poisoned").open.remove(&id);
+    }
+
+    /// Stop taking watchers for a game that's over. Its watchers are let
+    /// go once the game's thread drops the broadcast.
+    fn end_broadcast(&self, id: usize) -> () {
+        self.state.lock().expect("Lobby poisoned").broadcasts.remove(&id);
+    }
+
+    /// Wait for a game's seats to fill, or for its host to start it,
+    /// then play it to the end.
+    fn run_game(&self,
+                id: usize,
+                host_color: Color,
+                host: RemotePlayer,
+                arrivals: Receiver<(Color, RemotePlayer)>,
+                broadcast: Broadcast)
+                -> () {
+        let mut seated: BTreeMap<Color, RemotePlayer> = map!{ host_color => host };
+
+        while seated.len() < COLORS.len() {
+            match arrivals.recv_timeout(POLL_INTERVAL) {
+                Ok((clr, player)) => {
+                    info!("Player joined game {} as {:?}", id, clr);
+                    seated.insert(clr, player);
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => (),
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            let players: usize = if self.bots.is_empty() {
+                seated.len()
+            } else {
+                COLORS.len()
+            };
+            let host: &mut RemotePlayer = seated
+                .get_mut(&host_color)
+                .expect("The host is seated");
+            let request: String = match host.poll(POLL_INTERVAL) {
+                Ok(Some(request)) => request,
+                Ok(None) => continue,
+                Err(_) => {
+                    self.abandon_game(id, host_color, seated, &arrivals);
+                    return;
+                }
+            };
+
+            let answer: String = match deserialize::deserialize_lobby_request(&request) {
+                Ok(LobbyRequest::BeginGame) if players >= MIN_PLAYERS => break,
+                Ok(LobbyRequest::BeginGame) => serialize::xml_error("Not enough players"),
+                Ok(_) => serialize::xml_error("Waiting for the game to start"),
+                Err(reason) => serialize::xml_error(reason),
+            };
+            if host.send(&answer).is_err() {
+                self.abandon_game(id, host_color, seated, &arrivals);
+                return;
+            }
+        }
+
+        // Anyone who joined while the game was starting still gets to play.
+        self.close_game(id);
+        while let Ok((clr, player)) = arrivals.try_recv() {
+            seated.insert(clr, player);
+        }
+
+        // Give everyone a session, so they can reconnect if they need to.
+        let mut tokens: Vec<String> = Vec::new();
+        let seated: BTreeMap<Color, RemotePlayer> = {
+            let mut state = self.state.lock().expect("Lobby poisoned");
+            seated
+                .into_iter()
+                .map(|(clr, player)| {
+                         let (session, reconnect) = Session::new(self.grace);
+                         state.sessions.insert(session.token.clone(), reconnect);
+                         tokens.push(session.token.clone());
+                         (clr, player.with_session(session))
+                     })
+                .collect()
+        };
+
+        let outcome: GameOutcome = self.play_game(seated, broadcast);
+        {
+            let mut state = self.state.lock().expect("Lobby poisoned");
+            for token in tokens.iter() {
+                state.sessions.remove(token);
+            }
+            state.broadcasts.remove(&id);
+        }
+        info!("Game {} over after {} turns. Winner: {:?}",
+              id,
+              outcome.turns,
+              outcome.winner);
+        if let Some(ref results) = self.results {
+            results.send((id, outcome)).ok();
+        }
+    }
+
+    /// Close a game whose host left before starting it. Everyone else
+    /// who joined is told why before they're let go.
+    fn abandon_game(&self,
+                    id: usize,
+                    host_color: Color,
+                    mut seated: BTreeMap<Color, RemotePlayer>,
+                    arrivals: &Receiver<(Color, RemotePlayer)>)
+                    -> () {
+        info!("Host left game {}, closing it", id);
+        self.close_game(id);
+        self.end_broadcast(id);
+
+        // Nobody can join once the game is closed, but someone may have
+        // joined just before.
+        while let Ok((clr, player)) = arrivals.try_recv() {
+            seated.insert(clr, player);
+        }
+        seated.remove(&host_color);
+        for (_, mut player) in seated.into_iter() {
+            player.send(&serialize::xml_error("Host left")).ok();
+        }
+    }
+
+    /// Play a game between the seated players, and bots in any empty
+    /// seats, showing it on the game's broadcast.
+    fn play_game(&self, seated: BTreeMap<Color, RemotePlayer>, broadcast: Broadcast) -> GameOutcome {
+        let mut players: BTreeMap<Color, Box<Player>> = seated
+            .into_iter()
+            .map(|(clr, player)| {
+                     let player: Box<Player> = match self.substitute {
+                         Some(ref substitute) => {
+                             let substitute: Arc<PlayerFactory> = substitute.clone();
+                             Box::new(HeldSeat {
+                                          player: player,
+                                          substitute: self.time_control
+                                              .player(move || substitute(clr)),
+                                      })
+                         }
+                         None => Box::new(player),
+                     };
+                     (clr, player)
+                 })
+            .collect();
+        if !self.bots.is_empty() {
+            let empty: Vec<Color> = COLORS
+                .iter()
+                .cloned()
+                .filter(|clr| !players.contains_key(clr))
+                .collect();
+            for (i, clr) in empty.into_iter().enumerate() {
+                let bots: Arc<Vec<PlayerFactory>> = self.bots.clone();
+                let bot = move || (bots[i % bots.len()])(clr);
+                players.insert(clr, self.time_control.player(bot));
+            }
+        }
+
+        let colors: Vec<Color> = players.keys().cloned().collect();
+        let mut game: Game = Game::with_colors(&colors);
+        game.set_rules(self.rules);
+        game.set_time_control(self.time_control);
+        for (clr, player) in players.into_iter() {
+            game.seat_player(player, clr)
+                .expect("Each color is seated once");
+        }
+        game.add_spectator(Box::new(broadcast));
+
+        // Bots can be left playing each other after everyone else has
+        // gone, so don't let their games run forever.
+        game.play(Dice::roll, DEFAULT_TURN_LIMIT)
+    }
+}
+
+/// A network player's seat, which a substitute takes over if they
+/// disconnect for good. The substitute is shown the whole game, so it's
+/// ready to step in at any time.
+struct HeldSeat {
+    player: RemotePlayer,
+    substitute: Box<Player>,
+}
+
+impl HeldSeat {
+    /// Whether the substitute should play instead of the player, after
+    /// the player's call failed with the given error.
+    fn substitute_after<T>(&self, result: &Result<T, &'static str>) -> bool {
+        if result.is_err() && !self.player.is_connected() {
+            info!("Substitute taking over a seat");
+            return true;
+        }
+        false
+    }
+}
+
+impl Player for HeldSeat {
+    fn start_game(&mut self, color: Color) -> Result<String, &'static str> {
+        let substitute_name: String = self.substitute.start_game(color)?;
+        let result: Result<String, &'static str> = self.player.start_game(color);
+        if self.substitute_after(&result) {
+            return Ok(substitute_name);
+        }
+        result
+    }
 
-    fn handle_client(stream: TcpStream) {
-        println!("Client Connected");
+    fn do_move(&mut self, board: Board, dice: Dice) -> Result<Vec<Move>, &'static str> {
+        if self.player.is_connected() {
+            let result = self.player.do_move(board.clone(), dice.clone());
+            if !self.substitute_after(&result) {
+                return result;
+            }
+        }
+        self.substitute.do_move(board, dice)
+    }
+
+    fn set_time_limit(&mut self, limit: Option<Duration>) -> () {
+        self.substitute.set_time_limit(limit);
+        self.player.set_time_limit(limit);
+    }
+
+    fn doubles_penalty(&mut self) -> Result<(), &'static str> {
+        if self.player.is_connected() {
+            let result = self.player.doubles_penalty();
+            if !self.substitute_after(&result) {
+                return result;
+            }
+        }
+        self.substitute.doubles_penalty()
+    }
 
-        let mut writer = BufWriter::new(&stream);
-        writer.write_all("Red\n".as_bytes());
+    fn observe_turn(&mut self, event: &TurnEvent) -> Result<(), &'static str> {
+        self.substitute.observe_turn(event)?;
+        self.player.observe_turn(event)
+    }
 
+    fn game_over(&mut self, outcome: &GameOutcome) -> Result<(), &'static str> {
+        self.substitute.game_over(outcome)?;
+        self.player.game_over(outcome)
+    }
+}
 
-        writer
-            .flush()
-            .expect("could not flush");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use autoplayers;
+    use game::RecordedRoll;
+    use timecontrol::OnTimeout;
 
-        let mut reader = BufReader::new(&stream);
-        let mut response = String::new();
-        reader
-            .read_line(&mut response)
-            .expect("could not read");
-        println!("Server received {}", response);
+    fn bot(name: &'static str) -> PlayerFactory {
+        Box::new(move |clr| {
+                     Box::new(autoplayers::move_first_pawn_player(name.to_string(), clr)) as
+                     Box<Player>
+                 })
+    }
 
+    /// A client of the lobby. Once seated, it passes every turn.
+    struct Client {
+        stream: TcpStream,
+        reader: BufReader<TcpStream>,
     }
 
-    for stream in listener.incoming() {
+    impl Client {
+        fn connect(addr: SocketAddr) -> Client {
+            let stream: TcpStream = TcpStream::connect(addr).unwrap();
+            let reader: BufReader<TcpStream> = BufReader::new(stream.try_clone().unwrap());
+            Client {
+                stream: stream,
+                reader: reader,
+            }
+        }
 
-        match stream {
-            Ok(stream) => {
-                thread::spawn(move || handle_client(stream));
+        fn send(&mut self, msg: &str) -> () {
+            self.stream
+                .write_all((msg.to_string() + "\n").as_bytes())
+                .unwrap();
+        }
+
+        /// The next message from the server, or `None` once it hangs up.
+        fn receive(&mut self) -> Option<String> {
+            let mut line: String = String::new();
+            match self.reader.read_line(&mut line).unwrap() {
+                0 => None,
+                _ => Some(line.trim().to_string()),
             }
-            Err(_) => {
-                panic!("No connection");
+        }
+
+        fn ask(&mut self, msg: &str) -> String {
+            self.send(msg);
+            self.receive().unwrap()
+        }
+
+        /// Play until we've been asked for `turns` turns, then hang up.
+        /// Returns how many turns we were asked for, which is fewer if
+        /// the server hung up first.
+        fn play(mut self, name: &str, turns: usize) -> usize {
+            let mut played: usize = 0;
+            while let Some(msg) = self.receive() {
+                match deserialize::deserialize_decision(msg) {
+                    deserialize::XmlMessage::StartGame => {
+                        self.send(&("<name> ".to_string() + name + " </name>"))
+                    }
+                    deserialize::XmlMessage::DoMove => {
+                        played += 1;
+                        if played == turns {
+                            break;
+                        }
+                        self.send(&serialize::xml_moves(&Vec::new()));
+                    }
+                    _ => self.send(&serialize::xml_void()),
+                }
             }
+            played
+        }
+    }
+
+    #[test]
+    /// A host creates a game, another client finds and joins it, and
+    /// the host starts the game, which is played until everyone leaves.
+    fn lobby_hosts_a_game() {
+        let lobby: Lobby = Lobby::new().with_grace_period(Duration::from_millis(0));
+        let addr: SocketAddr = lobby.listen("127.0.0.1:0").unwrap();
+
+        let mut host: Client = Client::connect(addr);
+        assert_eq!(host.ask(&serialize::xml_create_game(Some(Color::Blue))),
+                   serialize::xml_joined(1, &Color::Blue));
+
+        let mut guest: Client = Client::connect(addr);
+        let listing: GameListing = GameListing {
+            id: 1,
+            open_seats: vec![Color::Red, Color::Yellow, Color::Green],
+        };
+        assert_eq!(guest.ask(&serialize::xml_list_games()),
+                   serialize::xml_games(&[listing]));
+        assert_eq!(guest.ask(&serialize::xml_join_game(1, Some(Color::Blue))),
+                   serialize::xml_error("Color is already taken"));
+        assert_eq!(guest.ask(&serialize::xml_join_game(1, None)),
+                   serialize::xml_joined(1, &Color::Red));
+
+        host.send(&serialize::xml_begin_game());
+        let guest_turns = thread::spawn(move || guest.play("Guest", 3));
+        assert_eq!(host.play("Host", 3), 3);
+        assert_eq!(guest_turns.join().unwrap(), 3);
+        assert!(lobby.open_games().is_empty());
+    }
+
+    #[test]
+    /// When the host leaves before starting their game, everyone who
+    /// joined it is told why, and the game is closed.
+    fn host_leaving_closes_the_game() {
+        let lobby: Lobby = Lobby::new();
+        let addr: SocketAddr = lobby.listen("127.0.0.1:0").unwrap();
+
+        let mut host: Client = Client::connect(addr);
+        host.ask(&serialize::xml_create_game(None));
+        let mut guest: Client = Client::connect(addr);
+        assert_eq!(guest.ask(&serialize::xml_join_game(1, None)),
+                   serialize::xml_joined(1, &Color::Blue));
+
+        drop(host);
+        assert_eq!(guest.receive(), Some(serialize::xml_error("Host left")));
+        assert_eq!(guest.receive(), None);
+        assert!(lobby.open_games().is_empty());
+    }
+
+    #[test]
+    /// Games start by themselves once every seat is taken, and can't
+    /// be joined after that.
+    fn full_games_start() {
+        let lobby: Lobby = Lobby::new().with_grace_period(Duration::from_millis(0));
+        let addr: SocketAddr = lobby.listen("127.0.0.1:0").unwrap();
+
+        let mut host: Client = Client::connect(addr);
+        host.ask(&serialize::xml_create_game(None));
+        assert_eq!(host.ask(&serialize::xml_begin_game()),
+                   serialize::xml_error("Not enough players"));
+
+        let mut guests: Vec<Client> = Vec::new();
+        for clr in [Color::Blue, Color::Yellow, Color::Green].iter() {
+            let mut guest: Client = Client::connect(addr);
+            assert_eq!(guest.ask(&serialize::xml_join_game(1, None)),
+                       serialize::xml_joined(1, clr));
+            guests.push(guest);
+        }
+
+        let mut latecomer: Client = Client::connect(addr);
+        assert_eq!(latecomer.ask(&serialize::xml_join_game(1, None)),
+                   serialize::xml_error("No such game"));
+
+        let games: Vec<_> = guests
+            .into_iter()
+            .map(|guest| thread::spawn(move || guest.play("Guest", 2)))
+            .collect();
+        host.play("Host", 2);
+        for game in games {
+            game.join().unwrap();
+        }
+    }
+
+    #[test]
+    /// A host can start a game alone, with bots in every other seat,
+    /// and the bots keep playing after the host leaves.
+    fn bots_fill_empty_seats() {
+        let (results, finished) = mpsc::channel();
+        let lobby: Lobby = Lobby::new()
+            .with_bots(vec![bot("Bot")])
+            .with_grace_period(Duration::from_millis(0))
+            .with_results(results);
+        let addr: SocketAddr = lobby.listen("127.0.0.1:0").unwrap();
+
+        let mut host: Client = Client::connect(addr);
+        host.ask(&serialize::xml_create_game(Some(Color::Yellow)));
+        host.send(&serialize::xml_begin_game());
+        assert_eq!(host.play("Host", 3), 3);
+
+        let (id, outcome) = finished.recv_timeout(Duration::from_secs(60)).unwrap();
+        assert_eq!(id, 1);
+        assert_eq!(outcome.names,
+                   map!{
+                       Color::Red => "Bot".to_string(),
+                       Color::Blue => "Bot".to_string(),
+                       Color::Yellow => "Host".to_string(),
+                       Color::Green => "Bot".to_string()
+                   });
+        assert_eq!(outcome.stats[&Color::Yellow].violation,
+                   Some("Player disconnected".to_string()));
+        assert!(outcome.turns > 3);
+    }
+
+    #[test]
+    /// Each game has its own broadcast, so a watcher follows only the
+    /// game they asked for, however many are played at once.
+    fn watchers_pick_a_game() {
+        let (results, finished) = mpsc::channel();
+        let lobby: Lobby = Lobby::new()
+            .with_bots(vec![bot("Bot")])
+            .with_grace_period(Duration::from_millis(0))
+            .with_results(results);
+        let addr: SocketAddr = lobby.listen("127.0.0.1:0").unwrap();
+
+        let mut hosts: Vec<Client> = Vec::new();
+        for id in 1..3 {
+            let mut host: Client = Client::connect(addr);
+            assert_eq!(host.ask(&serialize::xml_create_game(Some(Color::Red))),
+                       serialize::xml_joined(id, &Color::Red));
+            hosts.push(host);
+        }
+
+        let mut watcher: Client = Client::connect(addr);
+        assert_eq!(watcher.ask(&serialize::xml_watch_game(9)),
+                   serialize::xml_error("No such game"));
+        assert_eq!(watcher.ask(&serialize::xml_watch_game(2)),
+                   serialize::xml_watching(2));
+
+        let playing: Vec<thread::JoinHandle<usize>> = hosts
+            .into_iter()
+            .zip(["One", "Two"].iter())
+            .map(|(mut host, &name)| {
+                     host.send(&serialize::xml_begin_game());
+                     thread::spawn(move || host.play(name, 3))
+                 })
+            .collect();
+
+        let mut seen: Vec<String> = Vec::new();
+        while let Some(msg) = watcher.receive() {
+            seen.push(msg);
+        }
+        for host in playing {
+            host.join().unwrap();
         }
+
+        let starts: Vec<&String> = seen.iter().filter(|m| m.starts_with("<game-start>")).collect();
+        assert_eq!(starts.len(), 1);
+        assert!(starts[0].contains("<name> Two </name>"));
+        assert!(!starts[0].contains("<name> One </name>"));
+        assert_eq!(seen.iter().filter(|m| m.starts_with("<game-over>")).count(), 1);
+        assert!(seen.last().unwrap().starts_with("<game-over>"));
+
+        let mut ids: Vec<usize> = (0..2)
+            .map(|_| finished.recv_timeout(Duration::from_secs(60)).unwrap().0)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[test]
+    /// Bots play by the lobby's rules, so they see a game through
+    /// without breaking them, whichever rules those are.
+    fn bots_play_by_lobby_rules() {
+        let rules: RuleSet = RuleSet::parchis();
+        let (results, finished) = mpsc::channel();
+        let lobby: Lobby = Lobby::new()
+            .with_rules(rules)
+            .with_bots(vec![Box::new(move |clr| {
+                                Box::new(autoplayers::move_first_pawn_player("First".to_string(),
+                                                                             clr)
+                                             .with_rules(rules)) as
+                                Box<Player>
+                            }),
+                            Box::new(move |clr| {
+                                Box::new(autoplayers::move_last_pawn_player("Last".to_string(),
+                                                                            clr)
+                                             .with_rules(rules)) as
+                                Box<Player>
+                            })])
+            .with_grace_period(Duration::from_millis(0))
+            .with_results(results);
+        let addr: SocketAddr = lobby.listen("127.0.0.1:0").unwrap();
+
+        let mut host: Client = Client::connect(addr);
+        host.ask(&serialize::xml_create_game(Some(Color::Red)));
+        host.send(&serialize::xml_begin_game());
+        host.play("Host", 1);
+
+        let (_, outcome) = finished.recv_timeout(Duration::from_secs(60)).unwrap();
+        for clr in [Color::Blue, Color::Yellow, Color::Green].iter() {
+            assert_eq!((*clr, outcome.stats[clr].violation.clone()), (*clr, None));
+        }
+        assert!(outcome.winner.is_some());
+    }
+
+    #[test]
+    /// A player who loses their connection can reconnect with their
+    /// session token, and is caught up and asked to move again.
+    fn players_resume_after_disconnecting() {
+        let (results, finished) = mpsc::channel();
+        let lobby: Lobby = Lobby::new()
+            .with_bots(vec![bot("Bot")])
+            .with_grace_period(Duration::from_secs(2))
+            .with_results(results);
+        let addr: SocketAddr = lobby.listen("127.0.0.1:0").unwrap();
+
+        let mut host: Client = Client::connect(addr);
+        host.ask(&serialize::xml_create_game(None));
+        host.send(&serialize::xml_begin_game());
+        let start: String = host.receive().unwrap();
+        let token: String = deserialize::deserialize_session(&start).unwrap();
+        let do_move: String = host.ask("<name> Host </name>");
+        assert!(do_move.starts_with("<do-move>"));
+        drop(host);
+
+        let mut client: Client = Client::connect(addr);
+        assert_eq!(client.ask(&serialize::xml_resume("not a token")),
+                   serialize::xml_error("No such session"));
+        let resumed: String = client.ask(&serialize::xml_resume(&token));
+        assert!(resumed.starts_with("<resumed> <board>"));
+        assert_eq!(client.receive().unwrap(), do_move);
+        drop(client);
+
+        // Leaving again for longer than the grace period forfeits the game.
+        let (_, outcome) = finished.recv_timeout(Duration::from_secs(60)).unwrap();
+        assert_eq!(outcome.names[&Color::Red], "Host");
+        assert_eq!(outcome.stats[&Color::Red].violation,
+                   Some("Player disconnected".to_string()));
+    }
+
+    #[test]
+    /// A substitute plays out the game for a player who doesn't come back.
+    fn substitutes_take_over_for_good() {
+        let (results, finished) = mpsc::channel();
+        let lobby: Lobby = Lobby::new()
+            .with_bots(vec![bot("Bot")])
+            .with_substitute(Box::new(|clr| {
+                                          Box::new(autoplayers::GreedyPlayer::new("Substitute"
+                                                                                      .to_string(),
+                                                                                  clr)) as
+                                          Box<Player>
+                                      }))
+            .with_grace_period(Duration::from_millis(0))
+            .with_results(results);
+        let addr: SocketAddr = lobby.listen("127.0.0.1:0").unwrap();
+
+        let mut host: Client = Client::connect(addr);
+        host.ask(&serialize::xml_create_game(None));
+        host.send(&serialize::xml_begin_game());
+        assert_eq!(host.play("Host", 3), 3);
+
+        let (_, outcome) = finished.recv_timeout(Duration::from_secs(60)).unwrap();
+        assert_eq!(outcome.names[&Color::Red], "Host");
+        assert_eq!(outcome.stats[&Color::Red].violation, None);
+        assert!(outcome.stats[&Color::Red].turns > 3);
     }
 
+    #[test]
+    /// A client who runs out of time has a turn played for them, and
+    /// the answer they send too late isn't taken for their next one.
+    fn late_answers_are_skipped() {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client: Client = Client::connect(listener.local_addr().unwrap());
+        let player: RemotePlayer = RemotePlayer::new(listener.accept().unwrap().0).unwrap();
+
+        let late_client = thread::spawn(move || {
+            client.receive().unwrap();
+            assert!(client.ask("<name> Late </name>").starts_with("<do-move>"));
+
+            // Answering the first move only once the next has been asked
+            // for, and with the wrong message, does no harm.
+            assert!(client.receive().unwrap().starts_with("<do-move>"));
+            client.send(&serialize::xml_void());
+            client.send(&serialize::xml_moves(&Vec::new()));
+        });
+
+        let outcome: GameOutcome = {
+            let mut game: Game = Game::with_colors(&[Color::Red, Color::Blue]);
+            game.set_time_control(TimeControl {
+                                      per_move: Some(Duration::from_millis(200)),
+                                      on_timeout: OnTimeout::DefaultTurn,
+                                      ..TimeControl::unlimited()
+                                  });
+            game.seat_player(Box::new(player), Color::Red).unwrap();
+            game.seat_player(bot("Bot")(Color::Blue), Color::Blue).unwrap();
+
+            // Nobody can enter with a 1 and a 2, so passing is always legal.
+            game.play(|_| (Dice { rolls: vec![1, 2] }, false), 3)
+        };
+        late_client.join().unwrap();
+
+        let red_rolls: Vec<&RecordedRoll> = outcome
+            .record
+            .iter()
+            .filter(|roll| roll.color == Color::Red)
+            .collect();
+        assert_eq!(red_rolls.len(), 2);
+        assert!(red_rolls[0].timed_out);
+        assert!(red_rolls[0].time_used >= Duration::from_millis(200));
+        assert!(!red_rolls[1].timed_out);
+        assert_eq!(outcome.stats[&Color::Red].timeouts, 1);
+        assert_eq!(outcome.stats[&Color::Red].violation, None);
+    }
 }
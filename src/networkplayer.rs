@@ -1,7 +1,322 @@
-use super::player::Player;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::{Duration, Instant};
+use rand::{self, Rng};
+use super::board::{Board, Color, Loc};
+use super::deserialize;
+use super::dice::Dice;
+use super::game::Move;
+use super::player::{Player, TurnEvent};
+use super::serialize;
+
+/// Number of characters in a session token.
+pub static TOKEN_LENGTH: usize = 24;
 
 pub trait NetworkPlayer: Player {
     fn connect(&mut self) -> ();
     fn send(&self, msg: String) -> ();
     fn receive(&mut self) -> ();
 }
+
+/// The server's side of a connection to a player on another machine.
+///
+/// Each call is sent to the client as an XML message on a line of its
+/// own, and the client's answer is read back the same way:
+///
+/// - `<start-game>` is answered with `<name>`,
+/// - `<do-move>` is answered with `<moves>`,
+/// - and `<doubles-penalty>` is answered with `<void>`.
+///
+/// A client who answers with the wrong message makes the call fail,
+/// which ejects them from the game. So does a client who disconnects,
+/// unless they have a session and reconnect in time.
+///
+/// In a timed game, we stop waiting for `<moves>` once the client's
+/// time is up. Their answer is skipped when it does come, so it isn't
+/// mistaken for the answer to the next message.
+pub struct RemotePlayer {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+    /// The start of a line that hasn't been fully received yet.
+    pending: String,
+    session: Option<Session>,
+    /// The board as of the last turn we heard about, to catch up a
+    /// client who reconnects.
+    board: Option<Board>,
+    connected: bool,
+    /// How long the client has to answer the next `<do-move>`.
+    time_limit: Option<Duration>,
+    /// Answers still to come to messages we stopped waiting on.
+    late_answers: usize,
+}
+
+/// Holds a player's seat while they reconnect.
+///
+/// The session's token is sent to the client when the game starts, and
+/// a client who loses their connection can reconnect with it (see
+/// `networkgame::Lobby`). Their new connection is sent to the session,
+/// and the player carries on from where they left off.
+pub struct Session {
+    pub token: String,
+    reconnects: Receiver<TcpStream>,
+    /// How long to wait for a client to reconnect before giving up on them.
+    grace: Duration,
+}
+
+impl Session {
+    /// A session with a fresh random token, and the sender to hand the
+    /// client's new connections to.
+    pub fn new(grace: Duration) -> (Session, Sender<TcpStream>) {
+        let (reconnect, reconnects) = mpsc::channel();
+        let token: String = rand::thread_rng()
+            .gen_ascii_chars()
+            .take(TOKEN_LENGTH)
+            .collect();
+        let session: Session = Session {
+            token: token,
+            reconnects: reconnects,
+            grace: grace,
+        };
+        (session, reconnect)
+    }
+}
+
+impl RemotePlayer {
+    pub fn new(stream: TcpStream) -> io::Result<RemotePlayer> {
+        // Every message is answered before the next one is sent, so
+        // waiting to batch small writes only slows the game down.
+        stream.set_nodelay(true)?;
+        let reader: BufReader<TcpStream> = BufReader::new(stream.try_clone()?);
+        Ok(RemotePlayer {
+               stream: stream,
+               reader: reader,
+               pending: String::new(),
+               session: None,
+               board: None,
+               connected: true,
+               time_limit: None,
+               late_answers: 0,
+           })
+    }
+
+    /// Let the client reconnect through the given session if they lose
+    /// their connection.
+    pub fn with_session(mut self, session: Session) -> RemotePlayer {
+        self.session = Some(session);
+        self
+    }
+
+    /// Whether the client is still playing, i.e. they haven't lost
+    /// their connection for good.
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// The connection to the client, e.g. to hand it to a session.
+    pub fn into_stream(self) -> TcpStream {
+        self.stream
+    }
+
+    /// Send a message to the client.
+    pub fn send(&mut self, msg: &str) -> Result<(), &'static str> {
+        debug!("Sending {}", msg);
+        self.stream
+            .write_all((msg.to_string() + "\n").as_bytes())
+            .map_err(|_| "Player disconnected")
+    }
+
+    /// Wait for the client's next message.
+    pub fn receive(&mut self) -> Result<String, &'static str> {
+        loop {
+            if let Some(msg) = self.read_line()? {
+                return Ok(msg);
+            }
+        }
+    }
+
+    /// Wait up to `timeout` for the client's next message, returning
+    /// `None` if they haven't sent a whole one by then.
+    pub fn poll(&mut self, timeout: Duration) -> Result<Option<String>, &'static str> {
+        self.stream
+            .set_read_timeout(Some(timeout))
+            .map_err(|_| "Player disconnected")?;
+        let msg: Result<Option<String>, &'static str> = self.read_line();
+        self.stream
+            .set_read_timeout(None)
+            .map_err(|_| "Player disconnected")?;
+        msg
+    }
+
+    /// Read the rest of the current line. A read that times out keeps
+    /// what it got, so the next read can finish the line.
+    fn read_line(&mut self) -> Result<Option<String>, &'static str> {
+        match self.reader.read_line(&mut self.pending) {
+            Ok(0) => Err("Player disconnected"),
+            Ok(_) if self.pending.ends_with('\n') => {
+                let msg: String = self.pending.trim().to_string();
+                self.pending.clear();
+                debug!("Received {}", msg);
+                Ok(Some(msg))
+            }
+            Ok(_) => Ok(None),
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock ||
+                          e.kind() == ErrorKind::TimedOut => Ok(None),
+            Err(_) => Err("Player disconnected"),
+        }
+    }
+
+    /// Send a message and wait for the client's answer, giving up once
+    /// `time_limit` is up, if there is one. If the client disconnects,
+    /// wait for them to reconnect and ask again.
+    fn ask(&mut self, msg: &str, time_limit: Option<Duration>) -> Result<String, &'static str> {
+        if !self.connected {
+            return Err("Player disconnected");
+        }
+        self.check_reconnected();
+
+        let deadline: Option<Instant> = time_limit.map(|limit| Instant::now() + limit);
+        loop {
+            match self.send(msg).and_then(|_| self.answer(deadline)) {
+                Ok(Some(answer)) => return Ok(answer),
+                Ok(None) => {
+                    info!("Player ran out of time");
+                    self.late_answers += 1;
+                    return Err("Out of time");
+                }
+                Err(e) => {
+                    if !self.wait_for_reconnect() {
+                        info!("Player didn't reconnect in time");
+                        self.connected = false;
+                        return Err(e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Wait for the client's answer, skipping any late answers to
+    /// earlier messages. Returns `None` if `deadline` passes first.
+    fn answer(&mut self, deadline: Option<Instant>) -> Result<Option<String>, &'static str> {
+        loop {
+            let msg: Option<String> = match deadline {
+                Some(deadline) => {
+                    let now: Instant = Instant::now();
+                    if now >= deadline {
+                        return Ok(None);
+                    }
+                    self.poll(deadline - now)?
+                }
+                None => Some(self.receive()?),
+            };
+
+            match msg {
+                Some(_) if self.late_answers > 0 => {
+                    debug!("Skipping a late answer");
+                    self.late_answers -= 1;
+                }
+                Some(msg) => return Ok(Some(msg)),
+                None => (),
+            }
+        }
+    }
+
+    /// Switch to the client's newest connection, if they've reconnected
+    /// without us noticing they were gone.
+    fn check_reconnected(&mut self) -> () {
+        let mut newest: Option<TcpStream> = None;
+        if let Some(ref session) = self.session {
+            while let Ok(stream) = session.reconnects.try_recv() {
+                newest = Some(stream);
+            }
+        }
+        if let Some(stream) = newest {
+            self.resume(stream);
+        }
+    }
+
+    /// Wait out the session's grace period for the client to reconnect.
+    /// Returns whether they did.
+    fn wait_for_reconnect(&mut self) -> bool {
+        let stream: Option<TcpStream> = match self.session {
+            Some(ref session) => session.reconnects.recv_timeout(session.grace).ok(),
+            None => None,
+        };
+        match stream {
+            Some(stream) => {
+                self.resume(stream);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Carry on over a new connection, catching the client up with the
+    /// board as we last saw it.
+    fn resume(&mut self, stream: TcpStream) -> () {
+        info!("Player reconnected");
+        let reader: BufReader<TcpStream> = match stream.try_clone() {
+            Ok(clone) => BufReader::new(clone),
+            Err(e) => {
+                warn!("Couldn't resume a session: {}", e);
+                return;
+            }
+        };
+        stream.set_nodelay(true).ok();
+        self.stream = stream;
+        self.reader = reader;
+        self.pending.clear();
+        self.late_answers = 0;
+
+        let resumed: String = serialize::xml_resumed(self.board.as_ref());
+        if self.send(&resumed).is_err() {
+            debug!("Player disconnected again while resuming");
+        }
+    }
+}
+
+impl Player for RemotePlayer {
+    fn start_game(&mut self, color: Color) -> Result<String, &'static str> {
+        let start: String = match self.session {
+            Some(ref session) => serialize::xml_start_game_with_session(&color, &session.token),
+            None => serialize::xml_start_game(&color),
+        };
+        let answer: String = self.ask(&start, None)?;
+        deserialize::deserialize_name(&answer)
+    }
+
+    fn do_move(&mut self, board: Board, dice: Dice) -> Result<Vec<Move>, &'static str> {
+        self.board = Some(board.clone());
+        let time_limit: Option<Duration> = self.time_limit;
+        let answer: String = self.ask(&serialize::xml_do_move(&board, &dice), time_limit)?;
+        deserialize::try_deserialize_moves(&answer)
+    }
+
+    fn doubles_penalty(&mut self) -> Result<(), &'static str> {
+        let answer: String = self.ask(&serialize::xml_doubles_penalty(), None)?;
+        deserialize::deserialize_void(&answer)
+    }
+
+    fn set_time_limit(&mut self, limit: Option<Duration>) -> () {
+        self.time_limit = limit;
+    }
+
+    /// The protocol doesn't show players each other's turns, but they
+    /// keep the board up to date for a client who reconnects, and give
+    /// one who reconnected between their own turns their catch-up.
+    fn observe_turn(&mut self, event: &TurnEvent) -> Result<(), &'static str> {
+        match *event {
+            TurnEvent::Played { ref board, .. } => self.board = Some(board.clone()),
+            TurnEvent::Ejected { color, .. } => {
+                if let Some(ref mut board) = self.board {
+                    board.positions.insert(color, [Loc::Nest; 4]);
+                }
+            }
+        }
+
+        if self.connected {
+            self.check_reconnected();
+        }
+        Ok(())
+    }
+}
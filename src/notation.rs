@@ -0,0 +1,231 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::board::{Board, Color, Loc, PawnLocs};
+use super::constants::*;
+use super::dice::Dice;
+
+/// A position written on one line: every pawn's location, the player to
+/// move and the unused dice, separated by spaces.
+///
+/// ```text
+/// r:10,20,h,h/b:22,n,n,n r 1,2
+/// ```
+///
+/// Each color present on the board gets a group of four locations,
+/// separated by `/`. A location is `n` for the nest, `h` for home, or
+/// the index of a spot, using the same numbers as `Loc::Spot`: 0-67 for
+/// the main ring and 100-106, 200-206 and so on for the home rows.
+/// Colors are written as their first letter. A turn with no dice left
+/// is written `-`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    pub board: Board,
+    pub to_move: Color,
+    pub dice: Dice,
+}
+
+impl Position {
+    /// Parse a position from its notation.
+    pub fn parse(s: &str) -> Result<Position, &'static str> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        if fields.len() != 3 {
+            return Err("Position needs pawns, a player to move and dice, \
+                        separated by spaces");
+        }
+
+        let board: Board = parse_board(fields[0])?;
+        let to_move: Color = parse_color(fields[1])?;
+        if !board.positions.contains_key(&to_move) {
+            return Err("Player to move has no pawns on the board");
+        }
+
+        Ok(Position {
+               board: board,
+               to_move: to_move,
+               dice: parse_dice(fields[2])?,
+           })
+    }
+
+    /// Write out the position in notation.
+    pub fn to_notation(&self) -> String {
+        format!("{} {} {}",
+                format_board(&self.board),
+                color_letter(&self.to_move),
+                format_dice(&self.dice))
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_notation())
+    }
+}
+
+/// Write the pawn locations of a board, one group per color.
+pub fn format_board(board: &Board) -> String {
+    board
+        .positions
+        .iter()
+        .map(|(clr, locs)| {
+                 let locs: Vec<String> = locs.iter().map(|&l| format_loc(l)).collect();
+                 format!("{}:{}", color_letter(clr), locs.join(","))
+             })
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+/// Parse the pawn locations of a board.
+pub fn parse_board(s: &str) -> Result<Board, &'static str> {
+    let mut positions: BTreeMap<Color, PawnLocs> = BTreeMap::new();
+
+    for group in s.split('/') {
+        let mut parts = group.splitn(2, ':');
+        let color: Color = parse_color(parts.next().unwrap_or(""))?;
+        let locs: Vec<&str> = match parts.next() {
+            Some(locs) => locs.split(',').collect(),
+            None => return Err("Expected ':' between a color and its pawns"),
+        };
+        if locs.len() != 4 {
+            return Err("Each color needs exactly four pawn locations");
+        }
+
+        let mut pawn_locs: PawnLocs = [Loc::Nest; 4];
+        for (i, loc) in locs.iter().enumerate() {
+            pawn_locs[i] = parse_loc(loc, &color)?;
+        }
+
+        if positions.insert(color, pawn_locs).is_some() {
+            return Err("Color appears more than once");
+        }
+    }
+
+    Ok(Board { positions: positions })
+}
+
+/// Write the unused rolls of a turn.
+pub fn format_dice(dice: &Dice) -> String {
+    if dice.rolls.is_empty() {
+        return "-".to_string();
+    }
+
+    dice.rolls
+        .iter()
+        .map(|r| r.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Parse the unused rolls of a turn. Bonuses can be larger than a die,
+/// so any positive number is allowed.
+pub fn parse_dice(s: &str) -> Result<Dice, &'static str> {
+    if s == "-" {
+        return Ok(Dice::new());
+    }
+
+    let mut rolls: Vec<usize> = Vec::new();
+    for roll in s.split(',') {
+        match roll.parse::<usize>() {
+            Ok(r) if r > 0 => rolls.push(r),
+            _ => return Err("Dice must be positive numbers separated by ','"),
+        }
+    }
+    Ok(Dice { rolls: rolls })
+}
+
+fn color_letter(color: &Color) -> char {
+    match *color {
+        Color::Red => 'r',
+        Color::Green => 'g',
+        Color::Blue => 'b',
+        Color::Yellow => 'y',
+    }
+}
+
+fn parse_color(s: &str) -> Result<Color, &'static str> {
+    match s.to_lowercase().as_str() {
+        "r" => Ok(Color::Red),
+        "g" => Ok(Color::Green),
+        "b" => Ok(Color::Blue),
+        "y" => Ok(Color::Yellow),
+        _ => Err("Unknown color, expected one of r, g, b, y"),
+    }
+}
+
+fn format_loc(loc: Loc) -> String {
+    match loc {
+        Loc::Nest => "n".to_string(),
+        Loc::Home => "h".to_string(),
+        Loc::Spot { index } => index.to_string(),
+    }
+}
+
+fn parse_loc(s: &str, color: &Color) -> Result<Loc, &'static str> {
+    match s.to_lowercase().as_str() {
+        "n" => return Ok(Loc::Nest),
+        "h" => return Ok(Loc::Home),
+        _ => {}
+    }
+
+    let index: usize = match s.parse() {
+        Ok(index) => index,
+        Err(_) => return Err("Pawn location must be n, h or a spot number"),
+    };
+    let home_row: usize = Board::get_home_row(color);
+
+    if index < BOARD_SIZE ||
+       (index >= home_row && index < home_row + HOME_ROW_LENGTH) {
+        Ok(Loc::Spot { index: index })
+    } else {
+        Err("Spot is off the main ring and not in the pawn's home row")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Formatting a position and parsing it back gives the same position.
+    fn notation_round_trip() {
+        let position = Position {
+            board: Board::from(map!{
+                Color::Red => [Loc::Spot { index: 10 },
+                               Loc::Spot { index: 103 },
+                               Loc::Home,
+                               Loc::Nest],
+                Color::Blue => [Loc::Spot { index: 22 },
+                                Loc::Nest,
+                                Loc::Nest,
+                                Loc::Nest]
+            }),
+            to_move: Color::Blue,
+            dice: Dice { rolls: vec![3, 20] },
+        };
+
+        let notation: String = position.to_notation();
+        assert_eq!(notation,
+                   "r:10,103,h,n/g:n,n,n,n/b:22,n,n,n/y:n,n,n,n b 3,20");
+        assert_eq!(Position::parse(&notation), Ok(position));
+
+        let start = Position::parse("r:n,n,n,n/g:N,N,N,N R -").unwrap();
+        assert_eq!(start.board, Board::with_colors(&[Color::Red, Color::Green]));
+        assert!(start.dice.rolls.is_empty());
+    }
+
+    #[test]
+    /// Malformed notation is rejected with the reason why.
+    fn notation_errors() {
+        assert!(Position::parse("r:n,n,n,n r").is_err());
+        assert_eq!(parse_board("x:n,n,n,n").err(),
+                   Some("Unknown color, expected one of r, g, b, y"));
+        assert_eq!(parse_board("r:n,n,n").err(),
+                   Some("Each color needs exactly four pawn locations"));
+        // Red's pawns can't be in Blue's home row.
+        assert!(parse_board("r:200,n,n,n").is_err());
+        assert!(parse_board("r:n,n,n,n/r:n,n,n,n").is_err());
+        assert_eq!(Position::parse("r:n,n,n,n b 1,2").err(),
+                   Some("Player to move has no pawns on the board"));
+        assert!(parse_dice("1,0").is_err());
+    }
+}
@@ -0,0 +1,59 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Number of threads to use when none is given: one per available core.
+pub fn default_threads() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// Run `jobs` independent jobs on a pool of `threads` threads, returning
+/// their results in job order.
+///
+/// Threads take the next unstarted job as soon as they finish one, so
+/// slow jobs don't hold up the rest of the pool. Each job only sees its
+/// own index, so the results are the same however many threads run them.
+pub fn run<T, F>(jobs: usize, threads: usize, job: F) -> Vec<T>
+    where T: Send,
+          F: Fn(usize) -> T + Sync
+{
+    let threads: usize = threads.max(1).min(jobs.max(1));
+    let next: AtomicUsize = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<T>>> = Mutex::new((0..jobs).map(|_| None).collect());
+
+    thread::scope(|scope| for _ in 0..threads {
+                      scope.spawn(|| loop {
+                                      let i: usize = next.fetch_add(1, Ordering::SeqCst);
+                                      if i >= jobs {
+                                          break;
+                                      }
+                                      let result: T = job(i);
+                                      results.lock().expect("A job panicked")[i] =
+                                          Some(result);
+                                  });
+                  });
+
+    results
+        .into_inner()
+        .expect("A job panicked")
+        .into_iter()
+        .map(|r| r.expect("Every job runs once"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Results come back in job order, whatever the number of threads.
+    fn run_keeps_job_order() {
+        let squares: Vec<usize> = (0..50).map(|i| i * i).collect();
+        for threads in 1..6 {
+            assert_eq!(run(50, threads, |i| i * i), squares);
+        }
+        assert!(run(0, 4, |i| i).is_empty());
+    }
+}
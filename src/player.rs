@@ -1,12 +1,99 @@
-use super::board::Board;
-use super::game::Move;
+use std::time::Duration;
+use super::board::{Board, Color};
+use super::game::{GameOutcome, Move, RecordedRoll};
 use super::dice::Dice;
 
+#[derive(Debug, Clone, PartialEq)]
+/// Something that happened in a game, shown to the players.
+pub enum TurnEvent {
+    /// Another player finished their turn. `rolls` holds every roll of
+    /// the turn, more than one after doubles, and `board` is the board
+    /// the turn ended on.
+    Played {
+        color: Color,
+        rolls: Vec<RecordedRoll>,
+        doubles_penalty: bool,
+        board: Board,
+    },
+    /// A player broke the rules and was ejected. The ejected player
+    /// is told too, before they're removed from the game.
+    Ejected {
+        color: Color,
+        reason: String,
+    },
+}
+
 /// Generic Player trait provides an interface for the
 /// server to interact with players.
+///
+/// Every call can fail, e.g. when a network player disconnects. A
+/// player who fails to start or move is ejected with the error as
+/// their violation. Errors from `observe_turn` and `game_over` are
+/// logged, and don't change the game.
 pub trait Player {
     /// Inform the Player that a game has started, and
-    /// what color the player is.
+    /// what color the player is. Returns the player's name.
+    fn start_game(&mut self, color: Color) -> Result<String, &'static str>;
+
+    /// Ask the player what move they want to make.
+    fn do_move(&mut self, board: Board, dice: Dice) -> Result<Vec<Move>, &'static str>;
+
+    /// Inform the player that they have suffered a doubles
+    /// penalty.
+    fn doubles_penalty(&mut self) -> Result<(), &'static str> {
+        debug!("Penalty on me");
+        Ok(())
+    }
+
+    /// Tell the player how long they have to answer the next
+    /// `do_move`, when the game is timed. An answer that comes too late
+    /// is ignored, so a player who waits on something else, like a
+    /// network player, can stop waiting once their time is up.
+    fn set_time_limit(&mut self, _limit: Option<Duration>) -> () {}
+
+    /// Show the player something that happened on another player's turn.
+    fn observe_turn(&mut self, _event: &TurnEvent) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    /// Inform the player that the game is over, and how it ended.
+    fn game_over(&mut self, _outcome: &GameOutcome) -> Result<(), &'static str> {
+        Ok(())
+    }
+}
+
+impl<'a, P: Player + ?Sized> Player for &'a mut P {
+    fn start_game(&mut self, color: Color) -> Result<String, &'static str> {
+        (**self).start_game(color)
+    }
+
+    fn do_move(&mut self, board: Board, dice: Dice) -> Result<Vec<Move>, &'static str> {
+        (**self).do_move(board, dice)
+    }
+
+    fn doubles_penalty(&mut self) -> Result<(), &'static str> {
+        (**self).doubles_penalty()
+    }
+
+    fn set_time_limit(&mut self, limit: Option<Duration>) -> () {
+        (**self).set_time_limit(limit)
+    }
+
+    fn observe_turn(&mut self, event: &TurnEvent) -> Result<(), &'static str> {
+        (**self).observe_turn(event)
+    }
+
+    fn game_over(&mut self, outcome: &GameOutcome) -> Result<(), &'static str> {
+        (**self).game_over(outcome)
+    }
+}
+
+/// The original player interface, where players can't fail, can't
+/// change themselves, and only hear about their own turns.
+/// Wrap a `LegacyPlayer` in a `LegacyAdapter` to seat it in a game.
+pub trait LegacyPlayer {
+    /// Inform the Player that a game has started. Returns the
+    /// player's name.
     fn start_game(&self) -> String;
 
     /// Ask the player what move they want to make.
@@ -15,6 +102,81 @@ pub trait Player {
     /// Inform the player that they have suffered a doubles
     /// penalty.
     fn doubles_penalty(&self) -> () {
-        println!("Penalty on me");
+        debug!("Penalty on me");
+    }
+}
+
+/// Lets a `LegacyPlayer` play as a `Player`.
+///
+/// Legacy players are told their color when they're created, so the
+/// color passed to `start_game` is ignored, as are the other players'
+/// turns and the end of the game.
+pub struct LegacyAdapter<P> {
+    player: P,
+}
+
+impl<P: LegacyPlayer> LegacyAdapter<P> {
+    pub fn new(player: P) -> LegacyAdapter<P> {
+        LegacyAdapter { player: player }
+    }
+
+    /// Unwrap the adapted player.
+    pub fn into_inner(self) -> P {
+        self.player
+    }
+}
+
+impl<P: LegacyPlayer> Player for LegacyAdapter<P> {
+    fn start_game(&mut self, _color: Color) -> Result<String, &'static str> {
+        Ok(self.player.start_game())
+    }
+
+    fn do_move(&mut self, board: Board, dice: Dice) -> Result<Vec<Move>, &'static str> {
+        Ok(self.player.do_move(board, dice))
+    }
+
+    fn doubles_penalty(&mut self) -> Result<(), &'static str> {
+        self.player.doubles_penalty();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use game::Game;
+
+    /// Always passes, and counts how often it was asked to move.
+    struct Passer {
+        asked: Cell<usize>,
+    }
+
+    impl LegacyPlayer for Passer {
+        fn start_game(&self) -> String {
+            "Passer".to_string()
+        }
+
+        fn do_move(&self, _board: Board, _dice: Dice) -> Vec<Move> {
+            self.asked.set(self.asked.get() + 1);
+            Vec::new()
+        }
+    }
+
+    #[test]
+    /// A legacy player plays through the adapter, and can be unwrapped
+    /// after the game.
+    fn legacy_adapter_plays() {
+        let mut adapter = LegacyAdapter::new(Passer { asked: Cell::new(0) });
+        {
+            let mut game: Game = Game::with_colors(&[Color::Red]);
+            game.seat_player(Box::new(&mut adapter), Color::Red).unwrap();
+
+            // A 1 and a 2 can't enter a pawn, so passing is legal.
+            let outcome = game.play(|_| (Dice { rolls: vec![1, 2] }, false), 3);
+            assert_eq!(outcome.names[&Color::Red], "Passer");
+            assert_eq!(outcome.stats[&Color::Red].violation, None);
+        }
+        assert_eq!(adapter.into_inner().asked.get(), 3);
     }
 }
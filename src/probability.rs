@@ -0,0 +1,328 @@
+use super::board::{Board, Color, Loc};
+use super::dice::Dice;
+use super::gametree;
+use super::rules::RuleSet;
+use super::zobrist::TranspositionTable;
+
+/// Every roll of two dice, with its chance out of 36. The two orders
+/// of a non-double roll allow the same moves, so they're counted once
+/// with twice the weight.
+pub fn rolls() -> Vec<(usize, usize, f64)> {
+    let mut rolls: Vec<(usize, usize, f64)> = Vec::new();
+    for d1 in 1..7 {
+        for d2 in d1..7 {
+            let weight: f64 = if d1 == d2 { 1.0 } else { 2.0 };
+            rolls.push((d1, d2, weight / 36.0));
+        }
+    }
+    rolls
+}
+
+/// The dice a player gets for a roll, expanding doubles into the tops
+/// and bottoms of the dice the same way `Dice::roll` does.
+pub fn dice_for_roll(board: &Board,
+                     color: &Color,
+                     d1: usize,
+                     d2: usize,
+                     rules: &RuleSet)
+                     -> Dice {
+    if d1 == d2 && rules.doubles_bonus && board.all_pawns_entered(color) {
+        Dice { rolls: vec![d1, d1, 7 - d1, 7 - d1] }
+    } else {
+        Dice { rolls: vec![d1, d2] }
+    }
+}
+
+/// The chance that an opponent can bop each of a player's pawns on the
+/// opponent's next roll, indexed by pawn id.
+///
+/// A pawn counts as boppable with a roll if any complete turn the
+/// opponent can take with it sends the pawn back to the nest, including
+/// turns that use a bonus earned earlier in the turn. The extra roll
+/// after doubles isn't counted.
+pub fn chance_bopped_by(board: &Board,
+                        color: &Color,
+                        opponent: &Color,
+                        rules: &RuleSet)
+                        -> [f64; 4] {
+    let mut chances: [f64; 4] = [0.0; 4];
+    let before = board.get_pawns_by_color(color);
+
+    // Pawns in the nest or home can't be bopped.
+    if before.iter().all(|&l| l == Loc::Nest || l == Loc::Home) {
+        return chances;
+    }
+
+    let mut table: TranspositionTable<()> = TranspositionTable::new(TABLE_SIZE);
+    for (d1, d2, weight) in rolls() {
+        let dice: Dice = dice_for_roll(board, opponent, d1, d2, rules);
+        table.clear();
+
+        let mut boppable: [bool; 4] = [false; 4];
+        for (_, after) in gametree::turn_outcomes(board, &dice, *opponent, rules, &mut table) {
+            let after = after.get_pawns_by_color(color);
+            for id in 0..4 {
+                if before[id] != Loc::Nest && after[id] == Loc::Nest {
+                    boppable[id] = true;
+                }
+            }
+        }
+
+        for id in 0..4 {
+            if boppable[id] {
+                chances[id] += weight;
+            }
+        }
+    }
+    chances
+}
+
+/// The chance that each of a player's pawns is bopped before the
+/// player's next turn, under the standard rules.
+pub fn chance_bopped(board: &Board, color: &Color) -> [f64; 4] {
+    chance_bopped_with(board, color, &RuleSet::standard())
+}
+
+/// The chance that each of a player's pawns is bopped before the
+/// player's next turn, under the given rules.
+///
+/// Each opponent still playing, other than a partner, gets one roll
+/// against the current board. Their rolls are independent, so a pawn
+/// survives only if it survives every opponent. Moves opponents make
+/// before their turn comes aren't taken into account.
+pub fn chance_bopped_with(board: &Board, color: &Color, rules: &RuleSet) -> [f64; 4] {
+    let mut survives: [f64; 4] = [1.0; 4];
+
+    for opponent in board.colors() {
+        if opponent == *color ||
+           (rules.partners && opponent == Board::get_partner(color)) ||
+           board.has_finished(&opponent) {
+            continue;
+        }
+
+        let chances: [f64; 4] = chance_bopped_by(board, color, &opponent, rules);
+        for id in 0..4 {
+            survives[id] *= 1.0 - chances[id];
+        }
+    }
+
+    let mut chances: [f64; 4] = [0.0; 4];
+    for id in 0..4 {
+        chances[id] = 1.0 - survives[id];
+    }
+    chances
+}
+
+/// Number of slots in the table used to skip repeated positions
+/// while listing an opponent's turns.
+static TABLE_SIZE: usize = 1 << 12;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use board::{Pawn, MoveResult};
+    use game::{Move, MoveType};
+    use rand::{SeedableRng, StdRng};
+
+    /// Boards covering a plain roll, doubles with tops and bottoms,
+    /// and a bop bonus carrying Blue onto a second pawn.
+    fn boards() -> Vec<Board> {
+        vec![Board::from(map!{
+                 Color::Red => [Loc::Spot { index: 40 }, Loc::Spot { index: 20 },
+                                Loc::Spot { index: 28 }, Loc::Nest],
+                 Color::Blue => [Loc::Spot { index: 30 }, Loc::Nest, Loc::Nest, Loc::Nest]
+             }),
+             Board::from(map!{
+                 Color::Red => [Loc::Spot { index: 40 }, Loc::Spot { index: 60 },
+                                Loc::Home, Loc::Home],
+                 Color::Blue => [Loc::Spot { index: 30 }, Loc::Spot { index: 12 },
+                                 Loc::Spot { index: 13 }, Loc::Spot { index: 14 }]
+             })]
+    }
+
+    #[test]
+    /// Only pawns on the main ring, off the safeties and ahead of an
+    /// opponent can be bopped.
+    fn chance_bopped_counts_every_roll() {
+        let board: Board = boards().remove(0);
+        let chances: [f64; 4] = chance_bopped_by(&board,
+                                                 &Color::Red,
+                                                 &Color::Blue,
+                                                 &RuleSet::standard());
+
+        // 4+6, 6+4 or 5+5. Blue has pawns in the nest, so doubles
+        // don't get tops and bottoms.
+        assert!((chances[0] - 3.0 / 36.0).abs() < 1e-9);
+        // Behind Blue, on a safety, and in the nest.
+        assert_eq!(chances[1], 0.0);
+        assert_eq!(chances[2], 0.0);
+        assert_eq!(chances[3], 0.0);
+
+        // With Blue as the only opponent, nobody else adds to the chance.
+        let mut two_player: Board = board.clone();
+        two_player.positions.remove(&Color::Green);
+        two_player.positions.remove(&Color::Yellow);
+        let combined: [f64; 4] = chance_bopped(&two_player, &Color::Red);
+        for id in 0..4 {
+            assert!((combined[id] - chances[id]).abs() < 1e-9);
+        }
+
+        // Yellow can enter on 38 and move 2 onto Red's pawn on 40.
+        assert!(chance_bopped(&board, &Color::Red)[0] > chances[0]);
+    }
+
+    /// Mark each of Red's pawns that Blue can bop with the dice, by
+    /// trying every sequence of mini-moves the board allows rather than
+    /// asking `gametree`. Only sequences that end in a legal turn, with
+    /// no moves left and no blockade moved together, count.
+    fn search_bops(start: &Board, board: &Board, dice: &Dice, rules: &RuleSet, bopped: &mut [bool; 4]) {
+        let mut distances: Vec<usize> = dice.rolls.clone();
+        distances.sort();
+        distances.dedup();
+
+        let mut moves: Vec<Move> = Vec::new();
+        for id in 0..4 {
+            let pawn: Pawn = Pawn::new(id, Color::Blue);
+            match board.get_pawn_loc(&Color::Blue, id) {
+                Loc::Nest => {
+                    moves.push(Move {
+                                   pawn: pawn,
+                                   m_type: MoveType::EnterPiece,
+                               })
+                }
+                Loc::Home => (),
+                Loc::Spot { index } => {
+                    let in_home_row: bool = Board::is_home_row(Color::Blue, Loc::Spot { index: index });
+                    for &distance in distances.iter() {
+                        let m_type: MoveType = if in_home_row {
+                            MoveType::MoveHome { start: index, distance: distance }
+                        } else {
+                            MoveType::MoveMain { start: index, distance: distance }
+                        };
+                        moves.push(Move {
+                                       pawn: pawn,
+                                       m_type: m_type,
+                                   });
+                    }
+                }
+            }
+        }
+
+        let mut can_move: bool = false;
+        for m in moves {
+            if Board::explain_move_with(board, dice, &m, rules).is_err() {
+                continue;
+            }
+            let MoveResult(next, bonus) = match board.handle_move_with(m, rules) {
+                Ok(result) => result,
+                Err(_) => continue,
+            };
+            if !board.is_valid_turn(&next, dice, Color::Blue) {
+                continue;
+            }
+
+            can_move = true;
+            let mut next_dice: Dice = dice.consume_move_with(&m, rules);
+            if let Some(amt) = bonus {
+                next_dice = next_dice.give_bonus(amt);
+            }
+            search_bops(start, &next, &next_dice, rules, bopped);
+        }
+
+        if !can_move && start.is_valid_turn(board, dice, Color::Blue) {
+            for id in 0..4 {
+                if start.get_pawn_loc(&Color::Red, id) != Loc::Nest &&
+                   board.get_pawn_loc(&Color::Red, id) == Loc::Nest {
+                    bopped[id] = true;
+                }
+            }
+        }
+    }
+
+    #[test]
+    /// The exact chances match how often a simulated opponent can bop,
+    /// rolling with the same dice as a real game. The simulation finds
+    /// Blue's turns with its own search, so it doesn't share any
+    /// mistakes `gametree` might make.
+    fn chance_bopped_matches_simulation() {
+        let rules: RuleSet = RuleSet::standard();
+        let mut rng: StdRng = SeedableRng::from_seed(&[7][..]);
+        let samples: usize = 2000;
+
+        for board in boards() {
+            let exact: [f64; 4] = chance_bopped_by(&board, &Color::Red, &Color::Blue, &rules);
+
+            // The search is slow, so only do it once for each roll.
+            let mut searched: Vec<(Vec<usize>, [bool; 4])> = Vec::new();
+            let mut hits: [usize; 4] = [0; 4];
+            for _ in 0..samples {
+                let (dice, _) = Dice::roll_with(&mut rng,
+                                                board.all_pawns_entered(&Color::Blue));
+                let mut rolls: Vec<usize> = dice.rolls.clone();
+                rolls.sort();
+                let known: Option<[bool; 4]> = searched
+                    .iter()
+                    .find(|&&(ref r, _)| *r == rolls)
+                    .map(|&(_, bopped)| bopped);
+                let bopped: [bool; 4] = match known {
+                    Some(bopped) => bopped,
+                    None => {
+                        let mut bopped: [bool; 4] = [false; 4];
+                        search_bops(&board, &board, &dice, &rules, &mut bopped);
+                        searched.push((rolls, bopped));
+                        bopped
+                    }
+                };
+                for id in 0..4 {
+                    if bopped[id] {
+                        hits[id] += 1;
+                    }
+                }
+            }
+
+            for id in 0..4 {
+                let simulated: f64 = hits[id] as f64 / samples as f64;
+                assert!((simulated - exact[id]).abs() < 0.035,
+                        "pawn {}: exact {} simulated {}",
+                        id,
+                        exact[id],
+                        simulated);
+            }
+        }
+    }
+
+    #[test]
+    /// Bopping one pawn earns Blue 20, which can carry another pawn
+    /// onto a second Red pawn further along.
+    fn chance_bopped_follows_bonus() {
+        let board: Board = boards().remove(1);
+        let chances: [f64; 4] = chance_bopped_by(&board,
+                                                 &Color::Red,
+                                                 &Color::Blue,
+                                                 &RuleSet::standard());
+        assert!(chances[1] > 0.0);
+
+        // 60 is 30 past Blue's nearest pawn, out of reach of the dice alone.
+        let mut bonus_free: Board = board.clone();
+        bonus_free.positions.insert(Color::Red,
+                                    [Loc::Home, Loc::Spot { index: 60 }, Loc::Home, Loc::Home]);
+        let without_bonus: [f64; 4] = chance_bopped_by(&bonus_free,
+                                                       &Color::Red,
+                                                       &Color::Blue,
+                                                       &RuleSet::standard());
+        assert_eq!(without_bonus[1], 0.0);
+
+        // Check the chain really goes through a bop of pawn 0.
+        let MoveResult(after_bop, bonus) = board
+            .handle_move(Move {
+                             pawn: Pawn::new(0, Color::Blue),
+                             m_type: MoveType::MoveMain {
+                                 start: 30,
+                                 distance: 10,
+                             },
+                         })
+            .unwrap();
+        assert_eq!(after_bop.get_pawn_loc(&Color::Red, 0), Loc::Nest);
+        assert!(bonus.is_some());
+    }
+}
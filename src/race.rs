@@ -0,0 +1,387 @@
+use std::collections::BTreeMap;
+
+use super::analysis::progress;
+use super::board::{Board, Color, Loc, Path, PawnLocs};
+use super::dice::Dice;
+use super::game::Move;
+use super::gametree;
+use super::probability;
+use super::rules::RuleSet;
+use super::zobrist::TranspositionTable;
+
+/// How many turns ahead the chance of finishing is tracked. Races
+/// that last longer than this are counted as never finishing, which
+/// only matters for pawns that still have most of the board to cover.
+pub static HORIZON: usize = 100;
+
+/// Checks whether a board has become a pure dice race: no pawn is in a
+/// nest, and no pawn still has an opponent's pawn anywhere on the rest
+/// of its path, so nobody can bop or blockade anybody else again.
+pub fn is_race(board: &Board) -> bool {
+    for (clr, locs) in board.positions.iter() {
+        for &loc in locs.iter() {
+            match loc {
+                Loc::Nest => return false,
+                Loc::Home => continue,
+                Loc::Spot { .. } if Board::is_home_row(*clr, loc) => continue,
+                Loc::Spot { .. } => {}
+            }
+
+            let mut ahead = ::std::iter::once(loc).chain(Path::started(*clr, loc))
+                .take_while(|&l| !Board::is_home_row(*clr, l) && l != Loc::Home);
+            let is_contested: bool = ahead.any(|l| {
+                board
+                    .positions
+                    .iter()
+                    .any(|(other, other_locs)| other != clr && other_locs.contains(&l))
+            });
+            if is_contested {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// What the solver knows about one player's pawns.
+struct Solved {
+    /// Expected turns still needed after the one being played, when
+    /// about to roll.
+    extra: f64,
+    /// `within[t]` is the chance of finishing within `t` turns, counting
+    /// the one being played, when about to roll.
+    within: Vec<f64>,
+}
+
+/// Plays out the end of a race perfectly.
+///
+/// Once a board is a race, every player's pawns move independently, so
+/// each player is solved on their own: the best turn for every roll is
+/// the one that leaves the fewest expected turns to get every pawn
+/// home. Moves come from `gametree::turn_outcomes`, so the home row
+/// length, the ban on overshooting home and the home bonus are all
+/// played the same way a real game plays them. Doubles earn another
+/// roll in the same turn, with tops and bottoms when the rules give them.
+///
+/// The doubles penalty only sends back pawns on the main ring, so it
+/// can't touch pawns in their home rows. Races with pawns still on the
+/// main ring are solved as if the penalty never happens.
+pub struct RaceSolver {
+    rules: RuleSet,
+    solved: BTreeMap<(Color, [usize; 4]), Solved>,
+}
+
+impl RaceSolver {
+    /// A solver using the standard rules.
+    pub fn new() -> RaceSolver {
+        RaceSolver {
+            rules: RuleSet::standard(),
+            solved: BTreeMap::new(),
+        }
+    }
+
+    /// Solve under the given rules instead of the standard ones.
+    pub fn with_rules(mut self, rules: RuleSet) -> RaceSolver {
+        self.rules = rules;
+        self.solved.clear();
+        self
+    }
+
+    /// Expected number of turns a player needs to get every pawn home,
+    /// playing perfectly from the start of their turn.
+    pub fn expected_turns(&mut self, board: &Board, color: &Color) -> f64 {
+        let locs: PawnLocs = board.get_pawns_by_color(color);
+        if locs.iter().all(|&l| l == Loc::Home) {
+            return 0.0;
+        }
+        1.0 + self.solve(color, locs).extra
+    }
+
+    /// The chance that a player gets every pawn home within the given
+    /// number of turns, counting the one about to start.
+    pub fn finish_within(&mut self, board: &Board, color: &Color, turns: usize) -> f64 {
+        let locs: PawnLocs = board.get_pawns_by_color(color);
+        if locs.iter().all(|&l| l == Loc::Home) {
+            return 1.0;
+        }
+        self.solve(color, locs).within[turns.min(HORIZON)]
+    }
+
+    /// The complete turn that leaves the player the fewest expected turns
+    /// to finish, or `None` if the dice can't be used at all.
+    pub fn best_turn(&mut self,
+                     board: &Board,
+                     dice: &Dice,
+                     color: Color)
+                     -> Option<(Vec<Move>, Board)> {
+        let mut table: TranspositionTable<()> = TranspositionTable::new(TABLE_SIZE);
+        let outcomes = gametree::turn_outcomes(board, dice, color, &self.rules, &mut table);
+
+        let mut best: Option<(f64, (Vec<Move>, Board))> = None;
+        for (moves, next_board) in outcomes {
+            if moves.is_empty() {
+                continue;
+            }
+            let remaining: f64 = self.expected_turns(&next_board, &color);
+            best = match best {
+                Some((best_remaining, _)) if best_remaining <= remaining => best,
+                _ => Some((remaining, (moves, next_board))),
+            };
+        }
+        best.map(|(_, turn)| turn)
+    }
+
+    /// Each racing player's chance of getting all their pawns home
+    /// first, with `to_move` about to roll and the others following in
+    /// board order.
+    ///
+    /// Every player plays to finish in the fewest expected turns, which
+    /// isn't always the same as playing for the best chance of winning.
+    pub fn win_chances(&mut self,
+                       board: &Board,
+                       to_move: &Color)
+                       -> Result<BTreeMap<Color, f64>, &'static str> {
+        if self.rules.partners {
+            return Err("Races between partners aren't solved");
+        }
+        if !is_race(board) {
+            return Err("Pawns can still meet, so this isn't a race");
+        }
+        if !board.positions.contains_key(to_move) {
+            return Err("Player to move has no pawns on the board");
+        }
+
+        let mut chances: BTreeMap<Color, f64> = BTreeMap::new();
+        if let Some(winner) = board.has_winner() {
+            for clr in board.colors() {
+                chances.insert(clr, if clr == winner { 1.0 } else { 0.0 });
+            }
+            return Ok(chances);
+        }
+
+        let colors: Vec<Color> = board.colors();
+        let start: usize = colors.iter().position(|c| c == to_move).unwrap_or(0);
+        let order: Vec<Color> = (0..colors.len())
+            .map(|i| colors[(start + i) % colors.len()])
+            .collect();
+        let within: Vec<Vec<f64>> = order
+            .iter()
+            .map(|clr| {
+                     (0..HORIZON + 1)
+                         .map(|t| self.finish_within(board, clr, t))
+                         .collect()
+                 })
+            .collect();
+
+        // A player wins on their t-th turn if everyone before them in
+        // the order needs more than t turns, and everyone after them
+        // needs at least t.
+        for (i, clr) in order.iter().enumerate() {
+            let mut chance: f64 = 0.0;
+            for t in 1..HORIZON + 1 {
+                let mut p: f64 = within[i][t] - within[i][t - 1];
+                for (j, others) in within.iter().enumerate().filter(|&(j, _)| j != i) {
+                    p *= if j < i {
+                        1.0 - others[t]
+                    } else {
+                        1.0 - others[t - 1]
+                    };
+                }
+                chance += p;
+            }
+            chances.insert(*clr, chance);
+        }
+        Ok(chances)
+    }
+
+    /// Solve a player's pawns, alone on the board, and every position
+    /// they can reach.
+    fn solve(&mut self, color: &Color, locs: PawnLocs) -> &Solved {
+        let key = (*color, race_key(color, &locs));
+        if !self.solved.contains_key(&key) {
+            let solved: Solved = self.compute(color, locs);
+            self.solved.insert(key, solved);
+        }
+        &self.solved[&key]
+    }
+
+    fn compute(&mut self, color: &Color, locs: PawnLocs) -> Solved {
+        let key: [usize; 4] = race_key(color, &locs);
+        let mut positions = BTreeMap::new();
+        positions.insert(*color, locs);
+        let board: Board = Board { positions: positions };
+        let mut table: TranspositionTable<()> = TranspositionTable::new(TABLE_SIZE);
+
+        // For each roll, whether it's doubles and where the best turn
+        // leaves the pawns, or `None` if the roll can't be used.
+        let mut choices: Vec<(f64, bool, Option<PawnLocs>)> = Vec::new();
+        for (d1, d2, weight) in probability::rolls() {
+            let dice: Dice = probability::dice_for_roll(&board, color, d1, d2, &self.rules);
+            table.clear();
+
+            let mut best: Option<(f64, PawnLocs)> = None;
+            for (_, next_board) in gametree::turn_outcomes(&board,
+                                                           &dice,
+                                                           *color,
+                                                           &self.rules,
+                                                           &mut table) {
+                let next: PawnLocs = next_board.get_pawns_by_color(color);
+                if race_key(color, &next) == key {
+                    continue;
+                }
+                let extra: f64 = if next.iter().all(|&l| l == Loc::Home) {
+                    -1.0
+                } else {
+                    self.solve(color, next).extra
+                };
+                best = match best {
+                    Some((best_extra, _)) if best_extra <= extra => best,
+                    _ => Some((extra, next)),
+                };
+            }
+            choices.push((weight, d1 == d2, best.map(|(_, next)| next)));
+        }
+
+        // Rolls that can't be used leave the pawns where they are: after
+        // doubles the player rolls again, otherwise the turn is over.
+        let stuck_doubles: f64 = choices
+            .iter()
+            .filter(|&&(_, doubles, ref next)| doubles && next.is_none())
+            .map(|&(w, _, _)| w)
+            .sum();
+        let stuck: f64 = choices
+            .iter()
+            .filter(|&&(_, doubles, ref next)| !doubles && next.is_none())
+            .map(|&(w, _, _)| w)
+            .sum();
+        let moving: f64 = 1.0 - stuck_doubles - stuck;
+
+        let mut extra: f64 = stuck;
+        for &(weight, doubles, ref next) in choices.iter() {
+            if let Some(next) = *next {
+                if next.iter().all(|&l| l == Loc::Home) {
+                    continue;
+                }
+                let next_extra: f64 = self.solve(color, next).extra;
+                extra += weight * if doubles { next_extra } else { 1.0 + next_extra };
+            }
+        }
+        let extra: f64 = if moving > 0.0 {
+            extra / moving
+        } else {
+            f64::INFINITY
+        };
+
+        let mut within: Vec<f64> = vec![0.0; HORIZON + 1];
+        for t in 1..HORIZON + 1 {
+            let mut chance: f64 = stuck * within[t - 1];
+            for &(weight, doubles, ref next) in choices.iter() {
+                if let Some(next) = *next {
+                    chance += weight *
+                              if next.iter().all(|&l| l == Loc::Home) {
+                                  1.0
+                              } else if doubles {
+                                  self.solve(color, next).within[t]
+                              } else {
+                                  self.solve(color, next).within[t - 1]
+                              };
+                }
+            }
+            within[t] = if stuck_doubles < 1.0 {
+                chance / (1.0 - stuck_doubles)
+            } else {
+                0.0
+            };
+        }
+
+        Solved {
+            extra: extra,
+            within: within,
+        }
+    }
+}
+
+/// Number of slots in the table used to skip repeated positions
+/// while listing a player's turns.
+static TABLE_SIZE: usize = 1 << 10;
+
+/// Identifies a player's pawns by how far along their path each one
+/// is, ignoring which pawn is which.
+fn race_key(color: &Color, locs: &PawnLocs) -> [usize; 4] {
+    let mut key: [usize; 4] = [0; 4];
+    for (i, &loc) in locs.iter().enumerate() {
+        key[i] = progress(color, loc);
+    }
+    key.sort();
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A board with just Red and Blue, each with one pawn left to finish.
+    fn race(red: Loc, blue: Loc) -> Board {
+        let mut board: Board = Board::with_colors(&[Color::Red, Color::Blue]);
+        board.positions.insert(Color::Red, [red, Loc::Home, Loc::Home, Loc::Home]);
+        board.positions.insert(Color::Blue, [blue, Loc::Home, Loc::Home, Loc::Home]);
+        board
+    }
+
+    #[test]
+    /// A race starts once no pawn has an opponent left to pass.
+    fn is_race_when_paths_are_clear() {
+        assert!(is_race(&race(Loc::Spot { index: 106 }, Loc::Spot { index: 206 })));
+        // The other colors still have pawns in their nests.
+        assert!(!is_race(&Board::new()));
+
+        // Blue's path from 30 runs past Red's pawn on 65 before its home row.
+        assert!(!is_race(&race(Loc::Spot { index: 65 }, Loc::Spot { index: 30 })));
+        assert!(is_race(&race(Loc::Spot { index: 65 }, Loc::Spot { index: 203 })));
+    }
+
+    #[test]
+    /// A pawn one step from home needs a 1, which comes from a die or
+    /// from the bottom of double 6s. Other doubles roll again.
+    fn expected_turns_for_last_step() {
+        let board: Board = race(Loc::Spot { index: 106 }, Loc::Spot { index: 206 });
+        let mut solver: RaceSolver = RaceSolver::new();
+
+        // 12 of 36 rolls finish, and 4 of the other 24 roll again, so
+        // each turn finishes with chance 3/8.
+        assert!((solver.expected_turns(&board, &Color::Red) - 8.0 / 3.0).abs() < 1e-9);
+        assert!((solver.finish_within(&board, &Color::Red, 1) - 3.0 / 8.0).abs() < 1e-9);
+        assert_eq!(solver.finish_within(&board, &Color::Red, 0), 0.0);
+
+        // Red moves first, so wins with chance 3/8 / (1 - (5/8)^2).
+        let chances = solver.win_chances(&board, &Color::Red).unwrap();
+        assert!((chances[&Color::Red] - 8.0 / 13.0).abs() < 1e-9);
+        assert!((chances[&Color::Red] + chances[&Color::Blue] - 1.0).abs() < 1e-9);
+
+        assert!(solver.win_chances(&Board::new(), &Color::Red).is_err());
+    }
+
+    #[test]
+    /// Finishing one pawn earns 10, exactly enough to bring the pawn on
+    /// 65 home, so the best turn takes both pawns home.
+    fn best_turn_uses_home_bonus() {
+        let board: Board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 106 }, Loc::Spot { index: 65 }, Loc::Home, Loc::Home]
+        });
+        let mut solver: RaceSolver = RaceSolver::new();
+
+        let (moves, after) = solver
+            .best_turn(&board, &Dice { rolls: vec![1, 2] }, Color::Red)
+            .unwrap();
+        assert_eq!(moves.len(), 2);
+        assert!(after.has_finished(&Color::Red));
+
+        // 103 is 4 from home, and a pawn can't overshoot it.
+        let board: Board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 103 }, Loc::Home, Loc::Home, Loc::Home]
+        });
+        assert!(solver
+                    .best_turn(&board, &Dice { rolls: vec![6, 6] }, Color::Red)
+                    .is_none());
+    }
+}
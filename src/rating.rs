@@ -0,0 +1,408 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+use super::board::Color;
+use super::game::GameOutcome;
+
+/// Rating given to a player the first time they appear.
+pub static INITIAL_RATING: f64 = 1500.0;
+
+/// Largest rating change a single game can cause.
+pub static K_FACTOR: f64 = 32.0;
+
+/// Default location of the ratings file.
+pub static DEFAULT_LEDGER_PATH: &'static str = "ratings.txt";
+
+#[derive(Debug, Clone, PartialEq)]
+/// A player's current rating.
+pub struct Rating {
+    pub rating: f64,
+    pub games: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Records how one game changed one player's rating.
+pub struct RatingChange {
+    pub game: usize,
+    pub name: String,
+    pub before: f64,
+    pub after: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Keeps track of player ratings across games, keyed by the name each
+/// player gives when a game starts.
+///
+/// Ratings use Elo generalized to more than two players: a game is
+/// treated as a round-robin of head-to-head matches between every pair
+/// of players, decided by who finished ahead of whom.
+pub struct Ledger {
+    pub ratings: BTreeMap<String, Rating>,
+    pub history: Vec<RatingChange>,
+    pub games: usize,
+}
+
+impl Ledger {
+    pub fn new() -> Ledger {
+        Ledger {
+            ratings: BTreeMap::new(),
+            history: Vec::new(),
+            games: 0,
+        }
+    }
+
+    /// Load a ledger from a file. A missing file is an empty ledger.
+    pub fn load(path: &Path) -> Result<Ledger, &'static str> {
+        let mut file: File = match File::open(path) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(Ledger::new())
+            }
+            Err(_) => return Err("Could not open ratings file"),
+        };
+
+        let mut contents: String = String::new();
+        if file.read_to_string(&mut contents).is_err() {
+            return Err("Could not read ratings file");
+        }
+
+        Ledger::parse(&contents)
+    }
+
+    /// Save the ledger to a file, replacing its contents.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut file: File = File::create(path)?;
+        file.write_all(self.serialize().as_bytes())
+    }
+
+    /// Parse a ledger from the format written by `serialize`.
+    pub fn parse(text: &str) -> Result<Ledger, &'static str> {
+        let mut ledger: Ledger = Ledger::new();
+
+        for line in text.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields[0] {
+                "" => continue,
+                "games" if fields.len() == 2 => {
+                    ledger.games = parse_field(fields[1])?;
+                }
+                "rating" if fields.len() == 4 => {
+                    ledger.ratings.insert(fields[1].to_string(),
+                                          Rating {
+                                              rating: parse_field(fields[2])?,
+                                              games: parse_field(fields[3])?,
+                                          });
+                }
+                "change" if fields.len() == 5 => {
+                    ledger.history.push(RatingChange {
+                                            game: parse_field(fields[1])?,
+                                            name: fields[2].to_string(),
+                                            before: parse_field(fields[3])?,
+                                            after: parse_field(fields[4])?,
+                                        });
+                }
+                _ => return Err("Malformed line in ratings file"),
+            }
+        }
+
+        Ok(ledger)
+    }
+
+    /// Write the ledger as tab-separated lines, one per rating or change.
+    pub fn serialize(&self) -> String {
+        let mut text: String = format!("games\t{}\n", self.games);
+
+        for (name, r) in self.ratings.iter() {
+            text = text +
+                   &format!("rating\t{}\t{}\t{}\n", name, r.rating, r.games);
+        }
+
+        for c in self.history.iter() {
+            text = text +
+                   &format!("change\t{}\t{}\t{}\t{}\n",
+                            c.game,
+                            c.name,
+                            c.before,
+                            c.after);
+        }
+
+        text
+    }
+
+    /// Current rating for a player, or the initial rating if the
+    /// player hasn't played yet.
+    pub fn rating(&self, name: &str) -> f64 {
+        match self.ratings.get(name) {
+            Some(r) => r.rating,
+            None => INITIAL_RATING,
+        }
+    }
+
+    /// Update ratings from the result of a game.
+    pub fn record(&mut self, outcome: &GameOutcome) -> () {
+        let placings: BTreeMap<Color, usize> = outcome.placings();
+        let seats: Vec<(String, usize)> = placings
+            .iter()
+            .filter_map(|(clr, &place)| {
+                            outcome
+                                .names
+                                .get(clr)
+                                .map(|name| (clean_name(name), place))
+                        })
+            .collect();
+
+        if seats.len() < 2 {
+            return;
+        }
+
+        // Work out every change from the ratings before the game, and
+        // only then apply them, so the order of the seats doesn't
+        // matter. A name with more than one seat gets all of its seats'
+        // changes at once.
+        let before: Vec<f64> = seats
+            .iter()
+            .map(|&(ref name, _)| self.rating(name))
+            .collect();
+        let opponents: f64 = (seats.len() - 1) as f64;
+
+        let mut changes: Vec<(String, f64, f64)> = Vec::new();
+        for (i, &(ref name, place)) in seats.iter().enumerate() {
+            let mut delta: f64 = 0.0;
+            for (j, &(_, other_place)) in seats.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+
+                let actual: f64 = if place < other_place {
+                    1.0
+                } else if place == other_place {
+                    0.5
+                } else {
+                    0.0
+                };
+
+                delta += actual - expected_score(before[i], before[j]);
+            }
+
+            let change: f64 = K_FACTOR * delta / opponents;
+            match changes.iter_mut().find(|c| c.0 == *name) {
+                Some(c) => c.2 += change,
+                None => changes.push((name.clone(), before[i], before[i] + change)),
+            }
+        }
+
+        self.games += 1;
+
+        for (name, before, after) in changes {
+            let entry = self.ratings
+                .entry(name.clone())
+                .or_insert(Rating {
+                               rating: INITIAL_RATING,
+                               games: 0,
+                           });
+            entry.rating = after;
+            entry.games += 1;
+
+            self.history.push(RatingChange {
+                                  game: self.games,
+                                  name: name,
+                                  before: before,
+                                  after: after,
+                              });
+        }
+    }
+
+    /// Players ordered from highest to lowest rating.
+    pub fn leaderboard(&self) -> Vec<(String, Rating)> {
+        let mut board: Vec<(String, Rating)> = self.ratings
+            .iter()
+            .map(|(name, r)| (name.clone(), r.clone()))
+            .collect();
+
+        board.sort_by(|&(_, ref a), &(_, ref b)| {
+                          b.rating
+                              .partial_cmp(&a.rating)
+                              .unwrap_or(::std::cmp::Ordering::Equal)
+                      });
+        board
+    }
+
+    /// Every rating change for the given player, oldest first.
+    pub fn history_for(&self, name: &str) -> Vec<RatingChange> {
+        self.history
+            .iter()
+            .filter(|c| c.name == name)
+            .cloned()
+            .collect()
+    }
+
+    /// Human-readable leaderboard.
+    pub fn leaderboard_table(&self) -> String {
+        let mut table: String = format!("{:>4} {:<20} {:>8} {:>6}\n",
+                                        "rank",
+                                        "name",
+                                        "rating",
+                                        "games");
+
+        for (i, (name, r)) in self.leaderboard()
+                .into_iter()
+                .enumerate() {
+            table = table +
+                    &format!("{:>4} {:<20} {:>8.1} {:>6}\n",
+                             i + 1,
+                             name,
+                             r.rating,
+                             r.games);
+        }
+
+        table
+    }
+
+    /// Human-readable history, optionally restricted to one player.
+    pub fn history_table(&self, name: Option<&str>) -> String {
+        let changes: Vec<RatingChange> = match name {
+            Some(name) => self.history_for(name),
+            None => self.history.clone(),
+        };
+
+        let mut table: String = format!("{:>6} {:<20} {:>8} {:>8} {:>7}\n",
+                                        "game",
+                                        "name",
+                                        "before",
+                                        "after",
+                                        "change");
+
+        for c in changes.iter() {
+            table = table +
+                    &format!("{:>6} {:<20} {:>8.1} {:>8.1} {:>+7.1}\n",
+                             c.game,
+                             c.name,
+                             c.before,
+                             c.after,
+                             c.after - c.before);
+        }
+
+        table
+    }
+}
+
+/// Probability that a player rated `rating` beats one rated `other`.
+fn expected_score(rating: f64, other: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((other - rating) / 400.0))
+}
+
+/// Names are stored in a tab-separated file, so they can't contain
+/// tabs or line breaks.
+fn clean_name(name: &str) -> String {
+    name.trim()
+        .replace(|c: char| c == '\t' || c == '\n' || c == '\r', " ")
+}
+
+fn parse_field<T: ::std::str::FromStr>(field: &str) -> Result<T, &'static str> {
+    field
+        .parse::<T>()
+        .map_err(|_| "Malformed number in ratings file")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game::PlayerStats;
+
+    fn outcome(winner: Option<Color>, names: Vec<(Color, &str)>) -> GameOutcome {
+        GameOutcome {
+            winner: winner,
+            winning_team: None,
+            finishing_order: winner.into_iter().collect(),
+            names: names
+                .iter()
+                .map(|&(clr, name)| (clr, name.to_string()))
+                .collect(),
+            turns: 10,
+            stats: names
+                .iter()
+                .map(|&(clr, _)| (clr, PlayerStats::default()))
+                .collect(),
+            record: Vec::new(),
+        }
+    }
+
+    #[test]
+    /// The winner gains exactly what the losers lose.
+    fn winner_takes_points() {
+        let mut ledger = Ledger::new();
+        ledger.record(&outcome(Some(Color::Red),
+                               vec![(Color::Red, "Lloyd"),
+                                    (Color::Blue, "Moses"),
+                                    (Color::Green, "Sven")]));
+
+        assert!(ledger.rating("Lloyd") > INITIAL_RATING);
+        assert!(ledger.rating("Moses") < INITIAL_RATING);
+        assert_eq!(ledger.rating("Moses"), ledger.rating("Sven"));
+
+        let total: f64 = ["Lloyd", "Moses", "Sven"]
+            .iter()
+            .map(|name| ledger.rating(name))
+            .sum();
+        assert!((total - 3.0 * INITIAL_RATING).abs() < 1e-9);
+
+        assert_eq!(ledger.leaderboard()[0].0, "Lloyd");
+        assert_eq!(ledger.history_for("Sven").len(), 1);
+    }
+
+    #[test]
+    /// A game without a winner between equals changes nothing.
+    fn draw_between_equals() {
+        let mut ledger = Ledger::new();
+        ledger.record(&outcome(None,
+                               vec![(Color::Red, "Lloyd"),
+                                    (Color::Blue, "Moses")]));
+
+        assert_eq!(ledger.rating("Lloyd"), INITIAL_RATING);
+        assert_eq!(ledger.rating("Moses"), INITIAL_RATING);
+        assert_eq!(ledger.games, 1);
+    }
+
+    #[test]
+    /// Serializing and parsing a ledger gives back the same ledger.
+    fn ledger_round_trip() {
+        let mut ledger = Ledger::new();
+        ledger.record(&outcome(Some(Color::Blue),
+                               vec![(Color::Red, "Lloyd"),
+                                    (Color::Blue, "Moses")]));
+        ledger.record(&outcome(Some(Color::Red),
+                               vec![(Color::Red, "Lloyd"),
+                                    (Color::Blue, "Moses")]));
+
+        assert_eq!(Ledger::parse(&ledger.serialize()), Ok(ledger));
+        assert!(Ledger::parse("rating\tLloyd\tlots\t1\n").is_err());
+    }
+
+    #[test]
+    /// A name with two seats in one game is rated once, from where it
+    /// stood before the game, whichever seats it had.
+    fn repeated_name_rated_once() {
+        let mut ledger = Ledger::new();
+        ledger.record(&outcome(Some(Color::Red),
+                               vec![(Color::Red, "Moses"),
+                                    (Color::Blue, "Lloyd")]));
+
+        let mut first = ledger.clone();
+        first.record(&outcome(Some(Color::Red),
+                              vec![(Color::Red, "Lloyd"),
+                                   (Color::Blue, "Lloyd"),
+                                   (Color::Yellow, "Moses")]));
+        let mut second = ledger.clone();
+        second.record(&outcome(Some(Color::Yellow),
+                               vec![(Color::Red, "Moses"),
+                                    (Color::Blue, "Lloyd"),
+                                    (Color::Yellow, "Lloyd")]));
+
+        assert_eq!(first.ratings, second.ratings);
+        assert_eq!(first.ratings["Lloyd"].games, 2);
+        assert_eq!(first.history_for("Lloyd").len(), 2);
+        assert_eq!(first.history_for("Lloyd")[1].before, ledger.rating("Lloyd"));
+    }
+}
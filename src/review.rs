@@ -0,0 +1,321 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::analysis::Analyzer;
+use super::board::{Board, Color, Loc, MoveResult, Path, Pawn};
+use super::game::{self, Move, RecordedRoll};
+
+/// How much evaluation a turn has to lose, compared to the best turn,
+/// to count as a blunder.
+pub static BLUNDER_LOSS: f64 = 10.0;
+
+/// How far ahead of an opponent's pawn a pawn can be bopped in one roll.
+static BOP_REACH: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// Something a turn did worse than the engine's choice.
+pub enum Mistake {
+    /// Left a pawn where an opponent could reach it, when the best
+    /// turn didn't.
+    LeftPawnExposed(Pawn),
+    /// Passed up a bop the best turn made.
+    MissedBop,
+    /// Broke up a blockade the best turn kept.
+    BrokeBlockade(Loc),
+}
+
+impl fmt::Display for Mistake {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Mistake::LeftPawnExposed(pawn) => {
+                write!(f, "left pawn {} where it can be bopped", pawn.id)
+            }
+            Mistake::MissedBop => write!(f, "missed a bop"),
+            Mistake::BrokeBlockade(loc) => {
+                write!(f, "broke the blockade at {:?}", loc)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The engine's view of one recorded roll.
+pub struct TurnReview {
+    /// Which roll of the game this is, counting from 0.
+    pub roll: usize,
+    pub color: Color,
+    pub played: Vec<Move>,
+    pub played_score: f64,
+    pub best: Vec<Move>,
+    pub best_score: f64,
+    pub mistakes: Vec<Mistake>,
+}
+
+impl TurnReview {
+    /// How much evaluation the played moves gave up.
+    pub fn loss(&self) -> f64 {
+        (self.best_score - self.played_score).max(0.0)
+    }
+
+    pub fn is_blunder(&self) -> bool {
+        self.loss() >= BLUNDER_LOSS
+    }
+}
+
+/// A recorded game, replayed and compared roll by roll with the
+/// engine's top choice.
+#[derive(Debug, Clone)]
+pub struct Review {
+    pub turns: Vec<TurnReview>,
+}
+
+impl Review {
+    /// Total evaluation a player gave up over the game.
+    pub fn total_loss(&self, color: &Color) -> f64 {
+        self.turns
+            .iter()
+            .filter(|t| t.color == *color)
+            .map(|t| t.loss())
+            .sum()
+    }
+
+    /// An annotated report listing every roll where a player did worse
+    /// than the engine, followed by each player's totals.
+    /// Blunders are marked with `??`, smaller losses with `?`.
+    pub fn report(&self) -> String {
+        let mut report: String = String::new();
+
+        for t in self.turns.iter().filter(|t| t.loss() > 0.0) {
+            report = report +
+                     &format!("{:>4}. {:<6} {:<2} lost {:>6.2}  played {}\n",
+                              t.roll + 1,
+                              t.color.to_string(),
+                              if t.is_blunder() { "??" } else { "?" },
+                              t.loss(),
+                              describe_moves(&t.played));
+            report = report +
+                     &format!("{:>18} best {}\n", "", describe_moves(&t.best));
+            for m in t.mistakes.iter() {
+                report = report + &format!("{:>18} {}\n", "", m);
+            }
+        }
+
+        let mut colors: BTreeMap<Color, (usize, usize)> = BTreeMap::new();
+        for t in self.turns.iter() {
+            let entry = colors.entry(t.color).or_insert((0, 0));
+            entry.0 += 1;
+            if t.is_blunder() {
+                entry.1 += 1;
+            }
+        }
+
+        report = report +
+                 &format!("\n{:<8} {:>6} {:>10} {:>9}\n",
+                          "color",
+                          "rolls",
+                          "total loss",
+                          "blunders");
+        for (clr, &(rolls, blunders)) in colors.iter() {
+            report = report +
+                     &format!("{:<8} {:>6} {:>10.2} {:>9}\n",
+                              clr.to_string(),
+                              rolls,
+                              self.total_loss(clr),
+                              blunders);
+        }
+        report
+    }
+}
+
+/// Replay a recorded game and compare each roll's moves with the
+/// analyzer's top choice.
+pub fn review(record: &[RecordedRoll], analyzer: &Analyzer) -> Review {
+    let mut turns: Vec<TurnReview> = Vec::new();
+
+    for (i, roll) in record.iter().enumerate() {
+        let analyses = analyzer.analyze(&roll.board, &roll.dice, roll.color);
+        let best = match analyses.first() {
+            Some(best) => best,
+            None => continue,
+        };
+
+        let played: Board = replay(&roll.board, &roll.moves, analyzer);
+        let played_score: f64 = analyzer.score_turn(&played, roll.color);
+        let mistakes: Vec<Mistake> = if played_score < best.score {
+            find_mistakes(&roll.board, &played, &best.board, &roll.color)
+        } else {
+            Vec::new()
+        };
+
+        turns.push(TurnReview {
+                       roll: i,
+                       color: roll.color,
+                       played: roll.moves.clone(),
+                       played_score: played_score,
+                       best: best.moves.clone(),
+                       best_score: best.score,
+                       mistakes: mistakes,
+                   });
+    }
+
+    Review { turns: turns }
+}
+
+/// Play a roll's moves on the board it started from.
+fn replay(board: &Board, moves: &[Move], analyzer: &Analyzer) -> Board {
+    let mut board: Board = board.clone();
+    for &m in moves.iter() {
+        match board.handle_move_with(m, analyzer.rules()) {
+            Ok(MoveResult(next_board, _)) => board = next_board,
+            Err(_) => break,
+        }
+    }
+    board
+}
+
+/// Explain how the played turn compares badly with the best one.
+fn find_mistakes(before: &Board, played: &Board, best: &Board, color: &Color) -> Vec<Mistake> {
+    let mut mistakes: Vec<Mistake> = Vec::new();
+
+    let best_exposed: Vec<Pawn> = exposed_pawns(best, color);
+    for pawn in exposed_pawns(played, color) {
+        if !best_exposed.contains(&pawn) {
+            mistakes.push(Mistake::LeftPawnExposed(pawn));
+        }
+    }
+
+    if game::bopped_pawns(before, played, color).len() <
+       game::bopped_pawns(before, best, color).len() {
+        mistakes.push(Mistake::MissedBop);
+    }
+
+    let kept: Vec<Loc> = own_blockades(played, color);
+    let best_kept: Vec<Loc> = own_blockades(best, color);
+    for loc in own_blockades(before, color) {
+        if !kept.contains(&loc) && best_kept.contains(&loc) {
+            mistakes.push(Mistake::BrokeBlockade(loc));
+        }
+    }
+
+    mistakes
+}
+
+/// The player's pawns that an opponent's pawn could land on with a
+/// single roll.
+pub fn exposed_pawns(board: &Board, color: &Color) -> Vec<Pawn> {
+    let mut exposed: Vec<Pawn> = Vec::new();
+
+    for (id, &loc) in board.get_pawns_by_color(color).iter().enumerate() {
+        let is_main_ring: bool = match loc {
+            Loc::Spot { .. } => !Board::is_home_row(*color, loc),
+            _ => false,
+        };
+        if !is_main_ring || Board::is_safety(loc) {
+            continue;
+        }
+
+        let is_reachable: bool = board
+            .positions
+            .iter()
+            .filter(|&(clr, _)| clr != color)
+            .any(|(clr, locs)| {
+                locs.iter().any(|&opp_loc| match opp_loc {
+                    Loc::Spot { .. } => {
+                        Path::started(*clr, opp_loc)
+                            .take(BOP_REACH)
+                            .any(|l| l == loc)
+                    }
+                    _ => false,
+                })
+            });
+
+        if is_reachable {
+            exposed.push(Pawn::new(id, *color));
+        }
+    }
+    exposed
+}
+
+/// Spots where the player has a blockade.
+fn own_blockades(board: &Board, color: &Color) -> Vec<Loc> {
+    let locs = board.get_pawns_by_color(color);
+    board
+        .get_blockades()
+        .into_iter()
+        .filter(|b| locs.iter().filter(|&&l| l == *b).count() == 2)
+        .collect()
+}
+
+fn describe_moves(moves: &[Move]) -> String {
+    if moves.is_empty() {
+        return "nothing".to_string();
+    }
+
+    moves
+        .iter()
+        .map(|m| format!("{}:{:?}", m.pawn.id, m.m_type))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use dice::Dice;
+    use game::MoveType;
+
+    #[test]
+    /// Moving past an opponent instead of bopping them is flagged.
+    fn review_flags_missed_bop() {
+        let board: Board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 20 },
+                           Loc::Spot { index: 40 },
+                           Loc::Home,
+                           Loc::Home],
+            Color::Blue => [Loc::Spot { index: 43 },
+                            Loc::Nest,
+                            Loc::Nest,
+                            Loc::Nest]
+        });
+        let record: Vec<RecordedRoll> = vec![RecordedRoll {
+                                                 color: Color::Red,
+                                                 board: board,
+                                                 dice: Dice { rolls: vec![3] },
+                                                 moves: vec![Move {
+                                                                 pawn: Pawn::new(0, Color::Red),
+                                                                 m_type: MoveType::MoveMain {
+                                                                     start: 20,
+                                                                     distance: 3,
+                                                                 },
+                                                             }],
+                                                 time_used: Duration::from_millis(10),
+                                                 timed_out: false,
+                                             }];
+
+        let review: Review = review(&record, &Analyzer::new());
+        assert_eq!(review.turns.len(), 1);
+        assert!(review.turns[0].is_blunder());
+        assert!(review.turns[0].mistakes.contains(&Mistake::MissedBop));
+        assert!(review.report().contains("missed a bop"));
+        assert_eq!(review.total_loss(&Color::Red), review.turns[0].loss());
+    }
+
+    #[test]
+    /// Only pawns an opponent could land on with one roll are exposed.
+    fn exposed_pawns_in_reach() {
+        let board: Board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 20 },
+                           Loc::Spot { index: 40 },
+                           Loc::Spot { index: 28 },
+                           Loc::Spot { index: 101 }],
+            Color::Blue => [Loc::Spot { index: 30 },
+                            Loc::Nest,
+                            Loc::Nest,
+                            Loc::Nest]
+        });
+        // 40 is 10 ahead of Blue's pawn; 20 is behind it and 28 is a safety.
+        assert_eq!(exposed_pawns(&board, &Color::Red),
+                   vec![Pawn::new(1, Color::Red)]);
+    }
+}
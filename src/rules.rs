@@ -0,0 +1,105 @@
+use super::constants::*;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// Represents the rules that differ between variants of the game.
+///
+/// The board, dice and game all default to the standard rules;
+/// the `_with` variants of their methods take a RuleSet instead.
+pub struct RuleSet {
+    /// Extra distance awarded for bopping an opponent's pawn.
+    /// A bonus of 0 means no bonus is awarded.
+    pub bop_bonus: usize,
+    /// Extra distance awarded for moving a pawn home.
+    /// A bonus of 0 means no bonus is awarded.
+    pub home_bonus: usize,
+    /// Whether two dice adding up to 5 can enter a pawn.
+    /// A single 5 can always enter.
+    pub enter_with_sum: bool,
+    /// Whether doubles award the tops and bottoms of the dice,
+    /// once all of a player's pawns have entered.
+    pub doubles_bonus: bool,
+    /// Whether rolling doubles three times in one turn sends the
+    /// player's furthest pawn back to the nest.
+    pub doubles_penalty: bool,
+    /// Whether a pawn can bop an opponent sitting on the pawn's own
+    /// entrance. Otherwise the entrance is safe like any other safety.
+    pub bop_on_entrance: bool,
+    /// Whether opposite colors play as partners: partners can't bop
+    /// each other, a player whose pawns are all home moves their
+    /// partner's pawns instead, and a team wins when all eight of its
+    /// pawns are home.
+    pub partners: bool,
+}
+
+impl RuleSet {
+    /// The standard Parcheesi rules.
+    pub fn standard() -> RuleSet {
+        RuleSet {
+            bop_bonus: BOP_BONUS,
+            home_bonus: HOME_BONUS,
+            enter_with_sum: true,
+            doubles_bonus: true,
+            doubles_penalty: true,
+            bop_on_entrance: true,
+            partners: false,
+        }
+    }
+
+    /// Spanish Parchís rules: pawns only enter on a 5, and doubles
+    /// earn another roll but no tops and bottoms.
+    pub fn parchis() -> RuleSet {
+        RuleSet {
+            enter_with_sum: false,
+            doubles_bonus: false,
+            ..RuleSet::standard()
+        }
+    }
+
+    /// Simplified rules for kids: no bonuses, no doubles penalty,
+    /// and every safety spot is safe.
+    pub fn simple() -> RuleSet {
+        RuleSet {
+            bop_bonus: 0,
+            home_bonus: 0,
+            enter_with_sum: true,
+            doubles_bonus: false,
+            doubles_penalty: false,
+            bop_on_entrance: false,
+            partners: false,
+        }
+    }
+
+    /// Look up a preset by name.
+    pub fn from_name(name: &str) -> Option<RuleSet> {
+        match name {
+            "standard" => Some(RuleSet::standard()),
+            "parchis" => Some(RuleSet::parchis()),
+            "simple" => Some(RuleSet::simple()),
+            _ => None,
+        }
+    }
+
+    /// The bonus for bopping, if these rules award one.
+    pub fn bop_bonus(&self) -> Option<usize> {
+        if self.bop_bonus > 0 {
+            Some(self.bop_bonus)
+        } else {
+            None
+        }
+    }
+
+    /// The bonus for moving a pawn home, if these rules award one.
+    pub fn home_bonus(&self) -> Option<usize> {
+        if self.home_bonus > 0 {
+            Some(self.home_bonus)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for RuleSet {
+    fn default() -> RuleSet {
+        RuleSet::standard()
+    }
+}
@@ -7,8 +7,9 @@ use super::player::Player;
 use super::autoplayers::XMLTestPlayer;
 use super::dice::Dice;
 use super::board::{Color, Board, Pawn, Loc, MoveResult};
-use super::game::{Move, MoveType};
+use super::game::{Move, MoveType, xmlify_distance};
 use super::constants::*;
+use super::networkgame::GameListing;
 use super::quick_xml::reader::Reader;
 use super::quick_xml::events::Event;
 use std::io::prelude::*;
@@ -27,6 +28,13 @@ pub fn xml_start_game(&color: &Color) -> String {
     xml_response
 }
 
+/// Start the game like `xml_start_game`, also giving the player the token
+/// to reconnect with. The token comes first, so clients that only read
+/// the color carry on working.
+pub fn xml_start_game_with_session(&color: &Color, token: &str) -> String {
+    "<start-game> <session> ".to_string() + &xml_escape(token) + " </session> " +
+    &Color::to_string(&color) + " </start-game>"
+}
 
 pub fn xml_start_game_response(player: &XMLTestPlayer) -> String {
     let xml_response: String =
@@ -41,6 +49,48 @@ pub fn xml_do_move(board: &Board, dice: &Dice) -> String {
     xml_response
 }
 
+/// Write a board in the numbering clients use, which `deserialize_board`
+/// reads back: main ring spots are counted from 18 spots after ours, and
+/// home row spots from the start of each home row. `Board::xmlify` writes
+/// our own numbering instead.
+pub fn xml_protocol_board(board: &Board) -> String {
+    let mut start: String = String::new();
+    let mut main: String = String::new();
+    let mut home_rows: String = String::new();
+    let mut home: String = String::new();
+
+    for (clr, locs) in board.positions.iter() {
+        for (id, &loc) in locs.iter().enumerate() {
+            let pawn: String = Pawn::new(id, *clr).xmlify();
+            match loc {
+                Loc::Nest => start = start + " " + &pawn,
+                Loc::Home => home = home + " " + &pawn,
+                Loc::Spot { index } if index >= RED_HOME_ROW => {
+                    home_rows = home_rows + " <piece-loc> " + &pawn + " <loc> " +
+                                &(index - Board::get_home_row(clr)).to_string() +
+                                " </loc> </piece-loc>";
+                }
+                Loc::Spot { index } => {
+                    main = main + " <piece-loc> " + &pawn + " <loc> " +
+                           &((index + 18) % BOARD_SIZE).to_string() +
+                           " </loc> </piece-loc>";
+                }
+            }
+        }
+    }
+
+    "<board> <start>".to_string() + &start + " </start> <main>" + &main +
+    " </main> <home-rows>" + &home_rows + " </home-rows> <home>" + &home +
+    " </home> </board>"
+}
+
+/// Ask a client to move like `xml_do_move`, with the board in the
+/// numbering clients use. See `xml_protocol_board`.
+pub fn xml_protocol_do_move(board: &Board, dice: &Dice) -> String {
+    "<do-move> ".to_string() + &xml_protocol_board(board) + " " + &dice.xmlify() +
+    " </do-move>"
+}
+
 pub fn xml_moves(move_vec: &Vec<Move>) -> String {
     let mut move_header: String = "<moves>".to_string();
     for moves in move_vec {
@@ -57,6 +107,164 @@ pub fn xml_void() -> String {
     "<void> </void>".to_string()
 }
 
+// The messages below are only sent to spectators. Each one is sent on
+// a line of its own, like the messages players receive.
+
+/// Every seated player and the board, sent when a game starts and to
+/// spectators who join partway through.
+pub fn xml_game_start(board: &Board, names: &BTreeMap<Color, String>) -> String {
+    let mut players: String = "<players>".to_string();
+    for (clr, name) in names.iter() {
+        players = players + " <player> " + &xml_color(clr) + " <name> " +
+                  &xml_escape(name) + " </name> </player>";
+    }
+    "<game-start> ".to_string() + &players + " </players> " + &board.xmlify() +
+    " </game-start>"
+}
+
+/// The dice a player rolled.
+pub fn xml_roll(color: &Color, dice: &Dice) -> String {
+    "<roll> ".to_string() + &xml_color(color) + " " + &dice.xmlify() + " </roll>"
+}
+
+/// A move a player made.
+pub fn xml_applied_move(m: &Move) -> String {
+    "<move> ".to_string() + &m.xmlify() + " </move>"
+}
+
+/// A pawn that was sent back to the nest.
+pub fn xml_bop(pawn: &Pawn) -> String {
+    "<bop> ".to_string() + &pawn.xmlify() + " </bop>"
+}
+
+/// A bonus a player earned, for bopping or for bringing a pawn home.
+pub fn xml_bonus(color: &Color, distance: usize) -> String {
+    "<bonus> ".to_string() + &xml_color(color) + &xmlify_distance(distance) +
+    " </bonus>"
+}
+
+/// A player rolled doubles three times and was penalized.
+pub fn xml_penalty(color: &Color) -> String {
+    "<doubles-penalty> ".to_string() + &xml_color(color) + " </doubles-penalty>"
+}
+
+/// The board at the end of a player's turn.
+pub fn xml_turn_end(color: &Color, board: &Board) -> String {
+    "<turn-end> ".to_string() + &xml_color(color) + " " + &board.xmlify() +
+    " </turn-end>"
+}
+
+/// A player who broke the rules and was removed from the game.
+pub fn xml_ejected(color: &Color, reason: &str) -> String {
+    "<ejected> ".to_string() + &xml_color(color) + " <reason> " +
+    &xml_escape(reason) + " </reason> </ejected>"
+}
+
+/// The end of the game, and who won, if anyone did.
+pub fn xml_game_over(winner: Option<Color>) -> String {
+    match winner {
+        Some(clr) => {
+            "<game-over> <winner> ".to_string() + &xml_color_name(&clr) +
+            " </winner> </game-over>"
+        }
+        None => "<game-over> </game-over>".to_string(),
+    }
+}
+
+// Lobby requests, sent by clients before their game starts, and the
+// lobby's answers.
+
+pub fn xml_list_games() -> String {
+    "<list-games> </list-games>".to_string()
+}
+
+pub fn xml_create_game(color: Option<Color>) -> String {
+    "<create-game> ".to_string() + &xml_requested_color(color) + "</create-game>"
+}
+
+pub fn xml_join_game(id: usize, color: Option<Color>) -> String {
+    "<join-game> <id> ".to_string() + &id.to_string() + " </id> " +
+    &xml_requested_color(color) + "</join-game>"
+}
+
+pub fn xml_begin_game() -> String {
+    "<begin-game> </begin-game>".to_string()
+}
+
+/// Watch the game with the given id, without playing in it.
+pub fn xml_watch_game(id: usize) -> String {
+    "<watch-game> <id> ".to_string() + &id.to_string() + " </id> </watch-game>"
+}
+
+/// Reconnect to a game in progress, with the token from its start-game.
+pub fn xml_resume(token: &str) -> String {
+    "<resume> <session> ".to_string() + &xml_escape(token) + " </session> </resume>"
+}
+
+/// Sent to a client who reconnected, with the board as it stands, if
+/// the game has one yet. Any message they hadn't answered is sent again
+/// after this.
+pub fn xml_resumed(board: Option<&Board>) -> String {
+    match board {
+        Some(board) => "<resumed> ".to_string() + &board.xmlify() + " </resumed>",
+        None => "<resumed> </resumed>".to_string(),
+    }
+}
+
+/// The games waiting for players, and the seats still open in each.
+pub fn xml_games(games: &[GameListing]) -> String {
+    let mut games_string: String = "<games>".to_string();
+    for game in games.iter() {
+        games_string = games_string + " <game> <id> " + &game.id.to_string() +
+                       " </id> <open-seats>";
+        for clr in game.open_seats.iter() {
+            games_string = games_string + " " + &xml_color(clr);
+        }
+        games_string = games_string + " </open-seats> </game>";
+    }
+    games_string + " </games>"
+}
+
+/// The game a client was seated in, and their color.
+pub fn xml_joined(id: usize, color: &Color) -> String {
+    "<joined> <id> ".to_string() + &id.to_string() + " </id> " + &xml_color(color) +
+    " </joined>"
+}
+
+/// The game a client is watching. The game itself follows, as a
+/// spectator sees it.
+pub fn xml_watching(id: usize) -> String {
+    "<watching> <id> ".to_string() + &id.to_string() + " </id> </watching>"
+}
+
+/// Why the lobby couldn't do what a client asked, or why a client
+/// couldn't answer the server.
+pub fn xml_error(reason: &str) -> String {
+    "<error> ".to_string() + &xml_escape(reason) + " </error>"
+}
+
+fn xml_requested_color(color: Option<Color>) -> String {
+    match color {
+        Some(clr) => xml_color(&clr) + " ",
+        None => "".to_string(),
+    }
+}
+
+fn xml_color(color: &Color) -> String {
+    "<color> ".to_string() + &xml_color_name(color) + " </color>"
+}
+
+fn xml_color_name(color: &Color) -> String {
+    color.to_string().to_lowercase()
+}
+
+/// Escape text so it can't be mistaken for XML tags.
+fn xml_escape(text: &str) -> String {
+    text.replace("&", "&amp;")
+        .replace("<", "&lt;")
+        .replace(">", "&gt;")
+}
+
 
 
 
@@ -223,4 +431,20 @@ mod test {
                 &m_2.xmlify() + " " + &m_3.xmlify() +
                 " </moves>");
     }
+
+    #[test]
+    /// Clients read boards sent in their numbering back as they were.
+    fn xml_protocol_do_move_round_trip() {
+        let board: Board = Board::from(map!{
+            Color::Red => [Loc::Home, Loc::Spot { index: 103 },
+                           Loc::Spot { index: 60 }, Loc::Nest],
+            Color::Blue => [Loc::Spot { index: 4 }, Loc::Spot { index: 66 },
+                            Loc::Spot { index: 206 }, Loc::Nest]
+        });
+        let dice: Dice = Dice { rolls: vec![3, 3, 4, 4] };
+        let (read_board, read_dice) =
+            deserialize::deserialize_do_move(xml_protocol_do_move(&board, &dice));
+        assert_eq!(read_board, board);
+        assert_eq!(read_dice, dice);
+    }
 }
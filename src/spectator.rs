@@ -0,0 +1,419 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use super::board::{Board, Color, Loc, MoveResult};
+use super::game::{self, GameOutcome};
+use super::player::TurnEvent;
+use super::rules::RuleSet;
+use super::serialize;
+
+/// How long a write to a watcher may take before they're dropped, so a
+/// watcher who stops reading can't hold up the game.
+pub static WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Something that watches a game without playing in it.
+///
+/// Spectators are shown everything that happens, but are never asked
+/// for anything. A spectator who returns an error is dropped from the
+/// game; the game itself carries on.
+pub trait Spectator {
+    /// Inform the spectator that a game has started, with the board
+    /// it starts from, the name of each player and the rules it's
+    /// played by.
+    fn game_started(&mut self,
+                    board: &Board,
+                    names: &BTreeMap<Color, String>,
+                    rules: &RuleSet)
+                    -> Result<(), &'static str>;
+
+    /// Show the spectator a turn, or an ejection, as it happens.
+    fn observe_turn(&mut self, event: &TurnEvent) -> Result<(), &'static str>;
+
+    /// Inform the spectator that the game is over, and how it ended.
+    fn game_over(&mut self, outcome: &GameOutcome) -> Result<(), &'static str>;
+}
+
+impl<'a, S: Spectator + ?Sized> Spectator for &'a mut S {
+    fn game_started(&mut self,
+                    board: &Board,
+                    names: &BTreeMap<Color, String>,
+                    rules: &RuleSet)
+                    -> Result<(), &'static str> {
+        (**self).game_started(board, names, rules)
+    }
+
+    fn observe_turn(&mut self, event: &TurnEvent) -> Result<(), &'static str> {
+        (**self).observe_turn(event)
+    }
+
+    fn game_over(&mut self, outcome: &GameOutcome) -> Result<(), &'static str> {
+        (**self).game_over(outcome)
+    }
+}
+
+/// The XML messages describing an event, one per line:
+///
+/// - every roll of a turn, followed by each move made with it, the pawns
+///   each move bopped and the bonus it earned,
+/// - the doubles penalty, if the turn ended with one,
+/// - and the board the turn ended on.
+///
+/// Bops and bonuses aren't part of the event, so the moves are replayed
+/// by `rules` to find them.
+pub fn event_messages(event: &TurnEvent, rules: &RuleSet) -> Vec<String> {
+    let mut messages: Vec<String> = Vec::new();
+
+    match *event {
+        TurnEvent::Played { color, ref rolls, doubles_penalty, ref board } => {
+            for roll in rolls.iter() {
+                messages.push(serialize::xml_roll(&color, &roll.dice));
+
+                let mut current: Board = roll.board.clone();
+                for &m in roll.moves.iter() {
+                    messages.push(serialize::xml_applied_move(&m));
+                    let MoveResult(next, bonus) = match current.handle_move_with(m, rules) {
+                        Ok(result) => result,
+                        Err(e) => {
+                            warn!("Couldn't replay {:?} for spectators: {}", m, e);
+                            break;
+                        }
+                    };
+                    for pawn in game::bopped_pawns(&current, &next, &color).iter() {
+                        messages.push(serialize::xml_bop(pawn));
+                    }
+                    if let Some(amt) = bonus {
+                        messages.push(serialize::xml_bonus(&color, amt));
+                    }
+                    current = next;
+                }
+            }
+
+            if doubles_penalty {
+                messages.push(serialize::xml_penalty(&color));
+            }
+            messages.push(serialize::xml_turn_end(&color, board));
+        }
+        TurnEvent::Ejected { color, ref reason } => {
+            messages.push(serialize::xml_ejected(&color, reason));
+        }
+    }
+
+    messages
+}
+
+/// A spectator that writes the game as XML, one message per line.
+///
+/// The first message is a `<game-start>` with the players and the
+/// starting board, and the last is a `<game-over>` naming the winner.
+pub struct XmlSpectator<W: Write> {
+    writer: W,
+    rules: RuleSet,
+}
+
+impl<W: Write> XmlSpectator<W> {
+    pub fn new(writer: W) -> XmlSpectator<W> {
+        XmlSpectator {
+            writer: writer,
+            rules: RuleSet::standard(),
+        }
+    }
+
+    /// Unwrap the writer, e.g. to read back what was written.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn send(&mut self, msg: &str) -> Result<(), &'static str> {
+        writeln!(self.writer, "{}", msg)
+            .and_then(|_| self.writer.flush())
+            .map_err(|_| "Could not write to spectator")
+    }
+}
+
+impl<W: Write> Spectator for XmlSpectator<W> {
+    fn game_started(&mut self,
+                    board: &Board,
+                    names: &BTreeMap<Color, String>,
+                    rules: &RuleSet)
+                    -> Result<(), &'static str> {
+        self.rules = *rules;
+        self.send(&serialize::xml_game_start(board, names))
+    }
+
+    fn observe_turn(&mut self, event: &TurnEvent) -> Result<(), &'static str> {
+        for msg in event_messages(event, &self.rules).iter() {
+            self.send(msg)?;
+        }
+        Ok(())
+    }
+
+    fn game_over(&mut self, outcome: &GameOutcome) -> Result<(), &'static str> {
+        self.send(&serialize::xml_game_over(outcome.winner))
+    }
+}
+
+/// The watchers of a broadcast, and what a watcher who joins partway
+/// through a game needs to catch up.
+struct Audience {
+    watchers: Vec<TcpStream>,
+    names: BTreeMap<Color, String>,
+    /// The board as of the last turn, or `None` between games.
+    board: Option<Board>,
+    rules: RuleSet,
+}
+
+impl Audience {
+    /// Send a message to every watcher, dropping those who can't be
+    /// reached.
+    fn send(&mut self, msg: &str) -> () {
+        self.watchers.retain(|stream| {
+            let mut writer: &TcpStream = stream;
+            match writeln!(writer, "{}", msg).and_then(|_| writer.flush()) {
+                Ok(()) => true,
+                Err(e) => {
+                    info!("Dropping spectator: {}", e);
+                    false
+                }
+            }
+        });
+    }
+}
+
+/// Streams games to any number of watchers connected over TCP.
+///
+/// Each watcher is sent the same XML messages as an `XmlSpectator`.
+/// Watchers who connect partway through a game are first sent a
+/// `<game-start>` with the board as it stands, then follow along live.
+/// Watchers only ever receive; anything they send is ignored.
+///
+/// Clones share their audience, so a broadcast can be handed to a game
+/// while watchers are still being added to it. A broadcast follows one
+/// game at a time, as its messages don't say which game they're from:
+/// games played one after another can share a broadcast, but games
+/// played at the same time each need their own.
+#[derive(Clone)]
+pub struct Broadcast {
+    audience: Arc<Mutex<Audience>>,
+    pace: Duration,
+}
+
+impl Broadcast {
+    /// A broadcast with nobody watching yet.
+    pub fn new() -> Broadcast {
+        Broadcast {
+            audience: Arc::new(Mutex::new(Audience {
+                                               watchers: Vec::new(),
+                                               names: BTreeMap::new(),
+                                               board: None,
+                                               rules: RuleSet::standard(),
+                                           })),
+            pace: Duration::from_millis(0),
+        }
+    }
+
+    /// Wait this long after every turn, so people can keep up with
+    /// games between automated players.
+    pub fn with_pace(mut self, pace: Duration) -> Broadcast {
+        self.pace = pace;
+        self
+    }
+
+    /// Start accepting watchers on the given address, on a thread of
+    /// its own. Returns the broadcast and the address it listens on.
+    pub fn listen(addr: &str) -> io::Result<(Broadcast, SocketAddr)> {
+        let listener: TcpListener = TcpListener::bind(addr)?;
+        let local_addr: SocketAddr = listener.local_addr()?;
+        let broadcast: Broadcast = Broadcast::new();
+
+        let accepting: Broadcast = broadcast.clone();
+        thread::spawn(move || for stream in listener.incoming() {
+                          match stream {
+                              Ok(stream) => accepting.add_watcher(stream),
+                              Err(e) => warn!("Spectator couldn't connect: {}", e),
+                          }
+                      });
+
+        Ok((broadcast, local_addr))
+    }
+
+    /// Add a watcher, catching them up on the game in progress.
+    pub fn add_watcher(&self, stream: TcpStream) -> () {
+        if let Err(e) = stream.set_write_timeout(Some(WRITE_TIMEOUT)) {
+            warn!("Couldn't set a write timeout for a spectator: {}", e);
+        }
+
+        let mut audience = self.audience.lock().expect("Broadcast poisoned");
+        let mut writer: &TcpStream = &stream;
+        if let Some(ref board) = audience.board {
+            let snapshot: String = serialize::xml_game_start(board, &audience.names);
+            if writeln!(writer, "{}", snapshot).is_err() {
+                return;
+            }
+        }
+        audience.watchers.push(stream);
+        info!("Spectator joined. Now there are {}.", audience.watchers.len());
+    }
+
+    /// The number of watchers still connected.
+    pub fn watchers(&self) -> usize {
+        self.audience
+            .lock()
+            .expect("Broadcast poisoned")
+            .watchers
+            .len()
+    }
+}
+
+impl Spectator for Broadcast {
+    fn game_started(&mut self,
+                    board: &Board,
+                    names: &BTreeMap<Color, String>,
+                    rules: &RuleSet)
+                    -> Result<(), &'static str> {
+        let mut audience = self.audience.lock().expect("Broadcast poisoned");
+        audience.names = names.clone();
+        audience.board = Some(board.clone());
+        audience.rules = *rules;
+        audience.send(&serialize::xml_game_start(board, names));
+        Ok(())
+    }
+
+    fn observe_turn(&mut self, event: &TurnEvent) -> Result<(), &'static str> {
+        {
+            let mut audience = self.audience.lock().expect("Broadcast poisoned");
+            for msg in event_messages(event, &audience.rules).iter() {
+                audience.send(msg);
+            }
+
+            match *event {
+                TurnEvent::Played { ref board, .. } => audience.board = Some(board.clone()),
+                TurnEvent::Ejected { color, .. } => {
+                    // The game sends an ejected player's pawns back to
+                    // the nest, so late joiners see them there too.
+                    if let Some(ref mut board) = audience.board {
+                        board.positions.insert(color, [Loc::Nest; 4]);
+                    }
+                }
+            }
+        }
+
+        if let TurnEvent::Played { .. } = *event {
+            thread::sleep(self.pace);
+        }
+        Ok(())
+    }
+
+    fn game_over(&mut self, outcome: &GameOutcome) -> Result<(), &'static str> {
+        let mut audience = self.audience.lock().expect("Broadcast poisoned");
+        audience.send(&serialize::xml_game_over(outcome.winner));
+        audience.board = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::time::Instant;
+    use autoplayers;
+    use dice::Dice;
+    use game::Game;
+
+    /// A game where Red needs a 1 to bring their last pawn home.
+    fn last_step_game<'a>() -> Game<'a> {
+        let board: Board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 106 }, Loc::Home, Loc::Home, Loc::Home]
+        });
+        let mut game: Game = Game::from_board(board).unwrap();
+        for &clr in [Color::Red, Color::Blue].iter() {
+            let player = autoplayers::move_first_pawn_player(format!("{:?}", clr), clr);
+            game.seat_player(Box::new(player), clr).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    /// Spectators see the start, every roll, move and bonus, the end of
+    /// the turn and the winner, one message per line.
+    fn xml_spectator_sees_the_game() {
+        let mut spectator: XmlSpectator<Vec<u8>> = XmlSpectator::new(Vec::new());
+        {
+            let mut game: Game = last_step_game();
+            game.add_spectator(Box::new(&mut spectator));
+            let outcome = game.play(|_| (Dice { rolls: vec![1, 2] }, false), 10);
+            assert_eq!(outcome.winner, Some(Color::Red));
+        }
+
+        let written: String = String::from_utf8(spectator.into_inner()).unwrap();
+        let tags: Vec<&str> = written
+            .lines()
+            .map(|line| line.split_whitespace().next().unwrap())
+            .collect();
+        assert_eq!(tags,
+                   vec!["<game-start>",
+                        "<roll>",
+                        "<move>",
+                        "<bonus>",
+                        "<turn-end>",
+                        "<game-over>"]);
+
+        let lines: Vec<&str> = written.lines().collect();
+        assert!(lines[0].contains("<player> <color> red </color> <name> Red </name> </player>"));
+        assert!(lines[1].contains("<dice> <die> 1 </die> <die> 2 </die> </dice>"));
+        assert_eq!(lines[3], "<bonus> <color> red </color> <distance> 10 </distance> </bonus>");
+        assert_eq!(lines[5], "<game-over> <winner> red </winner> </game-over>");
+    }
+
+    /// Wait until the broadcast has `n` watchers.
+    fn wait_for_watchers(broadcast: &Broadcast, n: usize) -> () {
+        let start: Instant = Instant::now();
+        while broadcast.watchers() < n {
+            assert!(start.elapsed() < Duration::from_secs(5),
+                    "Watcher never joined");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    /// Watchers follow the game live, and late joiners are caught up
+    /// with the board as it stands.
+    fn broadcast_catches_up_late_joiners() {
+        let (mut broadcast, addr) = Broadcast::listen("127.0.0.1:0").unwrap();
+        let mut early = BufReader::new(TcpStream::connect(addr).unwrap());
+        wait_for_watchers(&broadcast, 1);
+
+        let board: Board = Board::from(map!{
+            Color::Red => [Loc::Spot { index: 106 }, Loc::Home, Loc::Home, Loc::Home]
+        });
+        let names: BTreeMap<Color, String> = map!{ Color::Red => "Red".to_string() };
+        broadcast.game_started(&board, &names, &RuleSet::standard()).unwrap();
+
+        let mut line: String = String::new();
+        early.read_line(&mut line).unwrap();
+        assert!(line.starts_with("<game-start> "));
+        assert!(line.contains(&board.xmlify()));
+
+        let finished: Board = Board::from(map!{ Color::Red => [Loc::Home; 4] });
+        broadcast.observe_turn(&TurnEvent::Played {
+                                    color: Color::Red,
+                                    rolls: Vec::new(),
+                                    doubles_penalty: false,
+                                    board: finished.clone(),
+                                })
+            .unwrap();
+        line.clear();
+        early.read_line(&mut line).unwrap();
+        assert_eq!(line.trim_end(),
+                   serialize::xml_turn_end(&Color::Red, &finished));
+
+        let mut late = BufReader::new(TcpStream::connect(addr).unwrap());
+        wait_for_watchers(&broadcast, 2);
+        line.clear();
+        late.read_line(&mut line).unwrap();
+        assert_eq!(line.trim_end(), serialize::xml_game_start(&finished, &names));
+    }
+}
@@ -0,0 +1,296 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+use super::board::{Board, Color};
+use super::dice::Dice;
+use super::game::{GameOutcome, Move};
+use super::player::{Player, TurnEvent};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// What happens to a player who runs out of time.
+pub enum OnTimeout {
+    /// The player is ejected from the game.
+    Forfeit,
+    /// The player stays in the game, and a legal turn is played for
+    /// them instead of the one they were too slow to choose.
+    DefaultTurn,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+/// How long each player has to choose their moves.
+///
+/// Only `do_move` is timed. A player who takes longer than they have
+/// left is out of time, and any answer they give is ignored. Players in
+/// the same process are only held to this if they run on a thread of
+/// their own; see `TimeControl::player`.
+pub struct TimeControl {
+    /// Time allowed for a single call to `do_move`.
+    pub per_move: Option<Duration>,
+    /// Time allowed for all of a player's calls to `do_move` over the
+    /// whole game. A player who has used it all up isn't asked to move
+    /// again, and is out of time on every roll.
+    pub per_game: Option<Duration>,
+    pub on_timeout: OnTimeout,
+}
+
+impl TimeControl {
+    /// No time limits at all.
+    pub fn unlimited() -> TimeControl {
+        TimeControl {
+            per_move: None,
+            per_game: None,
+            on_timeout: OnTimeout::Forfeit,
+        }
+    }
+
+    /// Whether any time limit applies.
+    pub fn is_limited(&self) -> bool {
+        self.per_move.is_some() || self.per_game.is_some()
+    }
+
+    /// Make a player with `build`. If any time limit applies, the player
+    /// is a `ThreadedPlayer`, so that one that hangs is held to the limit
+    /// instead of holding up the game.
+    pub fn player<F>(&self, build: F) -> Box<Player>
+        where F: FnOnce() -> Box<Player> + Send + 'static
+    {
+        if self.is_limited() {
+            Box::new(ThreadedPlayer::spawn(build))
+        } else {
+            build()
+        }
+    }
+
+    /// How long a player who has already used `used` of their time
+    /// has for their next move, or `None` if they have all the time
+    /// they want.
+    pub fn allowance(&self, used: Duration) -> Option<Duration> {
+        let left: Option<Duration> = self.per_game
+            .map(|limit| limit.checked_sub(used).unwrap_or_default());
+        match (self.per_move, left) {
+            (Some(per_move), Some(left)) => Some(per_move.min(left)),
+            (per_move, left) => per_move.or(left),
+        }
+    }
+}
+
+impl Default for TimeControl {
+    fn default() -> TimeControl {
+        TimeControl::unlimited()
+    }
+}
+
+/// A call to a `ThreadedPlayer`'s player, with somewhere to send the
+/// answer if it has one.
+enum Call {
+    Start(Color, Sender<Result<String, &'static str>>),
+    Move(Board, Dice, Sender<Result<Vec<Move>, &'static str>>),
+    DoublesPenalty(Sender<Result<(), &'static str>>),
+    TimeLimit(Option<Duration>),
+    Observe(TurnEvent),
+    GameOver(GameOutcome),
+}
+
+/// A player that runs on a thread of its own, so that it can be held to
+/// its time limit from outside.
+///
+/// A player in the same process is asked for its moves by a plain
+/// function call, and a player that never returns would hold up the
+/// game forever. Here the game only waits as long as the player's time
+/// limit for their moves, or for them to take a doubles penalty. A
+/// player who doesn't answer in time is out of time, and their answer
+/// is skipped when it does come. A player who never answers is left
+/// running on their thread, while the game goes on without them.
+///
+/// Other turns and the end of the game are passed on to the player
+/// without waiting for them, and any errors are only logged.
+pub struct ThreadedPlayer {
+    calls: Sender<Call>,
+    time_limit: Option<Duration>,
+}
+
+impl ThreadedPlayer {
+    /// Start a thread for the player made by `build`. The player is made
+    /// on its thread and stays there, so it doesn't need to be `Send`.
+    pub fn spawn<F>(build: F) -> ThreadedPlayer
+        where F: FnOnce() -> Box<Player> + Send + 'static
+    {
+        let (calls, received) = mpsc::channel();
+        thread::spawn(move || {
+            let mut player: Box<Player> = build();
+            for call in received.iter() {
+                // Nobody may be waiting for the answer any more, so it's
+                // fine if it can't be sent.
+                match call {
+                    Call::Start(color, answer) => {
+                        answer.send(player.start_game(color)).ok();
+                    }
+                    Call::Move(board, dice, answer) => {
+                        answer.send(player.do_move(board, dice)).ok();
+                    }
+                    Call::DoublesPenalty(answer) => {
+                        answer.send(player.doubles_penalty()).ok();
+                    }
+                    Call::TimeLimit(limit) => player.set_time_limit(limit),
+                    Call::Observe(event) => {
+                        if let Err(e) = player.observe_turn(&event) {
+                            warn!("Player couldn't be shown a turn: {}", e);
+                        }
+                    }
+                    Call::GameOver(outcome) => {
+                        if let Err(e) = player.game_over(&outcome) {
+                            warn!("Player couldn't be told the game is over: {}", e);
+                        }
+                    }
+                }
+            }
+        });
+
+        ThreadedPlayer {
+            calls: calls,
+            time_limit: None,
+        }
+    }
+
+    /// Send a call to the player's thread.
+    fn send(&self, call: Call) -> Result<(), &'static str> {
+        self.calls.send(call).map_err(|_| "Player stopped")
+    }
+
+    /// Wait for the answer to a call, for no longer than `limit`.
+    fn wait<T>(answer: &Receiver<Result<T, &'static str>>,
+               limit: Option<Duration>)
+               -> Result<T, &'static str> {
+        match limit {
+            Some(limit) => {
+                match answer.recv_timeout(limit) {
+                    Ok(result) => result,
+                    Err(RecvTimeoutError::Timeout) => Err("Out of time"),
+                    Err(RecvTimeoutError::Disconnected) => Err("Player stopped"),
+                }
+            }
+            None => answer.recv().unwrap_or(Err("Player stopped")),
+        }
+    }
+}
+
+impl Player for ThreadedPlayer {
+    fn start_game(&mut self, color: Color) -> Result<String, &'static str> {
+        let (answer, received) = mpsc::channel();
+        self.send(Call::Start(color, answer))?;
+        ThreadedPlayer::wait(&received, None)
+    }
+
+    /// A player who is still choosing their last moves only gets to
+    /// these once they're done, but their time starts now regardless.
+    fn do_move(&mut self, board: Board, dice: Dice) -> Result<Vec<Move>, &'static str> {
+        let (answer, received) = mpsc::channel();
+        self.send(Call::Move(board, dice, answer))?;
+        ThreadedPlayer::wait(&received, self.time_limit)
+    }
+
+    fn doubles_penalty(&mut self) -> Result<(), &'static str> {
+        let (answer, received) = mpsc::channel();
+        self.send(Call::DoublesPenalty(answer))?;
+        ThreadedPlayer::wait(&received, self.time_limit)
+    }
+
+    fn set_time_limit(&mut self, limit: Option<Duration>) -> () {
+        self.time_limit = limit;
+        self.send(Call::TimeLimit(limit)).ok();
+    }
+
+    fn observe_turn(&mut self, event: &TurnEvent) -> Result<(), &'static str> {
+        self.send(Call::Observe(event.clone()))
+    }
+
+    fn game_over(&mut self, outcome: &GameOutcome) -> Result<(), &'static str> {
+        self.send(Call::GameOver(outcome.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use board::Pawn;
+    use game::MoveType;
+
+    /// Takes longer to choose each move than the last, answering with
+    /// its count of moves so far, and never answers its `hang`th move.
+    struct Slowing {
+        moves: usize,
+        hang: usize,
+    }
+
+    impl Player for Slowing {
+        fn start_game(&mut self, _color: Color) -> Result<String, &'static str> {
+            Ok("Slowing".to_string())
+        }
+
+        fn do_move(&mut self, _board: Board, _dice: Dice) -> Result<Vec<Move>, &'static str> {
+            self.moves += 1;
+            if self.moves == self.hang {
+                loop {
+                    thread::park();
+                }
+            }
+            thread::sleep(Duration::from_millis(150) * (self.moves as u32 - 1));
+            Ok(vec![Move {
+                        pawn: Pawn::new(self.moves, Color::Red),
+                        m_type: MoveType::EnterPiece,
+                    }])
+        }
+    }
+
+    #[test]
+    /// A player has the shorter of the per-move limit and whatever is
+    /// left of their time for the game.
+    fn allowance_is_the_tighter_limit() {
+        let unlimited: TimeControl = TimeControl::unlimited();
+        assert!(!unlimited.is_limited());
+        assert_eq!(unlimited.allowance(Duration::from_secs(1000)), None);
+
+        let timed: TimeControl = TimeControl {
+            per_move: Some(Duration::from_secs(2)),
+            per_game: Some(Duration::from_secs(10)),
+            ..TimeControl::unlimited()
+        };
+        assert!(timed.is_limited());
+        assert_eq!(timed.allowance(Duration::from_secs(0)),
+                   Some(Duration::from_secs(2)));
+        assert_eq!(timed.allowance(Duration::from_secs(9)),
+                   Some(Duration::from_secs(1)));
+        assert_eq!(timed.allowance(Duration::from_secs(12)),
+                   Some(Duration::from_secs(0)));
+
+        let per_game: TimeControl = TimeControl {
+            per_game: Some(Duration::from_secs(10)),
+            ..TimeControl::unlimited()
+        };
+        assert_eq!(per_game.allowance(Duration::from_secs(4)),
+                   Some(Duration::from_secs(6)));
+    }
+
+    #[test]
+    /// A player on a thread of their own can't take longer than their
+    /// time limit, even if they never answer, and a late answer isn't
+    /// taken for the next one.
+    fn threaded_players_keep_time() {
+        let mut player: ThreadedPlayer =
+            ThreadedPlayer::spawn(|| Box::new(Slowing { moves: 0, hang: 4 }) as Box<Player>);
+        assert_eq!(player.start_game(Color::Red), Ok("Slowing".to_string()));
+        player.set_time_limit(Some(Duration::from_millis(100)));
+
+        let answered = |moves: Result<Vec<Move>, &'static str>| moves.map(|m| m[0].pawn.id);
+        assert_eq!(answered(player.do_move(Board::new(), Dice::new())), Ok(1));
+        assert_eq!(answered(player.do_move(Board::new(), Dice::new())), Err("Out of time"));
+        player.set_time_limit(Some(Duration::from_millis(1000)));
+        assert_eq!(answered(player.do_move(Board::new(), Dice::new())), Ok(3));
+
+        let asked: Instant = Instant::now();
+        assert_eq!(answered(player.do_move(Board::new(), Dice::new())), Err("Out of time"));
+        assert!(asked.elapsed() < Duration::from_secs(5));
+        assert_eq!(player.doubles_penalty(), Err("Out of time"));
+    }
+}
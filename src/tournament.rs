@@ -0,0 +1,571 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+use rand::{SeedableRng, StdRng};
+use super::board::Color;
+use super::constants::*;
+use super::dice::Dice;
+use super::game::{Game, GameOutcome};
+use super::parallel;
+use super::player::Player;
+use super::rules::RuleSet;
+use super::spectator::Spectator;
+use super::timecontrol::TimeControl;
+
+/// Builds a fresh player for a seat, given the color it will play.
+///
+/// Factories are shared between the threads of `Tournament::run_parallel`,
+/// but each player they build stays on the thread playing its game.
+pub type PlayerFactory = Box<Fn(Color) -> Box<Player> + Send + Sync>;
+
+/// Number of turns after which an unfinished game is abandoned.
+pub static DEFAULT_TURN_LIMIT: usize = 2000;
+
+/// A participant in a tournament.
+pub struct Entrant {
+    pub name: String,
+    factory: Arc<PlayerFactory>,
+}
+
+impl Entrant {
+    /// The entrant's name is whatever its players announce
+    /// when a game starts.
+    pub fn new(factory: PlayerFactory) -> Entrant {
+        let name: String = factory(Color::Red)
+            .start_game(Color::Red)
+            .unwrap_or_else(|_| "Unnamed".to_string());
+
+        Entrant {
+            name: name,
+            factory: Arc::new(factory),
+        }
+    }
+}
+
+/// Plays a series of games between a set of players, rotating them
+/// through every assignment of players to colors.
+///
+/// Every game rolls its dice from its own seed, so a tournament with
+/// the same entrants, game count and seed always plays out the same way.
+pub struct Tournament {
+    entrants: Vec<Entrant>,
+    games: usize,
+    seed: usize,
+    turn_limit: usize,
+    rules: RuleSet,
+    time_control: TimeControl,
+}
+
+impl Tournament {
+    pub fn new(games: usize, seed: usize) -> Tournament {
+        Tournament {
+            entrants: Vec::new(),
+            games: games,
+            seed: seed,
+            turn_limit: DEFAULT_TURN_LIMIT,
+            rules: RuleSet::standard(),
+            time_control: TimeControl::unlimited(),
+        }
+    }
+
+    /// Play every game by the given rules instead of the standard ones.
+    pub fn set_rules(&mut self, rules: RuleSet) -> () {
+        self.rules = rules;
+    }
+
+    /// Time every game's players, e.g. to keep a hung player from
+    /// holding up the tournament.
+    pub fn set_time_control(&mut self, time_control: TimeControl) -> () {
+        self.time_control = time_control;
+    }
+
+    /// Add a player to the tournament.
+    pub fn enter(&mut self, factory: PlayerFactory) -> () {
+        self.entrants.push(Entrant::new(factory));
+    }
+
+    /// Every way of seating the entrants, as a list of
+    /// (entrant index, color) pairs.
+    ///
+    /// With four or fewer entrants, everyone plays every game and the
+    /// seatings cover every assignment of entrants to colors. With more,
+    /// each seating picks an ordered selection of four entrants.
+    pub fn seatings(&self) -> Vec<Vec<(usize, Color)>> {
+        let n: usize = self.entrants.len();
+
+        if n <= COLORS.len() {
+            arrangements(COLORS.len(), n)
+                .into_iter()
+                .map(|colors| {
+                         colors
+                             .into_iter()
+                             .enumerate()
+                             .map(|(entrant, c)| (entrant, COLORS[c]))
+                             .collect()
+                     })
+                .collect()
+        } else {
+            arrangements(n, COLORS.len())
+                .into_iter()
+                .map(|entrants| {
+                         entrants
+                             .into_iter()
+                             .enumerate()
+                             .map(|(c, entrant)| (entrant, COLORS[c]))
+                             .collect()
+                     })
+                .collect()
+        }
+    }
+
+    /// Play every game in the tournament and collect the results.
+    pub fn run(&self) -> Report {
+        self.run_parallel(1)
+    }
+
+    /// Play every game in the tournament on a pool of threads.
+    ///
+    /// Games cycle through the seatings, so that every assignment of
+    /// colors gets an equal share of them. Each game's seating and seed
+    /// depend only on its place in the tournament, and outcomes are
+    /// recorded in that order, so the report is the same whatever the
+    /// number of threads.
+    pub fn run_parallel(&self, threads: usize) -> Report {
+        let mut report: Report = Report {
+            standings: self.entrants
+                .iter()
+                .map(|e| Standing::new(e.name.clone()))
+                .collect(),
+            outcomes: Vec::new(),
+        };
+
+        let seatings: Vec<Vec<(usize, Color)>> = self.seatings();
+        if seatings.is_empty() {
+            return report;
+        }
+
+        let outcomes: Vec<GameOutcome> = parallel::run(self.games, threads, |i| {
+            self.play_game(&seatings[i % seatings.len()], self.seed + i)
+        });
+        for (i, outcome) in outcomes.iter().enumerate() {
+            report.record(&seatings[i % seatings.len()], outcome);
+        }
+
+        report
+    }
+
+    /// Play a single game with the given seating, rolling the
+    /// dice from the given seed.
+    pub fn play_game(&self,
+                     seating: &Vec<(usize, Color)>,
+                     seed: usize)
+                     -> GameOutcome {
+        self.play_watched_game(seating, seed, Vec::new())
+    }
+
+    /// Play a single game like `play_game`, showing it to spectators.
+    pub fn play_watched_game(&self,
+                             seating: &Vec<(usize, Color)>,
+                             seed: usize,
+                             spectators: Vec<Box<Spectator>>)
+                             -> GameOutcome {
+        // In a timed tournament, each player runs on a thread of its own,
+        // so one that hangs can't hold up the rest.
+        let players: Vec<(Color, Box<Player>)> = seating
+            .iter()
+            .map(|&(e, clr)| {
+                     let factory: Arc<PlayerFactory> = self.entrants[e].factory.clone();
+                     (clr, self.time_control.player(move || factory(clr)))
+                 })
+            .collect();
+
+        // Only the seated colors have pawns on the board, so games
+        // with fewer than four entrants aren't blocked by empty seats.
+        let colors: Vec<Color> = seating.iter().map(|&(_, clr)| clr).collect();
+        let mut game: Game = Game::with_colors(&colors);
+        game.set_rules(self.rules);
+        game.set_time_control(self.time_control);
+        for (clr, p) in players.into_iter() {
+            game.seat_player(p, clr)
+                .expect("Seating assigns each color once");
+        }
+        for s in spectators.into_iter() {
+            game.add_spectator(s);
+        }
+
+        let rng: RefCell<StdRng> =
+            RefCell::new(SeedableRng::from_seed(&[seed][..]));
+        let roll = |bonus| Dice::roll_with(&mut *rng.borrow_mut(), bonus);
+
+        game.play(roll, self.turn_limit)
+    }
+}
+
+/// Returns every ordered selection of `k` distinct items from `0..n`.
+fn arrangements(n: usize, k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![Vec::new()];
+    }
+
+    let mut result: Vec<Vec<usize>> = Vec::new();
+    for prefix in arrangements(n, k - 1) {
+        for i in 0..n {
+            if !prefix.contains(&i) {
+                let mut next: Vec<usize> = prefix.clone();
+                next.push(i);
+                result.push(next);
+            }
+        }
+    }
+    result
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Accumulated results for one entrant over a tournament.
+pub struct Standing {
+    pub name: String,
+    pub games: usize,
+    pub wins: usize,
+    pub turns: usize,
+    pub bops_made: usize,
+    pub bops_suffered: usize,
+    pub doubles_penalties: usize,
+    pub violations: usize,
+}
+
+impl Standing {
+    fn new(name: String) -> Standing {
+        Standing {
+            name: name,
+            games: 0,
+            wins: 0,
+            turns: 0,
+            bops_made: 0,
+            bops_suffered: 0,
+            doubles_penalties: 0,
+            violations: 0,
+        }
+    }
+
+    /// Fraction of games won.
+    pub fn win_rate(&self) -> f64 {
+        if self.games == 0 {
+            0.0
+        } else {
+            self.wins as f64 / self.games as f64
+        }
+    }
+
+    /// 95% Wilson score interval for the win rate.
+    pub fn win_rate_interval(&self) -> (f64, f64) {
+        if self.games == 0 {
+            return (0.0, 1.0);
+        }
+
+        let z: f64 = 1.96;
+        let n: f64 = self.games as f64;
+        let p: f64 = self.win_rate();
+
+        let denominator: f64 = 1.0 + z * z / n;
+        let center: f64 = (p + z * z / (2.0 * n)) / denominator;
+        let margin: f64 = z * (p * (1.0 - p) / n + z * z / (4.0 * n * n))
+                                  .sqrt() / denominator;
+
+        ((center - margin).max(0.0), (center + margin).min(1.0))
+    }
+
+    /// Average length, in turns, of the games this entrant played.
+    pub fn average_game_length(&self) -> f64 {
+        if self.games == 0 {
+            0.0
+        } else {
+            self.turns as f64 / self.games as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Results of a tournament, one standing per entrant.
+pub struct Report {
+    pub standings: Vec<Standing>,
+    /// The outcome of every game, in the order they were played.
+    pub outcomes: Vec<GameOutcome>,
+}
+
+impl Report {
+    /// Fold a game's outcome into the standings.
+    fn record(&mut self,
+              seating: &Vec<(usize, Color)>,
+              outcome: &GameOutcome)
+              -> () {
+        for &(e, clr) in seating.iter() {
+            let standing: &mut Standing = &mut self.standings[e];
+            standing.games += 1;
+            standing.turns += outcome.turns;
+
+            if outcome.winner == Some(clr) {
+                standing.wins += 1;
+            }
+
+            if let Some(stats) = outcome.stats.get(&clr) {
+                standing.bops_made += stats.bops_made;
+                standing.bops_suffered += stats.bops_suffered;
+                standing.doubles_penalties += stats.doubles_penalties;
+                if stats.violation.is_some() {
+                    standing.violations += 1;
+                }
+            }
+        }
+
+        self.outcomes.push(outcome.clone());
+    }
+
+    /// Human-readable table of the standings.
+    pub fn table(&self) -> String {
+        let mut table: String = format!("{:<20} {:>6} {:>6} {:>7} {:>15} \
+                                         {:>9} {:>6} {:>8} {:>9} {:>10}\n",
+                                        "name",
+                                        "games",
+                                        "wins",
+                                        "win %",
+                                        "95% CI",
+                                        "avg turns",
+                                        "bops",
+                                        "bopped",
+                                        "penalties",
+                                        "violations");
+
+        for s in self.standings.iter() {
+            let (low, high) = s.win_rate_interval();
+            table = table +
+                    &format!("{:<20} {:>6} {:>6} {:>7.1} {:>15} {:>9.1} \
+                              {:>6} {:>8} {:>9} {:>10}\n",
+                             s.name,
+                             s.games,
+                             s.wins,
+                             100.0 * s.win_rate(),
+                             format!("[{:.1}, {:.1}]",
+                                     100.0 * low,
+                                     100.0 * high),
+                             s.average_game_length(),
+                             s.bops_made,
+                             s.bops_suffered,
+                             s.doubles_penalties,
+                             s.violations);
+        }
+
+        table
+    }
+
+    /// The standings as CSV, with a header row.
+    pub fn csv(&self) -> String {
+        let mut csv: String = "name,games,wins,win_rate,win_rate_low,\
+                               win_rate_high,average_turns,bops_made,\
+                               bops_suffered,doubles_penalties,violations\n"
+                .to_string();
+
+        for s in self.standings.iter() {
+            let (low, high) = s.win_rate_interval();
+            csv = csv +
+                  &format!("{},{},{},{:.4},{:.4},{:.4},{:.2},{},{},{},{}\n",
+                           csv_escape(&s.name),
+                           s.games,
+                           s.wins,
+                           s.win_rate(),
+                           low,
+                           high,
+                           s.average_game_length(),
+                           s.bops_made,
+                           s.bops_suffered,
+                           s.doubles_penalties,
+                           s.violations);
+        }
+
+        csv
+    }
+
+    /// The standings as a JSON array of objects.
+    pub fn json(&self) -> String {
+        let rows: Vec<String> = self.standings
+            .iter()
+            .map(|s| {
+                let (low, high) = s.win_rate_interval();
+                format!("{{\"name\": {}, \"games\": {}, \"wins\": {}, \
+                         \"win_rate\": {:.4}, \"win_rate_low\": {:.4}, \
+                         \"win_rate_high\": {:.4}, \"average_turns\": {:.2}, \
+                         \"bops_made\": {}, \"bops_suffered\": {}, \
+                         \"doubles_penalties\": {}, \"violations\": {}}}",
+                        json_string(&s.name),
+                        s.games,
+                        s.wins,
+                        s.win_rate(),
+                        low,
+                        high,
+                        s.average_game_length(),
+                        s.bops_made,
+                        s.bops_suffered,
+                        s.doubles_penalties,
+                        s.violations)
+            })
+            .collect();
+
+        "[".to_string() + &rows.join(", ") + "]"
+    }
+}
+
+/// Quote a CSV field if it contains anything special.
+fn csv_escape(field: &str) -> String {
+    if field.contains(',') || field.contains('"') || field.contains('\n') {
+        "\"".to_string() + &field.replace("\"", "\"\"") + "\""
+    } else {
+        field.to_string()
+    }
+}
+
+/// Encode a string as a JSON string literal.
+fn json_string(s: &str) -> String {
+    let mut encoded: String = "\"".to_string();
+    for c in s.chars() {
+        match c {
+            '"' => encoded.push_str("\\\""),
+            '\\' => encoded.push_str("\\\\"),
+            '\n' => encoded.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                encoded.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c => encoded.push(c),
+        }
+    }
+    encoded.push('"');
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+    use autoplayers::RandomPlayer;
+    use board::Board;
+    use game::Move;
+
+    fn random_tournament(games: usize, seed: usize) -> Tournament {
+        let mut tournament = Tournament::new(games, seed);
+        tournament.enter(Box::new(|clr| {
+            Box::new(RandomPlayer::new("Alice".to_string(), clr, 1)) as
+            Box<Player>
+        }));
+        tournament.enter(Box::new(|clr| {
+            Box::new(RandomPlayer::new("Bob".to_string(), clr, 2)) as
+            Box<Player>
+        }));
+        tournament
+    }
+
+    #[test]
+    /// Each seating uses distinct colors, and there is one per
+    /// ordered choice of colors.
+    fn seatings_cover_color_permutations() {
+        let tournament = random_tournament(0, 0);
+        let seatings = tournament.seatings();
+
+        assert_eq!(seatings.len(), 12);
+        for seating in seatings.iter() {
+            assert_eq!(seating.len(), 2);
+            assert!(seating[0].1 != seating[1].1);
+        }
+    }
+
+    #[test]
+    /// A tournament replays identically from the same seed.
+    fn tournament_is_reproducible() {
+        let first = random_tournament(3, 7).run();
+        let second = random_tournament(3, 7).run();
+
+        assert_eq!(first, second);
+        assert_eq!(first.standings[0].games, 3);
+        assert_eq!(first.standings[1].games, 3);
+    }
+
+    #[test]
+    /// Spreading games over threads doesn't change any result.
+    fn parallel_matches_serial() {
+        let serial = random_tournament(8, 3).run();
+        for threads in 1..5 {
+            assert_eq!(random_tournament(8, 3).run_parallel(threads), serial);
+        }
+    }
+
+    /// Never finishes choosing its moves.
+    struct Hung;
+
+    impl Player for Hung {
+        fn start_game(&mut self, _color: Color) -> Result<String, &'static str> {
+            Ok("Hung".to_string())
+        }
+
+        fn do_move(&mut self, _board: Board, _dice: Dice) -> Result<Vec<Move>, &'static str> {
+            loop {
+                thread::park();
+            }
+        }
+    }
+
+    #[test]
+    /// In a timed tournament, a player that never returns from
+    /// `do_move` runs out of time instead of holding up the games.
+    fn hung_players_run_out_of_time() {
+        let mut tournament = Tournament::new(4, 5);
+        tournament.enter(Box::new(|_| Box::new(Hung) as Box<Player>));
+        tournament.enter(Box::new(|clr| {
+            Box::new(RandomPlayer::new("Bob".to_string(), clr, 2)) as Box<Player>
+        }));
+        tournament.set_time_control(TimeControl {
+                                        per_move: Some(Duration::from_millis(50)),
+                                        ..TimeControl::unlimited()
+                                    });
+
+        for report in vec![tournament.run(), tournament.run_parallel(2)] {
+            assert_eq!(report.standings[0].games, 4);
+            assert_eq!(report.standings[0].violations, 4);
+            assert_eq!(report.standings[0].wins, 0);
+            assert_eq!(report.standings[1].violations, 0);
+        }
+    }
+
+    #[test]
+    /// Wilson intervals contain the observed win rate.
+    fn win_rate_interval_contains_win_rate() {
+        let mut standing = Standing::new("Test".to_string());
+        standing.games = 20;
+        standing.wins = 5;
+
+        let (low, high) = standing.win_rate_interval();
+        assert!(low < 0.25 && 0.25 < high);
+        assert!(low > 0.0 && high < 1.0);
+    }
+
+    #[test]
+    /// Reports come out as CSV, JSON and a table, with names escaped.
+    fn report_formats() {
+        let mut standing = Standing::new("Lloyd, \"the bot\"".to_string());
+        standing.games = 1;
+        standing.wins = 1;
+        let report = Report {
+            standings: vec![standing],
+            outcomes: Vec::new(),
+        };
+
+        assert!(report
+                    .csv()
+                    .contains("\"Lloyd, \"\"the bot\"\"\",1,1,1.0000"));
+        assert!(report
+                    .json()
+                    .starts_with("[{\"name\": \"Lloyd, \\\"the bot\\\"\", \
+                                  \"games\": 1, \"wins\": 1"));
+        assert_eq!(report
+                       .table()
+                       .lines()
+                       .count(),
+                   2);
+    }
+}
@@ -0,0 +1,159 @@
+use super::board::{Color, Loc};
+use super::compactboard;
+use super::dice::Dice;
+
+/// Salt for the side-to-move keys, so they don't overlap pawn keys.
+static SIDE_SALT: u64 = 1 << 40;
+
+/// Salt for the dice keys.
+static DICE_SALT: u64 = 1 << 41;
+
+/// Zobrist key for the `nth` pawn of a color on a location, counting
+/// from 0. Pawns in the nest don't contribute to the hash, since the
+/// rest of the board already determines how many are there. Neither do
+/// pawns off the board, which no real position has.
+///
+/// Keying by how many pawns share a location, rather than by pawn id,
+/// means positions that only differ in which pawn is which hash the same.
+pub fn pawn_key(color: &Color, loc: Loc, nth: usize) -> u64 {
+    let square: u64 = match compactboard::to_square(loc) {
+        Some(sq) if loc != Loc::Nest => sq as u64,
+        _ => return 0,
+    };
+    mix(((*color as u64) * 256 + square) * 4 + nth as u64)
+}
+
+/// Zobrist key for the player to move.
+pub fn side_key(color: &Color) -> u64 {
+    mix(SIDE_SALT + *color as u64)
+}
+
+/// Update a hash when the turn passes from one player to another.
+pub fn pass_turn(hash: u64, from: &Color, to: &Color) -> u64 {
+    hash ^ side_key(from) ^ side_key(to)
+}
+
+/// Hash of the unused rolls in a turn. Like pawns, rolls are keyed by
+/// how many of the same value came before them, so their order
+/// doesn't matter.
+pub fn dice_key(dice: &Dice) -> u64 {
+    let mut hash: u64 = 0;
+    for (i, &roll) in dice.rolls.iter().enumerate() {
+        let nth: usize = dice.rolls[..i]
+            .iter()
+            .filter(|&&r| r == roll)
+            .count();
+        hash ^= mix(DICE_SALT + (roll as u64) * 64 + nth as u64);
+    }
+    hash
+}
+
+/// SplitMix64 finalizer. Deriving keys from their index this way gives
+/// well-spread keys that are the same on every run, without storing
+/// a table of random numbers.
+fn mix(index: u64) -> u64 {
+    let mut z: u64 = index.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+/// A fixed-size table of values keyed by Zobrist hash, for remembering
+/// positions that were already looked at.
+///
+/// Each hash maps to one slot, and a new entry replaces whatever was in
+/// its slot, so the table never grows past its capacity.
+pub struct TranspositionTable<V> {
+    entries: Vec<Option<(u64, V)>>,
+    len: usize,
+}
+
+impl<V> TranspositionTable<V> {
+    /// Create a table with room for `capacity` entries.
+    pub fn new(capacity: usize) -> TranspositionTable<V> {
+        let capacity: usize = if capacity == 0 { 1 } else { capacity };
+        TranspositionTable {
+            entries: (0..capacity).map(|_| None).collect(),
+            len: 0,
+        }
+    }
+
+    fn slot(&self, hash: u64) -> usize {
+        (hash % self.entries.len() as u64) as usize
+    }
+
+    /// Look up the value stored for a hash.
+    pub fn get(&self, hash: u64) -> Option<&V> {
+        match self.entries[self.slot(hash)] {
+            Some((h, ref value)) if h == hash => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Checks whether a value is stored for a hash.
+    pub fn contains(&self, hash: u64) -> bool {
+        self.get(hash).is_some()
+    }
+
+    /// Store a value for a hash, replacing anything in its slot.
+    pub fn insert(&mut self, hash: u64, value: V) -> () {
+        let slot: usize = self.slot(hash);
+        if self.entries[slot].is_none() {
+            self.len += 1;
+        }
+        self.entries[slot] = Some((hash, value));
+    }
+
+    /// Number of entries in the table.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Remove every entry.
+    pub fn clear(&mut self) -> () {
+        for entry in self.entries.iter_mut() {
+            *entry = None;
+        }
+        self.len = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Entries can be looked up by hash, and a colliding hash replaces
+    /// the entry in its slot.
+    fn table_insert_and_replace() {
+        let mut table: TranspositionTable<&str> = TranspositionTable::new(4);
+        assert!(table.is_empty());
+
+        table.insert(1, "one");
+        table.insert(2, "two");
+        assert_eq!(table.get(1), Some(&"one"));
+        assert_eq!(table.len(), 2);
+
+        // 5 shares a slot with 1.
+        table.insert(5, "five");
+        assert_eq!(table.get(1), None);
+        assert_eq!(table.get(5), Some(&"five"));
+        assert_eq!(table.len(), 2);
+
+        table.clear();
+        assert!(!table.contains(5));
+    }
+
+    #[test]
+    /// The order of the rolls doesn't change the dice hash.
+    fn dice_key_ignores_order() {
+        assert_eq!(dice_key(&Dice { rolls: vec![1, 5, 5] }),
+                   dice_key(&Dice { rolls: vec![5, 1, 5] }));
+        assert!(dice_key(&Dice { rolls: vec![1, 5] }) !=
+                dice_key(&Dice { rolls: vec![1, 5, 5] }));
+    }
+}